@@ -0,0 +1,1435 @@
+//! # Octo Assembler
+//!
+//! This module assembles programs written in the [Octo](https://johnearnest.github.io/Octo/)
+//! language into a ROM image that [`Chip8::load_rom_bytes`](crate::Chip8::load_rom_bytes) can run.
+//!
+//! ```text
+//! : main
+//!     clear
+//!     v0 := 0
+//!     loop
+//!         i := digit
+//!         sprite v0 v0 5
+//!         v0 += 6
+//!         if v0 == 30 then return
+//!     again
+//!
+//! : digit
+//!     0xF0 0x90 0xF0 0x90 0xF0
+//! ```
+//!
+//! The supported syntax is:
+//! - Labels (`: name`), constants (`:const name 42`), register aliases (`:alias x v3`) and
+//!   constants computed at assembly time (`:calc name { 2 * x + 1 }`). As in Octo, `:calc`
+//!   expressions are evaluated from right to left, without operator precedence.
+//! - Every CHIP-8, SUPER-CHIP and XO-CHIP instruction in Octo notation (`v0 += v1`, `i := long
+//!   label`, `sprite v0 v1 5`, `scroll-down 4`, ...). A bare label name calls it as a subroutine.
+//! - `if ... then` before a single statement, `if ... begin ... else ... end` blocks, and
+//!   `loop ... while ... again` loops. The `<`, `>`, `<=` and `>=` comparisons overwrite `vF`.
+//! - Macros (`:macro name arg1 arg2 { ... }`), expanded by substituting the argument tokens.
+//! - Data: bare numbers emit bytes, `:byte { expr }` emits a computed byte and `:pointer label`
+//!   emits a 16-bit address.
+//! - `:org address`, `:call address`, `:unpack nibble label` and `:breakpoint name`.
+//!
+//! As in Octo, the program starts with a `jump main`, so a `main` label is required.
+//! Labels can be used before they are defined wherever an address is expected.
+//!
+//! Besides the ROM, the assembled [`Program`] holds a symbol table with the address of every label,
+//! the value of every constant and the `:breakpoint`s, for use by the debugger.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use crate::constants::chip8_constants::{RAM_SIZE, START_ADDRESS};
+
+/// The maximum number of macro expansions, to stop recursive macros.
+const MAX_EXPANSIONS: usize = 100_000;
+
+/// An error raised while assembling a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssemblerError {
+    /// The line of the source where the error was found, starting at 1.
+    pub line: usize,
+
+    /// A description of the error.
+    pub message: String,
+}
+
+impl std::fmt::Display for AssemblerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for AssemblerError {}
+
+/// An assembled program: the ROM image and its symbol table.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Program {
+    /// The ROM image, to be loaded at `START_ADDRESS`.
+    pub rom: Vec<u8>,
+
+    /// The address of every label.
+    pub labels: BTreeMap<String, u16>,
+
+    /// The value of every constant defined by `:const` or `:calc`.
+    pub constants: BTreeMap<String, f64>,
+
+    /// The addresses of the `:breakpoint`s, with their name.
+    pub breakpoints: BTreeMap<u16, String>,
+}
+
+impl Program {
+    /// Returns the name of `address` relative to the closest label at or before it
+    /// (`main`, `main+4`), or `None` if there is no label before it.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::assembler::assemble;
+    ///
+    /// let program = assemble(": main clear : forever jump forever").unwrap();
+    /// assert_eq!(program.get_symbol(0x202).as_deref(), Some("main"));
+    /// assert_eq!(program.get_symbol(0x205).as_deref(), Some("forever+1"));
+    /// assert_eq!(program.get_symbol(0x100), None);
+    /// ```
+    pub fn get_symbol(&self, address: u16) -> Option<String> {
+        let (name, label) = self
+            .labels
+            .iter()
+            .filter(|(_, &label)| label <= address)
+            .max_by_key(|(_, &label)| label)?;
+
+        match address - label {
+            0 => Some(name.clone()),
+            offset => Some(format!("{name}+{offset}")),
+        }
+    }
+}
+
+/// Assembles an Octo program.
+///
+/// # Errors
+///
+/// Returns the first `AssemblerError` found: unknown statement, undefined name, value out of
+/// range, unbalanced block, ...
+///
+/// # Example
+/// ```
+/// use rust_chip8::assembler::assemble;
+///
+/// let program = assemble(
+///     ": main
+///         v0 := 5
+///         loop
+///             v0 -= 1
+///             if v0 != 0 then
+///         again
+///         :breakpoint done
+///         jump main",
+/// )
+/// .unwrap();
+///
+/// assert_eq!(
+///     program.rom,
+///     [0x12, 0x02, 0x60, 0x05, 0x70, 0xFF, 0x30, 0x00, 0x12, 0x04, 0x12, 0x02]
+/// );
+/// assert_eq!(program.labels["main"], 0x202);
+/// assert_eq!(program.breakpoints[&0x20A], "done");
+/// ```
+pub fn assemble(source: &str) -> Result<Program, AssemblerError> {
+    let mut assembler = Assembler {
+        tokens: tokenize(source),
+        line: 1,
+        here: START_ADDRESS,
+        ..Assembler::default()
+    };
+
+    // Jump to `main`, patched once every label is known
+    assembler.emit_address(0x1000, &assembler.synthetic("main"))?;
+    while !assembler.tokens.is_empty() {
+        assembler.statement()?;
+    }
+
+    assembler.finish()
+}
+
+/// A token of the source, with its line.
+#[derive(Debug, Clone)]
+struct Token {
+    /// The text of the token.
+    text: String,
+
+    /// The line of the token, starting at 1.
+    line: usize,
+}
+
+/// A user defined macro.
+#[derive(Debug, Clone)]
+struct Macro {
+    /// The names of the arguments.
+    params: Vec<String>,
+
+    /// The tokens of the body.
+    body: Vec<Token>,
+}
+
+/// How an address is written in the ROM once its label is defined.
+#[derive(Debug, Clone, Copy)]
+enum FixupKind {
+    /// The 12 lowest bits of the instruction at the fixup address.
+    Nnn,
+
+    /// The 2 bytes at the fixup address.
+    Long,
+
+    /// The 4 lowest bits of the byte at the fixup address, set to bits 8-11 of the address.
+    High,
+
+    /// The byte at the fixup address, set to the 8 lowest bits of the address.
+    Low,
+}
+
+/// A reference to a label that was not defined yet.
+#[derive(Debug, Clone)]
+struct Fixup {
+    /// The address of the bytes to patch.
+    address: usize,
+
+    /// How the label address is written.
+    kind: FixupKind,
+
+    /// The token naming the label.
+    label: Token,
+}
+
+/// An open control flow block.
+#[derive(Debug, Clone)]
+enum Block {
+    /// An `if ... begin`, with the address of the jump to its end.
+    Begin(usize),
+
+    /// An `else`, with the address of the jump to its end.
+    Else(usize),
+
+    /// A `loop`, with its start and the addresses of the jumps out of it (`while`).
+    Loop {
+        /// The address of the first instruction of the loop.
+        start: usize,
+
+        /// The addresses of the `while` jumps.
+        breaks: Vec<usize>,
+    },
+}
+
+/// An operand that is either a register or a byte.
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    /// A register index.
+    Register(u8),
+
+    /// An immediate byte.
+    Byte(u8),
+}
+
+/// A condition of `if` and `while`.
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    /// `vx key` (`pressed`) or `vx -key`.
+    Key {
+        /// The register holding the key.
+        x: u8,
+
+        /// Whether the condition is that the key is pressed.
+        pressed: bool,
+    },
+
+    /// `vx == rhs` (`equal`) or `vx != rhs`.
+    Equal {
+        /// The compared register.
+        x: u8,
+
+        /// The compared value.
+        rhs: Operand,
+
+        /// Whether the condition is an equality.
+        equal: bool,
+    },
+
+    /// `vx < rhs`, `vx > rhs`, `vx <= rhs` or `vx >= rhs`, computed in `vF`.
+    Order {
+        /// The compared register.
+        x: u8,
+
+        /// The compared value.
+        rhs: Operand,
+
+        /// The comparison operator.
+        operator: &'static str,
+    },
+}
+
+/// The state of the assembler.
+#[derive(Debug, Default)]
+struct Assembler {
+    /// The tokens left to assemble, macro expansions being pushed at the front.
+    tokens: VecDeque<Token>,
+
+    /// The line of the last token read.
+    line: usize,
+
+    /// The ROM image, starting at `START_ADDRESS`.
+    rom: Vec<u8>,
+
+    /// The address of the next emitted byte.
+    here: usize,
+
+    /// The defined labels.
+    labels: BTreeMap<String, u16>,
+
+    /// The defined constants.
+    constants: BTreeMap<String, f64>,
+
+    /// The register aliases.
+    aliases: HashMap<String, u8>,
+
+    /// The defined macros.
+    macros: HashMap<String, Macro>,
+
+    /// The references to labels not defined yet.
+    fixups: Vec<Fixup>,
+
+    /// The open control flow blocks, the innermost last.
+    blocks: Vec<Block>,
+
+    /// The `:breakpoint`s.
+    breakpoints: BTreeMap<u16, String>,
+
+    /// The number of macro expansions so far.
+    expansions: usize,
+}
+
+/// Splits the source into tokens, removing the comments.
+///
+/// Tokens are separated by whitespace, braces and parentheses being tokens on their own.
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+
+    for (i, line) in source.lines().enumerate() {
+        let code = line.split('#').next().unwrap_or_default();
+        let mut text = String::new();
+
+        for c in code.chars().chain(std::iter::once(' ')) {
+            if c.is_whitespace() || matches!(c, '{' | '}' | '(' | ')') {
+                if !text.is_empty() {
+                    tokens.push_back(Token {
+                        text: std::mem::take(&mut text),
+                        line: i + 1,
+                    });
+                }
+                if !c.is_whitespace() {
+                    tokens.push_back(Token {
+                        text: c.to_string(),
+                        line: i + 1,
+                    });
+                }
+            } else {
+                text.push(c);
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Parses a decimal, hexadecimal (`0x`) or binary (`0b`) number, optionally negative.
+fn parse_number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if digits.starts_with(|c: char| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+/// Parses a register name (`v0` to `vF`, in any case).
+fn parse_register(text: &str) -> Option<u8> {
+    let digit = text.strip_prefix(['v', 'V'])?;
+    if digit.len() != 1 {
+        return None;
+    }
+
+    u8::from_str_radix(digit, 16).ok()
+}
+
+impl Assembler {
+    /// Returns an error at the line of the last token read.
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, AssemblerError> {
+        Err(AssemblerError {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    /// Returns a token that does not come from the source, at the current line.
+    fn synthetic(&self, text: &str) -> Token {
+        Token {
+            text: text.to_string(),
+            line: self.line,
+        }
+    }
+
+    /// Reads the next token.
+    fn next(&mut self) -> Result<Token, AssemblerError> {
+        match self.tokens.pop_front() {
+            Some(token) => {
+                self.line = token.line;
+                Ok(token)
+            }
+            None => self.error("unexpected end of the source"),
+        }
+    }
+
+    /// Returns the text of the next token without reading it.
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    /// Reads the next token, which must be `expected`.
+    fn expect(&mut self, expected: &str) -> Result<(), AssemblerError> {
+        let token = self.next()?;
+        if token.text != expected {
+            return self.error(format!("expected `{expected}`, found `{}`", token.text));
+        }
+
+        Ok(())
+    }
+
+    /// Reads a new name, which must not be a number, a register or an already defined name.
+    fn new_name(&mut self) -> Result<String, AssemblerError> {
+        let name = self.next()?.text;
+
+        let valid = name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-'))
+            && !name.starts_with(|c: char| c.is_ascii_digit() || c == '-');
+        if !valid || parse_register(&name).is_some() {
+            return self.error(format!("`{name}` is not a valid name"));
+        }
+
+        if self.labels.contains_key(&name)
+            || self.constants.contains_key(&name)
+            || self.aliases.contains_key(&name)
+            || self.macros.contains_key(&name)
+        {
+            return self.error(format!("`{name}` is already defined"));
+        }
+
+        Ok(name)
+    }
+
+    /// Reads the tokens up to the `}` matching an already read `{`.
+    fn block_tokens(&mut self) -> Result<Vec<Token>, AssemblerError> {
+        let mut tokens = Vec::new();
+        let mut depth = 0;
+
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => return Ok(tokens),
+                "}" => depth -= 1,
+                _ => {}
+            }
+            tokens.push(token);
+        }
+    }
+
+    /// Returns the register named by `token`, directly or through an alias.
+    fn register_of(&self, token: &Token) -> Option<u8> {
+        parse_register(&token.text).or_else(|| self.aliases.get(&token.text).copied())
+    }
+
+    /// Reads a register.
+    fn register(&mut self) -> Result<u8, AssemblerError> {
+        let token = self.next()?;
+
+        match self.register_of(&token) {
+            Some(register) => Ok(register),
+            None => self.error(format!("expected a register, found `{}`", token.text)),
+        }
+    }
+
+    /// Returns the value of a number, constant or defined label.
+    fn known_value(&self, token: &Token) -> Option<i64> {
+        parse_number(&token.text)
+            .or_else(|| {
+                self.constants
+                    .get(&token.text)
+                    .map(|value| value.floor() as i64)
+            })
+            .or_else(|| self.labels.get(&token.text).map(|&address| address as i64))
+    }
+
+    /// Reads a value that must be known now, between `min` and `max`.
+    fn value(&mut self, min: i64, max: i64) -> Result<i64, AssemblerError> {
+        let token = self.next()?;
+        let Some(value) = self.known_value(&token) else {
+            return self.error(format!("`{}` is not a defined value", token.text));
+        };
+
+        self.check_range(value, min, max)
+    }
+
+    /// Checks that `value` is between `min` and `max`.
+    fn check_range(&self, value: i64, min: i64, max: i64) -> Result<i64, AssemblerError> {
+        if !(min..=max).contains(&value) {
+            return self.error(format!("{value} is out of the range {min} to {max}"));
+        }
+
+        Ok(value)
+    }
+
+    /// Reads a byte, a negative value being written in two's complement.
+    fn byte(&mut self) -> Result<u8, AssemblerError> {
+        Ok(self.value(-128, 255)? as u8)
+    }
+
+    /// Reads a 4-bit value.
+    fn nibble(&mut self) -> Result<u16, AssemblerError> {
+        Ok(self.value(0, 15)? as u16)
+    }
+
+    /// Reads a register or a byte.
+    fn operand(&mut self) -> Result<Operand, AssemblerError> {
+        match self
+            .tokens
+            .front()
+            .and_then(|token| self.register_of(token))
+        {
+            Some(register) => {
+                self.next()?;
+                Ok(Operand::Register(register))
+            }
+            None => Ok(Operand::Byte(self.byte()?)),
+        }
+    }
+
+    /// Emits a byte.
+    fn emit(&mut self, byte: u8) -> Result<(), AssemblerError> {
+        if self.here >= RAM_SIZE {
+            return self.error("the program does not fit in memory");
+        }
+
+        let offset = self.here - START_ADDRESS;
+        if offset >= self.rom.len() {
+            self.rom.resize(offset + 1, 0);
+        }
+        self.rom[offset] = byte;
+        self.here += 1;
+
+        Ok(())
+    }
+
+    /// Emits a big-endian word.
+    fn emit_word(&mut self, word: u16) -> Result<(), AssemblerError> {
+        let [high, low] = word.to_be_bytes();
+        self.emit(high)?;
+        self.emit(low)
+    }
+
+    /// Adds a reference to a label that is not defined yet.
+    fn fixup(&mut self, address: usize, kind: FixupKind, label: &Token) {
+        self.fixups.push(Fixup {
+            address,
+            kind,
+            label: label.clone(),
+        });
+    }
+
+    /// Emits `opcode` with the 12-bit address named by `token` in its lowest bits.
+    fn emit_address(&mut self, opcode: u16, token: &Token) -> Result<(), AssemblerError> {
+        match self.known_value(token) {
+            Some(address) => {
+                let address = self.check_range(address, 0, 0xFFF)?;
+                self.emit_word(opcode | address as u16)
+            }
+            None => {
+                self.fixup(self.here, FixupKind::Nnn, token);
+                self.emit_word(opcode)
+            }
+        }
+    }
+
+    /// Emits the 16-bit address named by `token`.
+    fn emit_long(&mut self, token: &Token) -> Result<(), AssemblerError> {
+        match self.known_value(token) {
+            Some(address) => {
+                let address = self.check_range(address, 0, 0xFFFF)?;
+                self.emit_word(address as u16)
+            }
+            None => {
+                self.fixup(self.here, FixupKind::Long, token);
+                self.emit_word(0)
+            }
+        }
+    }
+
+    /// Emits a jump to be patched later, returning its address.
+    fn emit_placeholder_jump(&mut self) -> Result<usize, AssemblerError> {
+        let address = self.here;
+        self.emit_word(0x1000)?;
+
+        Ok(address)
+    }
+
+    /// Patches the jump at `address` to jump to the current address.
+    fn patch_jump(&mut self, address: usize) -> Result<(), AssemblerError> {
+        if self.here > 0xFFF {
+            return self.error(format!("jump target 0x{:X} is out of range", self.here));
+        }
+
+        let offset = address - START_ADDRESS;
+        self.rom[offset] |= (self.here >> 8) as u8;
+        self.rom[offset + 1] = self.here as u8;
+
+        Ok(())
+    }
+
+    /// Assembles a statement.
+    fn statement(&mut self) -> Result<(), AssemblerError> {
+        let token = self.next()?;
+
+        match token.text.as_str() {
+            ":" => {
+                let name = self.new_name()?;
+                self.labels.insert(name, self.here as u16);
+            }
+            ":const" => {
+                let name = self.new_name()?;
+                let value = self.value(i64::MIN, i64::MAX)?;
+                self.constants.insert(name, value as f64);
+            }
+            ":alias" => {
+                let name = self.new_name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":calc" => {
+                let name = self.new_name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+            }
+            ":byte" => {
+                let value = if self.peek() == Some("{") {
+                    self.next()?;
+                    let value = self.calc()?.floor() as i64;
+                    self.check_range(value, -128, 255)?
+                } else {
+                    self.value(-128, 255)?
+                };
+                self.emit(value as u8)?;
+            }
+            ":pointer" => {
+                let label = self.next()?;
+                self.emit_long(&label)?;
+            }
+            ":org" => {
+                let address = self.value(START_ADDRESS as i64, RAM_SIZE as i64 - 1)?;
+                self.here = address as usize;
+            }
+            ":macro" => {
+                let name = self.new_name()?;
+                let mut params = Vec::new();
+                loop {
+                    let param = self.next()?;
+                    if param.text == "{" {
+                        break;
+                    }
+                    params.push(param.text);
+                }
+                let body = self.block_tokens()?;
+                self.macros.insert(name, Macro { params, body });
+            }
+            ":call" => {
+                let label = self.next()?;
+                self.emit_address(0x2000, &label)?;
+            }
+            ":unpack" => {
+                let nibble = self.nibble()? as u8;
+                let label = self.next()?;
+                match self.known_value(&label) {
+                    Some(address) => {
+                        let address = self.check_range(address, 0, 0xFFF)? as u16;
+                        self.emit_word(0x6000 | (nibble as u16) << 4 | address >> 8)?;
+                        self.emit_word(0x6100 | (address & 0xFF))?;
+                    }
+                    None => {
+                        self.fixup(self.here + 1, FixupKind::High, &label);
+                        self.emit_word(0x6000 | (nibble as u16) << 4)?;
+                        self.fixup(self.here + 1, FixupKind::Low, &label);
+                        self.emit_word(0x6100)?;
+                    }
+                }
+            }
+            ":breakpoint" => {
+                let name = self.next()?.text;
+                self.breakpoints.insert(self.here as u16, name);
+            }
+
+            "clear" => self.emit_word(0x00E0)?,
+            "return" | ";" => self.emit_word(0x00EE)?,
+            "scroll-right" => self.emit_word(0x00FB)?,
+            "scroll-left" => self.emit_word(0x00FC)?,
+            "exit" => self.emit_word(0x00FD)?,
+            "lores" => self.emit_word(0x00FE)?,
+            "hires" => self.emit_word(0x00FF)?,
+            "audio" => self.emit_word(0xF002)?,
+            "scroll-down" => {
+                let n = self.nibble()?;
+                self.emit_word(0x00C0 | n)?;
+            }
+            "scroll-up" => {
+                let n = self.nibble()?;
+                self.emit_word(0x00D0 | n)?;
+            }
+            "plane" => {
+                let n = self.value(0, 3)? as u16;
+                self.emit_word(0xF001 | n << 8)?;
+            }
+            "native" => {
+                let label = self.next()?;
+                self.emit_address(0x0000, &label)?;
+            }
+            "jump" => {
+                let label = self.next()?;
+                self.emit_address(0x1000, &label)?;
+            }
+            "jump0" => {
+                let label = self.next()?;
+                self.emit_address(0xB000, &label)?;
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let n = self.nibble()?;
+                self.emit_word(0xD000 | x << 8 | y << 4 | n)?;
+            }
+            "bcd" => self.register_instruction(0xF033)?,
+            "saveflags" => self.register_instruction(0xF075)?,
+            "loadflags" => self.register_instruction(0xF085)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                let store = token.text == "save";
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    self.emit_word(if store { 0x5002 } else { 0x5003 } | x << 8 | y << 4)?;
+                } else {
+                    self.emit_word(if store { 0xF055 } else { 0xF065 } | x << 8)?;
+                }
+            }
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let opcode = match token.text.as_str() {
+                    "delay" => 0xF015,
+                    "buzzer" => 0xF018,
+                    _ => 0xF03A,
+                };
+                self.register_instruction(opcode)?;
+            }
+            "i" => self.index_statement()?,
+
+            "if" => {
+                let condition = self.condition()?;
+                match self.next()?.text.as_str() {
+                    "then" => self.emit_skip(condition, false)?,
+                    "begin" => {
+                        self.emit_skip(condition, true)?;
+                        let jump = self.emit_placeholder_jump()?;
+                        self.blocks.push(Block::Begin(jump));
+                    }
+                    other => {
+                        return self.error(format!("expected `then` or `begin`, found `{other}`"))
+                    }
+                }
+            }
+            "else" => {
+                let Some(Block::Begin(jump)) = self.blocks.pop() else {
+                    return self.error("`else` without `if ... begin`");
+                };
+                let end = self.emit_placeholder_jump()?;
+                self.patch_jump(jump)?;
+                self.blocks.push(Block::Else(end));
+            }
+            "end" => match self.blocks.pop() {
+                Some(Block::Begin(jump) | Block::Else(jump)) => self.patch_jump(jump)?,
+                _ => return self.error("`end` without `if ... begin`"),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here,
+                breaks: Vec::new(),
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                if !self
+                    .blocks
+                    .iter()
+                    .any(|block| matches!(block, Block::Loop { .. }))
+                {
+                    return self.error("`while` outside of a `loop`");
+                }
+                self.emit_skip(condition, true)?;
+                let jump = self.emit_placeholder_jump()?;
+                if let Some(Block::Loop { breaks, .. }) = self
+                    .blocks
+                    .iter_mut()
+                    .rev()
+                    .find(|block| matches!(block, Block::Loop { .. }))
+                {
+                    breaks.push(jump);
+                }
+            }
+            "again" => {
+                let Some(Block::Loop { start, breaks }) = self.blocks.pop() else {
+                    return self.error("`again` without `loop`");
+                };
+                if start > 0xFFF {
+                    return self.error(format!("loop start 0x{start:X} is out of range"));
+                }
+                self.emit_word(0x1000 | start as u16)?;
+                for jump in breaks {
+                    self.patch_jump(jump)?;
+                }
+            }
+
+            _ => {
+                if let Some(x) = self.register_of(&token) {
+                    self.register_statement(x)?;
+                } else if let Some(definition) = self.macros.get(&token.text).cloned() {
+                    self.expand(&token, definition)?;
+                } else if let Some(value) = self
+                    .known_value(&token)
+                    .filter(|_| !self.labels.contains_key(&token.text))
+                {
+                    let value = self.check_range(value, -128, 255)?;
+                    self.emit(value as u8)?;
+                } else if token.text.starts_with(':') || parse_number(&token.text).is_some() {
+                    return self.error(format!("unknown statement `{}`", token.text));
+                } else {
+                    self.emit_address(0x2000, &token)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Emits `opcode` with a register read from the source as `x`.
+    fn register_instruction(&mut self, opcode: u16) -> Result<(), AssemblerError> {
+        let x = self.register()? as u16;
+        self.emit_word(opcode | x << 8)
+    }
+
+    /// Assembles a statement on the index register (`i := ...`, `i += vx`).
+    fn index_statement(&mut self) -> Result<(), AssemblerError> {
+        let operator = self.next()?;
+
+        match operator.text.as_str() {
+            "+=" => self.register_instruction(0xF01E),
+            ":=" => match self.peek() {
+                Some("long") => {
+                    self.next()?;
+                    let label = self.next()?;
+                    self.emit_word(0xF000)?;
+                    self.emit_long(&label)
+                }
+                Some("hex") => {
+                    self.next()?;
+                    self.register_instruction(0xF029)
+                }
+                Some("bighex") => {
+                    self.next()?;
+                    self.register_instruction(0xF030)
+                }
+                _ => {
+                    let label = self.next()?;
+                    self.emit_address(0xA000, &label)
+                }
+            },
+            other => self.error(format!("expected `:=` or `+=`, found `{other}`")),
+        }
+    }
+
+    /// Assembles a statement on the register `vx` (`vx := ...`, `vx += ...`, ...).
+    fn register_statement(&mut self, x: u8) -> Result<(), AssemblerError> {
+        let x = x as u16;
+        let operator = self.next()?.text;
+
+        // Register to register operations
+        let alu = |operator: &str| match operator {
+            ":=" => Some(0x0),
+            "|=" => Some(0x1),
+            "&=" => Some(0x2),
+            "^=" => Some(0x3),
+            "+=" => Some(0x4),
+            "-=" => Some(0x5),
+            ">>=" => Some(0x6),
+            "=-" => Some(0x7),
+            "<<=" => Some(0xE),
+            _ => None,
+        };
+        let Some(n) = alu(&operator) else {
+            return self.error(format!("unknown operator `{operator}`"));
+        };
+
+        match (operator.as_str(), self.peek()) {
+            (":=", Some("delay")) => {
+                self.next()?;
+                return self.emit_word(0xF007 | x << 8);
+            }
+            (":=", Some("key")) => {
+                self.next()?;
+                return self.emit_word(0xF00A | x << 8);
+            }
+            (":=", Some("random")) => {
+                self.next()?;
+                let nn = self.byte()? as u16;
+                return self.emit_word(0xC000 | x << 8 | nn);
+            }
+            _ => {}
+        }
+
+        match (operator.as_str(), self.operand()?) {
+            (_, Operand::Register(y)) => self.emit_word(0x8000 | x << 8 | (y as u16) << 4 | n),
+            (":=", Operand::Byte(nn)) => self.emit_word(0x6000 | x << 8 | nn as u16),
+            ("+=", Operand::Byte(nn)) => self.emit_word(0x7000 | x << 8 | nn as u16),
+            ("-=", Operand::Byte(nn)) => self.emit_word(0x7000 | x << 8 | nn.wrapping_neg() as u16),
+            (operator, Operand::Byte(_)) => {
+                self.error(format!("`{operator}` needs a register operand"))
+            }
+        }
+    }
+
+    /// Reads the condition of an `if` or a `while`.
+    fn condition(&mut self) -> Result<Condition, AssemblerError> {
+        let x = self.register()?;
+        let operator = self.next()?.text;
+
+        match operator.as_str() {
+            "key" => Ok(Condition::Key { x, pressed: true }),
+            "-key" => Ok(Condition::Key { x, pressed: false }),
+            "==" | "!=" => Ok(Condition::Equal {
+                x,
+                rhs: self.operand()?,
+                equal: operator == "==",
+            }),
+            "<" | ">" | "<=" | ">=" => Ok(Condition::Order {
+                x,
+                rhs: self.operand()?,
+                operator: match operator.as_str() {
+                    "<" => "<",
+                    ">" => ">",
+                    "<=" => "<=",
+                    _ => ">=",
+                },
+            }),
+            other => self.error(format!("unknown comparison `{other}`")),
+        }
+    }
+
+    /// Emits the instructions skipping the next one when `condition` is `skip_when`.
+    fn emit_skip(&mut self, condition: Condition, skip_when: bool) -> Result<(), AssemblerError> {
+        match condition {
+            Condition::Key { x, pressed } => {
+                let opcode = if pressed == skip_when { 0xE09E } else { 0xE0A1 };
+                self.emit_word(opcode | (x as u16) << 8)
+            }
+            Condition::Equal { x, rhs, equal } => {
+                let x = (x as u16) << 8;
+                match (rhs, equal == skip_when) {
+                    (Operand::Byte(nn), true) => self.emit_word(0x3000 | x | nn as u16),
+                    (Operand::Byte(nn), false) => self.emit_word(0x4000 | x | nn as u16),
+                    (Operand::Register(y), true) => self.emit_word(0x5000 | x | (y as u16) << 4),
+                    (Operand::Register(y), false) => self.emit_word(0x9000 | x | (y as u16) << 4),
+                }
+            }
+            Condition::Order { x, rhs, operator } => {
+                let x = x as u16;
+
+                // vF = 1 if x >= rhs (for `<` and `>=`) or rhs >= x (for `>` and `<=`)
+                let x_first = matches!(operator, "<" | ">=");
+                match (rhs, x_first) {
+                    (Operand::Register(y), true) => {
+                        self.emit_word(0x8F00 | x << 4)?;
+                        self.emit_word(0x8F05 | (y as u16) << 4)?;
+                    }
+                    (Operand::Register(y), false) => {
+                        self.emit_word(0x8F00 | (y as u16) << 4)?;
+                        self.emit_word(0x8F05 | x << 4)?;
+                    }
+                    (Operand::Byte(nn), true) => {
+                        self.emit_word(0x6F00 | nn as u16)?;
+                        self.emit_word(0x8F07 | x << 4)?;
+                    }
+                    (Operand::Byte(nn), false) => {
+                        self.emit_word(0x6F00 | nn as u16)?;
+                        self.emit_word(0x8F05 | x << 4)?;
+                    }
+                }
+
+                // The condition holds when vF == 0 for `<` and `>`, vF == 1 for `<=` and `>=`
+                let true_flag = matches!(operator, "<=" | ">=") as u16;
+                let opcode = if skip_when { 0x3F00 } else { 0x4F00 };
+                self.emit_word(opcode | true_flag)
+            }
+        }
+    }
+
+    /// Expands a macro invocation, reading its arguments.
+    fn expand(&mut self, name: &Token, definition: Macro) -> Result<(), AssemblerError> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return self.error(format!(
+                "too many expansions of `{}`, is it recursive?",
+                name.text
+            ));
+        }
+
+        let mut arguments = HashMap::new();
+        for param in &definition.params {
+            arguments.insert(param.clone(), self.next()?.text);
+        }
+
+        for token in definition.body.into_iter().rev() {
+            self.tokens.push_front(Token {
+                text: arguments.get(&token.text).cloned().unwrap_or(token.text),
+                line: name.line,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Evaluates a `:calc` expression, the `{` being already read.
+    fn calc(&mut self) -> Result<f64, AssemblerError> {
+        let tokens = self.block_tokens()?;
+        let mut position = 0;
+        let value = self.calc_expression(&tokens, &mut position)?;
+
+        match tokens.get(position) {
+            Some(token) => self.error(format!("unexpected `{}` in expression", token.text)),
+            None => Ok(value),
+        }
+    }
+
+    /// Evaluates `term [operator expression]`, from right to left.
+    fn calc_expression(
+        &self,
+        tokens: &[Token],
+        position: &mut usize,
+    ) -> Result<f64, AssemblerError> {
+        let left = self.calc_term(tokens, position)?;
+        let Some(operator) = tokens.get(*position).map(|token| token.text.as_str()) else {
+            return Ok(left);
+        };
+        if operator == ")" {
+            return Ok(left);
+        }
+        *position += 1;
+
+        let right = self.calc_expression(tokens, position)?;
+        let (a, b) = (left as i64, right as i64);
+        let boolean = |value: bool| value as i64 as f64;
+        Ok(match operator {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" if right == 0.0 => return self.error("division by zero"),
+            "/" => left / right,
+            "%" if b == 0 => return self.error("division by zero"),
+            "%" => (a % b) as f64,
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.checked_shl(b as u32).unwrap_or(0) as f64,
+            ">>" => a.checked_shr(b as u32).unwrap_or(0) as f64,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => boolean(left < right),
+            ">" => boolean(left > right),
+            "<=" => boolean(left <= right),
+            ">=" => boolean(left >= right),
+            "==" => boolean(left == right),
+            "!=" => boolean(left != right),
+            other => return self.error(format!("unknown operator `{other}`")),
+        })
+    }
+
+    /// Evaluates a number, a name, `HERE`, a parenthesized expression or a unary operation.
+    fn calc_term(&self, tokens: &[Token], position: &mut usize) -> Result<f64, AssemblerError> {
+        let Some(token) = tokens.get(*position) else {
+            return self.error("unexpected end of expression");
+        };
+        *position += 1;
+
+        let unary = |operator: &str, value: f64| -> Option<f64> {
+            Some(match operator {
+                "-" => -value,
+                "~" => !(value as i64) as f64,
+                "!" => (value == 0.0) as i64 as f64,
+                "abs" => value.abs(),
+                "sign" => value.signum(),
+                "floor" => value.floor(),
+                "ceil" => value.ceil(),
+                "sqrt" => value.sqrt(),
+                "sin" => value.sin(),
+                "cos" => value.cos(),
+                "tan" => value.tan(),
+                "exp" => value.exp(),
+                "log" => value.ln(),
+                _ => return None,
+            })
+        };
+
+        match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression(tokens, position)?;
+                match tokens.get(*position) {
+                    Some(token) if token.text == ")" => {
+                        *position += 1;
+                        Ok(value)
+                    }
+                    _ => self.error("missing `)` in expression"),
+                }
+            }
+            "HERE" => Ok(self.here as f64),
+            text => {
+                if let Some(value) = parse_number(text) {
+                    return Ok(value as f64);
+                }
+                if let Some(&value) = self.constants.get(text) {
+                    return Ok(value);
+                }
+                if let Some(&address) = self.labels.get(text) {
+                    return Ok(address as f64);
+                }
+                if unary(text, 0.0).is_some() {
+                    let value = self.calc_term(tokens, position)?;
+                    return Ok(unary(text, value).unwrap_or(value));
+                }
+
+                self.error(format!("`{text}` is not a defined value"))
+            }
+        }
+    }
+
+    /// Resolves the references to labels and returns the program.
+    fn finish(mut self) -> Result<Program, AssemblerError> {
+        if let Some(block) = self.blocks.last() {
+            let name = match block {
+                Block::Loop { .. } => "`loop` without `again`",
+                _ => "`if ... begin` without `end`",
+            };
+            return self.error(name);
+        }
+
+        if !self.labels.contains_key("main") {
+            return self.error("the program has no `main` label");
+        }
+
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.line = fixup.label.line;
+            let Some(&address) = self.labels.get(&fixup.label.text) else {
+                return self.error(format!("`{}` is not defined", fixup.label.text));
+            };
+
+            let offset = fixup.address - START_ADDRESS;
+            match fixup.kind {
+                FixupKind::Nnn | FixupKind::High if address > 0xFFF => {
+                    return self.error(format!(
+                        "`{}` (0x{address:X}) does not fit in 12 bits, use `i := long`",
+                        fixup.label.text
+                    ));
+                }
+                FixupKind::Nnn => {
+                    self.rom[offset] |= (address >> 8) as u8;
+                    self.rom[offset + 1] = address as u8;
+                }
+                FixupKind::Long => {
+                    self.rom[offset..offset + 2].copy_from_slice(&address.to_be_bytes())
+                }
+                FixupKind::High => self.rom[offset] |= (address >> 8) as u8,
+                FixupKind::Low => self.rom[offset] = address as u8,
+            }
+        }
+
+        Ok(Program {
+            rom: self.rom,
+            labels: self.labels,
+            constants: self.constants,
+            breakpoints: self.breakpoints,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble, AssemblerError};
+    use crate::chip8::{Chip8Builder, StepOutcome};
+
+    /// Assembles `: main` followed by `source`, returning the bytes after the initial jump.
+    fn main_body(source: &str) -> Vec<u8> {
+        let program = assemble(&format!(": main {source}")).unwrap();
+        assert_eq!(program.rom[..2], [0x12, 0x02]);
+
+        program.rom[2..].to_vec()
+    }
+
+    /// Assembles a program that must fail.
+    fn error(source: &str) -> AssemblerError {
+        assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn if_then_skips_the_statement_when_the_condition_is_false() {
+        assert_eq!(
+            main_body("if v1 == 5 then v2 := 1"),
+            [0x41, 0x05, 0x62, 0x01]
+        );
+        assert_eq!(main_body("if v1 != 5 then"), [0x31, 0x05]);
+        assert_eq!(main_body("if v1 == v2 then"), [0x91, 0x20]);
+        assert_eq!(main_body("if v1 != v2 then"), [0x51, 0x20]);
+        assert_eq!(main_body("if v1 key then"), [0xE1, 0xA1]);
+        assert_eq!(main_body("if v1 -key then"), [0xE1, 0x9E]);
+    }
+
+    #[test]
+    fn if_begin_else_end() {
+        assert_eq!(
+            main_body("if v1 == 5 begin v2 := 1 else v2 := 2 end"),
+            [
+                0x31, 0x05, // 0x202: skip the jump to the else branch if v1 == 5
+                0x12, 0x0A, // 0x204: jump to the else branch
+                0x62, 0x01, // 0x206
+                0x12, 0x0C, // 0x208: jump over the else branch
+                0x62, 0x02, // 0x20A
+            ]
+        );
+        assert_eq!(
+            main_body("if v1 key begin v2 := 1 end"),
+            [0xE1, 0x9E, 0x12, 0x08, 0x62, 0x01]
+        );
+    }
+
+    #[test]
+    fn loop_while_again() {
+        assert_eq!(
+            main_body("loop v1 += 1 while v1 != 3 again"),
+            [
+                0x71, 0x01, // 0x202
+                0x41, 0x03, // 0x204: skip the jump out if v1 != 3
+                0x12, 0x0A, // 0x206: jump out
+                0x12, 0x02, // 0x208
+            ]
+        );
+    }
+
+    #[test]
+    fn comparisons_are_lowered_through_vf() {
+        assert_eq!(
+            main_body("if v1 < 5 then"),
+            [0x6F, 0x05, 0x8F, 0x17, 0x4F, 0x00]
+        );
+        assert_eq!(
+            main_body("if v1 > v2 then"),
+            [0x8F, 0x20, 0x8F, 0x15, 0x4F, 0x00]
+        );
+        assert_eq!(
+            main_body("if v1 <= 5 then"),
+            [0x6F, 0x05, 0x8F, 0x15, 0x4F, 0x01]
+        );
+        assert_eq!(
+            main_body("if v1 >= v2 then"),
+            [0x8F, 0x10, 0x8F, 0x25, 0x4F, 0x01]
+        );
+    }
+
+    #[test]
+    fn comparisons_hold_when_run() {
+        let compare = [
+            ("<", u8::lt as fn(&u8, &u8) -> bool),
+            (">", u8::gt),
+            ("<=", u8::le),
+            (">=", u8::ge),
+        ];
+        for (operator, expected) in compare {
+            for rhs in ["v2", "5"] {
+                let source = format!(": main v3 := 0 if v1 {operator} {rhs} then v3 := 1 exit");
+                let rom = assemble(&source).unwrap().rom;
+
+                for (a, b) in [(3, 5), (5, 5), (7, 5)] {
+                    let mut chip8 = Chip8Builder::new()
+                        .register(0x1, a)
+                        .register(0x2, b)
+                        .memory(0x200, &rom)
+                        .build();
+                    while chip8.step().unwrap() != StepOutcome::Halted {}
+
+                    assert_eq!(
+                        chip8.get_registers()[0x3] == 1,
+                        expected(&a, &b),
+                        "{a} {operator} {b} ({rhs})"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forward_labels_are_fixed_up() {
+        let program = assemble(
+            ": main
+                jump finish
+                i := data
+                sub
+                :pointer data
+                i := long data
+            : finish
+                exit
+            : sub
+                return
+            : data
+                0xAB",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.rom,
+            [
+                0x12, 0x02, // jump main
+                0x12, 0x0E, // 0x202: jump finish
+                0xA2, 0x12, // 0x204: i := data
+                0x22, 0x10, // 0x206: sub
+                0x02, 0x12, // 0x208: :pointer data
+                0xF0, 0x00, 0x02, 0x12, // 0x20A: i := long data
+                0x00, 0xFD, // 0x20E: finish
+                0x00, 0xEE, // 0x210: sub
+                0xAB, // 0x212: data
+            ]
+        );
+        assert_eq!(program.labels["data"], 0x212);
+    }
+
+    #[test]
+    fn unpack() {
+        let program = assemble(
+            ": data 0x42
+            : main
+                :unpack 0xB data
+                :unpack 1 later
+            : later",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.rom,
+            [
+                0x12, 0x03, // jump main
+                0x42, // 0x202: data
+                0x60, 0xB2, 0x61, 0x02, // 0x203: v0 := 0xB2, v1 := 0x02
+                0x60, 0x12, 0x61, 0x0B, // 0x207: v0 := 0x12, v1 := 0x0B
+            ]
+        );
+    }
+
+    #[test]
+    fn macros_substitute_their_arguments() {
+        let program = assemble(
+            ":macro set-both a b { v0 := a v1 := b }
+            : main
+                set-both 1 2
+                set-both v2 3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            program.rom[2..],
+            [0x60, 0x01, 0x61, 0x02, 0x80, 0x20, 0x61, 0x03]
+        );
+    }
+
+    #[test]
+    fn calc_is_evaluated_from_right_to_left() {
+        let program = assemble(
+            ":calc a { 2 * 3 + 1 }
+            :calc b { ( 2 * 3 ) + 1 }
+            :calc c { 10 - 4 - 3 }
+            :calc d { a + b }
+            : main
+                :byte { d * 2 }",
+        )
+        .unwrap();
+
+        assert_eq!(program.constants["a"], 8.0);
+        assert_eq!(program.constants["b"], 7.0);
+        assert_eq!(program.constants["c"], 9.0);
+        assert_eq!(program.constants["d"], 15.0);
+        assert_eq!(program.rom[2..], [30]);
+    }
+
+    #[test]
+    fn undefined_label() {
+        let err = error(": main\n    clear\n    jump nowhere");
+
+        assert_eq!(err.line, 3);
+        assert_eq!(err.message, "`nowhere` is not defined");
+    }
+
+    #[test]
+    fn immediate_out_of_range() {
+        assert_eq!(
+            error(": main v0 := 256").message,
+            "256 is out of the range -128 to 255"
+        );
+        assert_eq!(
+            error(": main sprite v0 v1 16").message,
+            "16 is out of the range 0 to 15"
+        );
+    }
+
+    #[test]
+    fn unterminated_blocks() {
+        assert_eq!(error(": main loop clear").message, "`loop` without `again`");
+        assert_eq!(
+            error(": main if v0 == 1 begin clear").message,
+            "`if ... begin` without `end`"
+        );
+        assert_eq!(
+            error(": main :calc x { 1 + 2").message,
+            "unexpected end of the source"
+        );
+        assert_eq!(
+            error(": main else").message,
+            "`else` without `if ... begin`"
+        );
+    }
+
+    #[test]
+    fn missing_main() {
+        assert_eq!(
+            error(": start clear").message,
+            "the program has no `main` label"
+        );
+    }
+}
@@ -0,0 +1,415 @@
+//! # Audio Playback
+//!
+//! This module plays the CHIP-8 sound through the `rodio` crate.
+//! It is kept outside of the emulator core so that a `Chip8` can be used without an audio device:
+//! the frontend calls [`Beeper::update`] once per frame and the beeper reads the sound state back.
+//!
+//! All the sound goes through a single endless [`SynthSource`], which is silent while the sound
+//! timer is inactive, so the sound starts and stops exactly with it. While active, it plays:
+//! - the XO-CHIP audio pattern at the rate given by the pitch register, if the program loaded one;
+//! - otherwise the sound file given in [`AudioSettings::sample`], if any;
+//! - otherwise a tone generated from the [`AudioSettings`].
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, PlayError, Sink, Source, StreamError};
+
+use crate::constants::chip8_constants;
+use crate::Chip8;
+
+/// The sample rate of the synthesized audio stream.
+const SAMPLE_RATE: u32 = 44100;
+
+/// An error raised while opening the audio output.
+#[derive(Debug)]
+pub enum AudioError {
+    /// No output stream could be opened on the default device.
+    Stream(StreamError),
+
+    /// The sink could not be attached to the output stream.
+    Play(PlayError),
+
+    /// The sound file could not be read.
+    Sample(std::io::Error),
+
+    /// The sound file could not be decoded.
+    Decode(DecoderError),
+}
+
+impl std::fmt::Display for AudioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stream(err) => write!(f, "failed to create output stream: {err}"),
+            Self::Play(err) => write!(f, "failed to create sound sink: {err}"),
+            Self::Sample(err) => write!(f, "failed to open audio file: {err}"),
+            Self::Decode(err) => write!(f, "failed to decode audio file: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// The shape of the generated beep tone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    /// A square wave, the classic buzzer sound.
+    #[default]
+    Square,
+
+    /// A triangle wave.
+    Triangle,
+
+    /// A sawtooth wave.
+    Sawtooth,
+
+    /// A sine wave.
+    Sine,
+}
+
+impl Waveform {
+    /// All the waveforms.
+    pub const ALL: [Self; 4] = [Self::Square, Self::Triangle, Self::Sawtooth, Self::Sine];
+
+    /// Returns the name of the waveform, as accepted by `from_str`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Square => "square",
+            Self::Triangle => "triangle",
+            Self::Sawtooth => "sawtooth",
+            Self::Sine => "sine",
+        }
+    }
+
+    /// Returns the value of the waveform (from -1.0 to 1.0) at `phase` (from 0.0 to 1.0).
+    fn sample(self, phase: f32) -> f32 {
+        match self {
+            Self::Square => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Self::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Self::Sawtooth => 2.0 * phase - 1.0,
+            Self::Sine => (std::f32::consts::TAU * phase).sin(),
+        }
+    }
+}
+
+impl std::fmt::Display for Waveform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing an unknown waveform name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownWaveform(pub String);
+
+impl std::fmt::Display for UnknownWaveform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown waveform `{}`, expected one of: square, triangle, sawtooth, sine",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownWaveform {}
+
+impl std::str::FromStr for Waveform {
+    type Err = UnknownWaveform;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|waveform| waveform.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownWaveform(name.to_string()))
+    }
+}
+
+/// The settings of the sound output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSettings {
+    /// The frequency of the beep tone in Hz.
+    pub frequency: f32,
+
+    /// The output volume, from 0.0 (muted) to 1.0.
+    pub volume: f32,
+
+    /// The shape of the beep tone.
+    pub waveform: Waveform,
+
+    /// A sound file played in a loop instead of the beep tone (e.g. `assets/audio/orb.mp3`).
+    pub sample: Option<PathBuf>,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            volume: 0.25,
+            waveform: Waveform::default(),
+            sample: None,
+        }
+    }
+}
+
+/// A decoded sound file.
+#[derive(Debug)]
+struct SampleData {
+    /// The interleaved samples.
+    samples: Vec<f32>,
+
+    /// The number of channels of the samples.
+    channels: u16,
+
+    /// The sample rate of the samples.
+    rate: u32,
+}
+
+impl SampleData {
+    /// Decodes the sound file at `path`.
+    fn load(path: &Path) -> Result<Self, AudioError> {
+        let file = std::fs::File::open(path).map_err(AudioError::Sample)?;
+        let decoder = Decoder::new(std::io::BufReader::new(file)).map_err(AudioError::Decode)?;
+
+        let (channels, rate) = (decoder.channels().max(1), decoder.sample_rate());
+
+        Ok(Self {
+            samples: decoder.convert_samples().collect(),
+            channels,
+            rate,
+        })
+    }
+
+    /// Returns the number of frames (samples per channel).
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+}
+
+/// What the [`SynthSource`] is currently playing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Voice {
+    /// The sound timer is inactive.
+    Silent,
+
+    /// The generated beep tone.
+    Tone,
+
+    /// The XO-CHIP audio pattern, played at `rate` samples per second.
+    Pattern {
+        /// The 128 1-bit samples to play in a loop.
+        pattern: [u8; chip8_constants::AUDIO_PATTERN_SIZE],
+
+        /// The playback rate of the pattern in samples per second.
+        rate: f32,
+    },
+
+    /// The sound file.
+    Sample,
+}
+
+/// An endless `rodio` source synthesizing the CHIP-8 sound.
+///
+/// The current [`Voice`] is selected by the [`Beeper`], the source restarts from the beginning
+/// of the waveform, pattern or sound file every time it starts playing.
+pub struct SynthSource {
+    /// The voice to play, updated by the [`Beeper`].
+    voice: Arc<Mutex<Voice>>,
+
+    /// The voice played by the previous sample.
+    previous: Voice,
+
+    /// The current position: phase of the tone, or index in the pattern or in the sound file.
+    position: f32,
+
+    /// The settings of the tone.
+    settings: AudioSettings,
+
+    /// The decoded sound file, if any.
+    sample: Option<SampleData>,
+}
+
+impl std::fmt::Debug for SynthSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SynthSource")
+            .field("previous", &self.previous)
+            .field("position", &self.position)
+            .field("settings", &self.settings)
+            .field("sample", &"[...]")
+            .finish()
+    }
+}
+
+impl Iterator for SynthSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let voice = *self.voice.lock().unwrap();
+        if std::mem::discriminant(&voice) != std::mem::discriminant(&self.previous) {
+            self.position = 0.0;
+        }
+        self.previous = voice;
+
+        let sample = match (voice, &self.sample) {
+            (Voice::Silent, _) => 0.0,
+            (Voice::Tone, _) | (Voice::Sample, None) => {
+                let sample = self.settings.waveform.sample(self.position);
+                self.position =
+                    (self.position + self.settings.frequency / SAMPLE_RATE as f32) % 1.0;
+
+                sample
+            }
+            (Voice::Pattern { pattern, rate }, _) => {
+                let bits = (chip8_constants::AUDIO_PATTERN_SIZE * 8) as f32;
+                let bit = self.position as usize;
+                self.position = (self.position + rate / SAMPLE_RATE as f32) % bits;
+
+                if pattern[bit / 8] & (0x80 >> (bit % 8)) != 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            (Voice::Sample, Some(data)) => {
+                let frames = data.frames().max(1) as f32;
+                let frame = self.position as usize;
+                self.position = (self.position + data.rate as f32 / SAMPLE_RATE as f32) % frames;
+
+                data.samples
+                    .get(frame * data.channels as usize)
+                    .copied()
+                    .unwrap_or(0.0)
+            }
+        };
+
+        Some(sample * self.settings.volume)
+    }
+}
+
+impl Source for SynthSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Plays the CHIP-8 sound on the default audio device.
+pub struct Beeper {
+    /// Audio sink, always playing a [`SynthSource`].
+    _sink: Sink,
+
+    /// The voice read by the [`SynthSource`].
+    voice: Arc<Mutex<Voice>>,
+
+    /// Whether a sound file is used instead of the beep tone.
+    has_sample: bool,
+
+    /// Audio output stream (kept to prevent garbage collection).
+    _stream: OutputStream,
+
+    /// Handle to the output stream.
+    _handle: OutputStreamHandle,
+}
+
+impl std::fmt::Debug for Beeper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Beeper")
+            .field("sink", &"[...]")
+            .field("voice", &self.voice)
+            .field("has_sample", &self.has_sample)
+            .field("stream", &"[...]")
+            .finish()
+    }
+}
+
+impl Beeper {
+    /// Opens the default audio device.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The settings of the tone, and the optional sound file to play instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`AudioError`] if no output stream or sink can be created,
+    /// or if the sound file cannot be loaded.
+    pub fn new(settings: &AudioSettings) -> Result<Self, AudioError> {
+        let sample = settings
+            .sample
+            .as_deref()
+            .map(SampleData::load)
+            .transpose()?;
+        let has_sample = sample.is_some();
+
+        let (stream, handle) = OutputStream::try_default().map_err(AudioError::Stream)?;
+        let sink = Sink::try_new(&handle).map_err(AudioError::Play)?;
+
+        let voice = Arc::new(Mutex::new(Voice::Silent));
+        sink.append(SynthSource {
+            voice: Arc::clone(&voice),
+            previous: Voice::Silent,
+            position: 0.0,
+            settings: settings.clone(),
+            sample,
+        });
+        sink.play();
+
+        Ok(Self {
+            _sink: sink,
+            voice,
+            has_sample,
+            _stream: stream,
+            _handle: handle,
+        })
+    }
+
+    /// Updates the sound output from the state of the machine.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The machine, whose sound timer, audio pattern and pitch are read.
+    ///
+    /// This should be called once per frame.
+    pub fn update(&self, chip8: &Chip8) {
+        let voice = if chip8.get_sound_timer() == 0 {
+            Voice::Silent
+        } else if let Some(pattern) = chip8.get_audio_pattern() {
+            Voice::Pattern {
+                pattern: *pattern,
+                rate: chip8.get_playback_rate(),
+            }
+        } else if self.has_sample {
+            Voice::Sample
+        } else {
+            Voice::Tone
+        };
+
+        *self.voice.lock().unwrap() = voice;
+    }
+
+    /// Stops the sound, until the next [`update`](Self::update).
+    ///
+    /// This should be called when no frame runs (pause, crash, ...) as the sound timer is frozen.
+    pub fn silence(&self) {
+        *self.voice.lock().unwrap() = Voice::Silent;
+    }
+}
@@ -0,0 +1,93 @@
+#![warn(missing_debug_implementations, rust_2018_idioms, missing_docs)]
+
+//! # CHIP-8 Assembler
+//!
+//! Assembles an Octo source file into a `.ch8` ROM (see the `rust_chip8::assembler` module for
+//! the supported syntax).
+//!
+//! ## Usage
+//! ```sh
+//! cargo run --bin chip8-asm -- game.8o -o game.ch8 --symbols
+//! ```
+//! Without `-o`, the ROM is written next to the source with the `.ch8` extension.
+
+use std::path::PathBuf;
+
+use rust_chip8::assembler::assemble;
+
+/// The help message, printed by `--help`.
+const USAGE: &str = "\
+Usage: chip8-asm [OPTIONS] <source file>
+
+Assembles an Octo source file into a CHIP-8 ROM.
+
+Options:
+  -o, --output <FILE>   Write the ROM to FILE (default: the source with the .ch8 extension)
+  --symbols             Print the labels, constants and breakpoints
+  -h, --help            Print this help and exit";
+
+/// Prints the usage and exits with an error code.
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut source = None;
+    let mut output = None;
+    let mut symbols = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            "-o" | "--output" => match args.next() {
+                Some(path) => output = Some(PathBuf::from(path)),
+                None => usage_error(&format!("missing value for `{arg}`")),
+            },
+            "--symbols" => symbols = true,
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option `{arg}`")),
+            _ if source.is_some() => usage_error(&format!("unexpected argument `{arg}`")),
+            _ => source = Some(PathBuf::from(arg)),
+        }
+    }
+
+    let Some(source) = source else {
+        usage_error("missing source file");
+    };
+    let output = output.unwrap_or_else(|| source.with_extension("ch8"));
+
+    let text = std::fs::read_to_string(&source).unwrap_or_else(|err| {
+        eprintln!("Cannot read {}: {err}", source.display());
+        std::process::exit(1);
+    });
+    let program = assemble(&text).unwrap_or_else(|err| {
+        eprintln!("{}: {err}", source.display());
+        std::process::exit(1);
+    });
+
+    if let Err(err) = std::fs::write(&output, &program.rom) {
+        eprintln!("Cannot write {}: {err}", output.display());
+        std::process::exit(1);
+    }
+    println!(
+        "{} bytes written to {}",
+        program.rom.len(),
+        output.display()
+    );
+
+    if symbols {
+        for (name, address) in &program.labels {
+            println!("label       0x{address:03X}  {name}");
+        }
+        for (name, value) in &program.constants {
+            println!("const       {value:<6}  {name}");
+        }
+        for (address, name) in &program.breakpoints {
+            println!("breakpoint  0x{address:03X}  {name}");
+        }
+    }
+}
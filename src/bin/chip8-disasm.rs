@@ -0,0 +1,115 @@
+#![warn(missing_debug_implementations, rust_2018_idioms, missing_docs)]
+
+//! # CHIP-8 Disassembler
+//!
+//! Prints the listing of a ROM: the address, the raw bytes and the mnemonic of every instruction,
+//! the targets of the jumps and calls being named with generated labels.
+//!
+//! With `--analyze`, the control flow is followed to tell code from data (see the
+//! `rust_chip8::chip8::analysis` module): data is written as `DB` rows and the unreachable
+//! regions, computed jumps and invalid opcodes are reported after the listing.
+//! With `--dot`, the control-flow graph is printed in the Graphviz DOT format instead.
+//!
+//! ## Usage
+//! ```sh
+//! cargo run --bin chip8-disasm -- games/PONG.ch8
+//! cargo run --bin chip8-disasm -- --dot games/PONG.ch8 | dot -Tsvg -o pong.svg
+//! ```
+//! Example output:
+//! ```text
+//! label_200:
+//! 200  6A02      LD VA, 0x02
+//! 202  6B0C      LD VB, 0x0C
+//! ...
+//! ```
+
+use rust_chip8::chip8::analysis::analyze;
+use rust_chip8::chip8::disassembler::disassemble_rom;
+
+/// The help message, printed by `--help`.
+const USAGE: &str = "\
+Usage: chip8-disasm [OPTIONS] <ROM file>
+
+Prints the disassembly of a CHIP-8, SUPER-CHIP or XO-CHIP ROM.
+
+Options:
+  --analyze     Follow the control flow to separate code from data
+  --dot         Print the control-flow graph in the Graphviz DOT format
+  -h, --help    Print this help and exit";
+
+/// What to print.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    /// The linear disassembly.
+    Listing,
+
+    /// The disassembly of the reachable code, with the data as bytes.
+    Analysis,
+
+    /// The control-flow graph.
+    Dot,
+}
+
+/// Prints the usage and exits with an error code.
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(2);
+}
+
+fn main() {
+    let mut path = None;
+    let mut output = Output::Listing;
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return;
+            }
+            "--analyze" => output = Output::Analysis,
+            "--dot" => output = Output::Dot,
+            _ if arg.starts_with('-') => usage_error(&format!("unknown option `{arg}`")),
+            _ if path.is_some() => usage_error(&format!("unexpected argument `{arg}`")),
+            _ => path = Some(arg),
+        }
+    }
+
+    let Some(path) = path else {
+        usage_error("missing ROM file");
+    };
+    let rom = std::fs::read(&path).unwrap_or_else(|err| {
+        eprintln!("Cannot read {path}: {err}");
+        std::process::exit(1);
+    });
+
+    match output {
+        Output::Listing => {
+            for line in disassemble_rom(&rom) {
+                println!("{line}");
+            }
+        }
+        Output::Analysis => {
+            let analysis = analyze(&rom);
+            for line in analysis.listing() {
+                println!("{line}");
+            }
+
+            println!();
+            for range in analysis.get_unreachable() {
+                println!(
+                    "; unreachable: 0x{:03X}-0x{:03X} ({} bytes)",
+                    range.start,
+                    range.end - 1,
+                    range.len()
+                );
+            }
+            for address in &analysis.computed_jumps {
+                println!("; computed jump at 0x{address:03X}, its targets are not followed");
+            }
+            for address in &analysis.invalid {
+                println!("; invalid opcode reached at 0x{address:03X}");
+            }
+        }
+        Output::Dot => print!("{}", analyze(&rom).to_dot()),
+    }
+}
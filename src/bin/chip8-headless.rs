@@ -0,0 +1,319 @@
+#![warn(missing_debug_implementations, rust_2018_idioms, missing_docs)]
+
+//! # Headless CHIP-8 Runner
+//!
+//! Runs a ROM without a window or an audio device, then writes the final display as ASCII art,
+//! PBM or PNG. This is meant for CI: the exit code is 1 if the program crashes (`Chip8Error`).
+//!
+//! ## Usage
+//! ```sh
+//! cargo run --bin chip8-headless -- --frames 300 --keys "5@10-20,A@30" -o pong.png games/PONG.ch8
+//! ```
+//! The key script is a list of `KEY@START[-END]` entries, separated by commas or whitespace:
+//! the hexadecimal CHIP-8 key `KEY` is held from the frame `START` to the frame `END` included
+//! (`START` only if `END` is omitted). The frames are counted from 0.
+//!
+//! A ROM file with the `.8o` extension is an Octo source, assembled before running.
+//!
+//! `--movie` plays back a movie recorded by the frontend with `--record`, with the quirks, seed,
+//! speed and keypad of the recording, to check that the run is still reproduced.
+
+use std::path::PathBuf;
+
+use rust_chip8::assembler::assemble;
+use rust_chip8::movie::Movie;
+use rust_chip8::screenshot::{to_ascii, to_pbm, to_png, DEFAULT_PALETTE};
+use rust_chip8::{constants, Chip8, Platform, StepOutcome};
+
+/// The help message, printed by `--help`.
+const USAGE: &str = "\
+Usage: chip8-headless [OPTIONS] <ROM file>
+
+Runs a CHIP-8 ROM without a window and prints or writes its final display.
+
+Options:
+  --frames <N>           Number of 60 Hz frames to run (default: 60, or the whole movie)
+  --cycles-per-frame <N> Instructions executed per frame (default: 16)
+  --platform <NAME>      Quirk preset: vip, chip48, schip or xochip
+  --seed <N>             Seed of the random number generator
+  --keys <SCRIPT>        Keys to hold, as KEY@START[-END] entries (e.g. \"5@10-20,A@30\")
+  --keys-file <FILE>     Read the key script from FILE
+  --movie <FILE>         Play back a movie, running all its frames by default
+                         (cannot be used with --cycles-per-frame, --platform, --seed or --keys)
+  -o, --output <FILE>    Write the display to FILE instead of printing it
+  --format <FORMAT>      ascii, pbm or png (default: from the output extension, else ascii)
+  -h, --help             Print this help and exit
+
+Exit status: 0 on success, 1 if the program crashed or could not be loaded, 2 on usage errors.";
+
+/// The format of the written display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// ASCII art, `.` and `#`.
+    Ascii,
+
+    /// Plain PBM image.
+    Pbm,
+
+    /// PNG image.
+    Png,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "ascii" | "txt" => Ok(Self::Ascii),
+            "pbm" => Ok(Self::Pbm),
+            "png" => Ok(Self::Png),
+            _ => Err(format!(
+                "unknown format `{name}`, expected ascii, pbm or png"
+            )),
+        }
+    }
+}
+
+/// A key held during a range of frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct KeyPress {
+    /// The CHIP-8 key, from 0x0 to 0xF.
+    key: u8,
+
+    /// The first frame the key is held.
+    start: u64,
+
+    /// The last frame the key is held.
+    end: u64,
+}
+
+/// Parses a key script, a list of `KEY@START[-END]` entries.
+fn parse_keys(script: &str) -> Result<Vec<KeyPress>, String> {
+    script
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let invalid = || format!("invalid key entry `{entry}`, expected KEY@START[-END]");
+
+            let (key, frames) = entry.split_once('@').ok_or_else(invalid)?;
+            let key = u8::from_str_radix(key, 16)
+                .ok()
+                .filter(|&key| key < 16)
+                .ok_or_else(invalid)?;
+            let (start, end) = frames.split_once('-').unwrap_or((frames, frames));
+            let start: u64 = start.parse().map_err(|_| invalid())?;
+            let end: u64 = end.parse().map_err(|_| invalid())?;
+            if end < start {
+                return Err(invalid());
+            }
+
+            Ok(KeyPress { key, start, end })
+        })
+        .collect()
+}
+
+/// The parsed command-line arguments.
+#[derive(Debug)]
+struct Args {
+    /// The ROM or Octo source to run.
+    rom: String,
+
+    /// The number of frames to run, all the frames of the movie if `None`, else 60.
+    frames: Option<u64>,
+
+    /// The number of instructions per frame.
+    cycles_per_frame: usize,
+
+    /// The quirk preset.
+    platform: Option<Platform>,
+
+    /// The seed of the random number generator.
+    seed: Option<u64>,
+
+    /// The keys to hold.
+    keys: Vec<KeyPress>,
+
+    /// The movie to play back.
+    movie: Option<PathBuf>,
+
+    /// The file to write the display to, printed if `None`.
+    output: Option<PathBuf>,
+
+    /// The format of the display.
+    format: Format,
+}
+
+/// Parses the value of the option `name`.
+fn parse_number<T: std::str::FromStr>(name: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{name}`"))
+}
+
+/// Parses the command-line arguments, `None` if the help was requested.
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut rom = None;
+    let mut frames = None;
+    let mut cycles_per_frame = None;
+    let mut platform = None;
+    let mut seed = None;
+    let mut keys = Vec::new();
+    let mut movie = None;
+    let mut output: Option<PathBuf> = None;
+    let mut format = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for `{arg}`"));
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--frames" => frames = Some(parse_number(&arg, value()?)?),
+            "--cycles-per-frame" => cycles_per_frame = Some(parse_number(&arg, value()?)?),
+            "--platform" => platform = Some(value()?.parse().map_err(|err| format!("{err}"))?),
+            "--seed" => seed = Some(parse_number(&arg, value()?)?),
+            "--keys" => keys.extend(parse_keys(&value()?)?),
+            "--keys-file" => {
+                let path = value()?;
+                let script = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("cannot read {path}: {err}"))?;
+                let script: String = script
+                    .lines()
+                    .map(|line| line.split('#').next().unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                keys.extend(parse_keys(&script)?);
+            }
+            "--movie" => movie = Some(value()?.into()),
+            "-o" | "--output" => output = Some(value()?.into()),
+            "--format" => format = Some(value()?.parse()?),
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if rom.is_some() => return Err(format!("unexpected argument `{arg}`")),
+            _ => rom = Some(arg),
+        }
+    }
+
+    if movie.is_some()
+        && (cycles_per_frame.is_some() || platform.is_some() || seed.is_some() || !keys.is_empty())
+    {
+        return Err("the movie sets the speed, quirks, seed and keys".to_string());
+    }
+
+    let extension = output
+        .as_ref()
+        .and_then(|path| path.extension())
+        .and_then(|extension| extension.to_str()?.parse().ok());
+
+    Ok(Some(Args {
+        rom: rom.ok_or("missing ROM file")?,
+        frames,
+        cycles_per_frame: cycles_per_frame.unwrap_or(constants::CYCLES_PER_FRAME),
+        platform,
+        seed,
+        keys,
+        movie,
+        output,
+        format: format.or(extension).unwrap_or(Format::Ascii),
+    }))
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return;
+        }
+        Err(err) => {
+            eprintln!("[Usage] {err}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    let quirks = args.platform.unwrap_or_default().quirks();
+    let mut chip8 = match args.seed {
+        Some(seed) => Chip8::with_seed(quirks, seed),
+        None => Chip8::with_quirks(quirks),
+    };
+    chip8.load_fontset();
+    let loaded = if args.rom.ends_with(".8o") {
+        std::fs::read_to_string(&args.rom)
+            .map_err(|err| err.to_string())
+            .and_then(|source| assemble(&source).map_err(|err| err.to_string()))
+            .and_then(|program| {
+                chip8
+                    .load_rom_bytes(&program.rom)
+                    .map_err(|err| err.to_string())
+            })
+    } else {
+        chip8.load_rom(&args.rom).map_err(|err| err.to_string())
+    };
+    if let Err(err) = loaded {
+        eprintln!("[Load] {}: {err}", args.rom);
+        std::process::exit(1);
+    }
+
+    let movie = args.movie.as_ref().map(|path| {
+        Movie::load(path)
+            .and_then(|movie| movie.start(&mut chip8).map(|()| movie))
+            .unwrap_or_else(|err| {
+                eprintln!("[Movie] {}: {err}", path.display());
+                std::process::exit(1);
+            })
+    });
+    let (frames, cycles_per_frame) = match &movie {
+        Some(movie) => (
+            args.frames.unwrap_or(movie.len() as u64),
+            movie.get_cycles_per_frame(),
+        ),
+        None => (args.frames.unwrap_or(60), args.cycles_per_frame),
+    };
+
+    for frame in 0..frames {
+        match &movie {
+            Some(movie) => {
+                movie.apply_frame(&mut chip8, frame as usize);
+            }
+            None => {
+                for key in 0..16 {
+                    let pressed = args.keys.iter().any(|press| {
+                        press.key == key && (press.start..=press.end).contains(&frame)
+                    });
+                    chip8.update_key(key, pressed);
+                }
+            }
+        }
+
+        match chip8.run_frame(cycles_per_frame) {
+            Ok(StepOutcome::Halted) => break,
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("[Crash] frame {frame}: {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let image = match args.format {
+        Format::Ascii => to_ascii(&chip8).into_bytes(),
+        Format::Pbm => to_pbm(&chip8).into_bytes(),
+        Format::Png => to_png(&chip8, &DEFAULT_PALETTE),
+    };
+
+    match &args.output {
+        Some(path) => {
+            if let Err(err) = std::fs::write(path, image) {
+                eprintln!("[Output] {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        }
+        None => {
+            use std::io::Write;
+
+            if let Err(err) = std::io::stdout().write_all(&image) {
+                eprintln!("[Output] {err}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
@@ -1,31 +1,55 @@
 //! # CHIP-8 Emulator Core
 //!
 //! This module implements the CHIP-8 emulator, including memory, registers, display, input handling,
-//! and instruction execution. It also handles sound using the `rodio` crate for audio playback.
+//! and instruction execution. It performs no I/O on its own, so it can be constructed, stepped and
+//! inspected without a window or an audio device.
 //!
 //! ## Features
 //! - CHIP-8 CPU emulation
-//! - 4 KB of RAM with program memory starting at `0x200`
+//! - 64 KB of RAM with program memory starting at `0x200`
 //! - 16 registers (`V0`-`VF`)
 //! - Stack with 16 levels for subroutine calls
-//! - Timers (delay and sound timers)
-//! - Keypad input handling
-//! - Display rendering (64x32 monochrome)
-//! - Sound playback using `rodio`
+//! - Timers (delay and sound timers) ticking at 60 Hz, independently of the instruction rate
+//! - Keypad input handling through key-down and key-up events (`Fx0A` waits for a press then a release)
+//! - Display buffer (64x32 monochrome, 128x64 in SUPER-CHIP high-resolution mode)
+//! - SUPER-CHIP 1.1 extensions (scrolling, 16x16 sprites, big font, RPL flags)
+//! - XO-CHIP extensions (64 KB of RAM, two display planes, register ranges, long loads, audio patterns)
 //!
 //! ## Used modules
 //! - `instructions`: Handles CHIP-8 opcode execution.
+//! - `builder`: Creates a machine in a given state, e.g. for tests.
+//! - `disassembler`: Turns opcodes back into mnemonics.
+//! - `breakpoints`: Breakpoints and watchpoints stopping the execution.
+//! - `error`: Errors raised by the emulated program.
+//! - `quirks`: Behavior switches for the ambiguous instructions.
+//! - `rng`: Random number sources for `RND Vx, byte`.
+//! - `state`: Save states of the whole machine.
 //! - `constants`: Stores emulator constants (e.g., memory size, fontset, screen dimensions).
 //!
 //! ## Usage
 //! The `Chip8` struct provides methods to initialize, load a ROM, update input, and execute instructions step by step.
+//! The frontend reads the machine state back through the `get_*` methods (e.g. the display buffer and the sound timer).
 
-use rodio::{Decoder, OutputStream, Sink};
-use std::sync::{Arc, Mutex};
+use sha1::{Digest, Sha1};
 
-use super::constants::chip8_constants;
+use crate::constants::chip8_constants;
 
+pub mod analysis;
+pub mod breakpoints;
+pub mod builder;
+pub mod disassembler;
+pub mod error;
 pub mod instructions;
+pub mod quirks;
+pub mod rng;
+pub mod state;
+
+pub use breakpoints::{BreakReason, Breakpoints};
+pub use builder::Chip8Builder;
+pub use error::Chip8Error;
+pub use quirks::{Platform, Quirks};
+pub use rng::{RandomSource, SeededRng};
+pub use state::StateError;
 
 impl std::fmt::Debug for Chip8 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -38,10 +62,20 @@ impl std::fmt::Debug for Chip8 {
             .field("sp", &self.sp)
             .field("delay_timer", &self.delay_timer)
             .field("sound_timer", &self.sound_timer)
+            .field("audio_pattern", &self.audio_pattern)
+            .field("pitch", &self.pitch)
             .field("keypad", &self.keypad)
-            .field("key_pressed", &self.key_pressed)
+            .field("key_wait", &self.key_wait)
             .field("display", &"[...]")
+            .field("hires", &self.hires)
+            .field("planes", &self.planes)
+            .field("rpl_flags", &self.rpl_flags)
+            .field("halted", &self.halted)
             .field("opcode", &self.opcode)
+            .field("quirks", &self.quirks)
+            .field("rom_hash", &self.rom_hash)
+            .field("breakpoints", &self.breakpoints)
+            .field("rng", &self.rng)
             .finish()
     }
 }
@@ -51,8 +85,8 @@ pub struct Chip8 {
     /// 16 general-purpose registers (`V0`-`VF`).
     registers: [u8; 16],
 
-    /// 4 KB (4096 bytes) of memory.
-    memory: [u8; 4096],
+    /// 64 KB (65536 bytes) of memory.
+    memory: Vec<u8>,
 
     /// 16-bit index register, used for memory operations.
     index: u16,
@@ -66,54 +100,136 @@ pub struct Chip8 {
     /// Stack pointer, keeps track of the stack level.
     sp: u8,
 
-    /// Delay timer (decrements at `EMULATOR_IPS` Hz).
+    /// Delay timer (decrements at `TIMER_FREQUENCY` Hz).
     delay_timer: u8,
 
-    /// Sound timer (decrements at `EMULATOR_IPS` Hz, plays sound when zero).
+    /// Sound timer (decrements at `TIMER_FREQUENCY` Hz, the frontend plays sound while it is non-zero).
     sound_timer: u8,
 
+    /// XO-CHIP 1-bit audio pattern, set once the program loads one with `F002`.
+    audio_pattern: Option<[u8; chip8_constants::AUDIO_PATTERN_SIZE]>,
+
+    /// XO-CHIP pitch register, selecting the audio pattern playback rate.
+    pitch: u8,
+
     /// CHIP-8 16-key keypad state (true = pressed, false = not pressed).
     keypad: [bool; 16],
 
-    /// Tracks if a key was pressed (for input-related opcodes).
-    key_pressed: bool,
+    /// The progress of `Fx0A`, driven by the key-down and key-up events.
+    key_wait: KeyWait,
+
+    /// Display buffer (64x32 or 128x64 pixels).
+    ///
+    /// Each pixel holds one bit per plane (bit 0 for plane 1, bit 1 for plane 2).
+    display: Vec<u8>,
+
+    /// XO-CHIP bitmask of the planes affected by drawing, clearing and scrolling.
+    planes: u8,
+
+    /// SUPER-CHIP high-resolution mode (128x64 display).
+    hires: bool,
 
-    /// Display buffer (64x32 pixels), `true` represents a `on` pixel.
-    display: [bool; 64 * 32],
+    /// SUPER-CHIP RPL user flags, saved and restored by `Fx75`/`Fx85`.
+    rpl_flags: [u8; 16],
+
+    /// Set once the program exited with `00FD`.
+    halted: bool,
 
     /// The current opcode being executed.
     opcode: u16,
 
+    /// The interpretation used for the ambiguous instructions.
+    quirks: Quirks,
+
+    /// Set when a sprite was drawn during the current frame (used by the `display_wait` quirk).
+    drawn: bool,
+
+    /// Random number source used by `RND Vx, byte`.
+    rng: Box<dyn RandomSource>,
+
+    /// SHA-1 hash of the loaded ROM.
+    rom_hash: [u8; 20],
+
+    /// Breakpoints and watchpoints checked by `step`.
+    breakpoints: Breakpoints,
+
+    /// Address of the breakpoint just reported, skipped by the next step to resume the execution.
+    resume_from: Option<u16>,
+
+    /// Memory watchpoint triggered by the instruction being executed.
+    watch_hit: Option<BreakReason>,
+
     /// Lookup table for opcode handlers.
-    lookup: [fn(&mut Chip8, Instruction); 16],
+    lookup: [InstructionHandler; 16],
+}
+
+/// An opcode handler, executing one decoded instruction on the machine.
+pub type InstructionHandler = fn(&mut Chip8, Instruction) -> Result<(), Chip8Error>;
+
+/// The result of a successful call to [`Chip8::step`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    /// The instruction was executed.
+    Executed,
+
+    /// The program exited (`00FD`), no more instructions are executed.
+    Halted,
+
+    /// A breakpoint or a watchpoint stopped the execution (see the `breakpoints` module).
+    BreakpointHit(BreakReason),
+}
 
-    /// Audio sink for sound playback.
-    sound_sink: Arc<Mutex<Sink>>,
+/// The progress of `Fx0A` (`LD Vx, K`), which waits for a key to be pressed then released.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyWait {
+    /// No instruction is waiting for a key.
+    #[default]
+    Idle,
 
-    /// Audio output stream (kept to prevent garbage collection).
-    _audio_stream: OutputStream,
+    /// Waiting for a key to be pressed, the keys already held are ignored.
+    Press,
+
+    /// The key was pressed, waiting for it to be released.
+    Release(u8),
+
+    /// The key was released, the next execution of `Fx0A` stores it and completes.
+    Released(u8),
 }
 
 /// Represents a decoded CHIP-8 instruction.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Instruction {
     /// The raw 16-bit opcode.
-    opcode: u16,
+    pub opcode: u16,
 
     /// The `X` register field (`0x0F00` bitmask).
-    x: u8,
+    pub x: u8,
 
     /// The `Y` register field (`0x00F0` bitmask).
-    y: u8,
+    pub y: u8,
 
     /// The last 4 bits (`0x000F`), typically used as a nibble.
-    n: u8,
+    pub n: u8,
 
     /// The last 8 bits (`0x00FF`), used for immediate values.
-    nn: u8,
+    pub nn: u8,
 
     /// The last 12 bits (`0x0FFF`), typically used as an address.
-    nnn: u16,
+    pub nnn: u16,
+}
+
+impl Instruction {
+    /// Decodes a raw 16-bit opcode into its fields.
+    pub const fn new(opcode: u16) -> Self {
+        Self {
+            opcode,
+            x: ((opcode & 0x0F00) >> 8) as u8,
+            y: ((opcode & 0x00F0) >> 4) as u8,
+            n: (opcode & 0x000F) as u8,
+            nn: (opcode & 0x00FF) as u8,
+            nnn: opcode & 0x0FFF,
+        }
+    }
 }
 
 impl Default for Chip8 {
@@ -123,29 +239,53 @@ impl Default for Chip8 {
 }
 
 impl Chip8 {
-    /// Creates a new `Chip8` instance with initialized memory, registers, and sound.
+    /// Creates a new `Chip8` instance with initialized memory and registers.
+    ///
+    /// The default quirks (COSMAC VIP) are used.
     ///
     /// # Returns
     ///
     /// A new instance of `Chip8` ready for execution.
     pub fn new() -> Self {
-        let (stream, sound_sink) =
-            rodio::OutputStream::try_default().expect("Failed to create output stream");
-        let sound_sink = Sink::try_new(&sound_sink).expect("Failed to create sound sink");
+        Self::with_quirks(Quirks::default())
+    }
 
+    /// Creates a new `Chip8` instance using the given quirks.
+    ///
+    /// # Arguments
+    ///
+    /// * `quirks` - The interpretation of the ambiguous instructions (e.g. `Quirks::SUPER_CHIP`).
+    ///
+    /// # Returns
+    ///
+    /// A new instance of `Chip8` ready for execution.
+    pub fn with_quirks(quirks: Quirks) -> Self {
         Self {
             registers: [0; 16],
-            memory: [0; 4096],
+            memory: vec![0; chip8_constants::RAM_SIZE],
             index: 0,
             pc: chip8_constants::START_ADDRESS as u16,
             stack: [0; 16],
             sp: 0,
             delay_timer: 0,
             sound_timer: 0,
+            audio_pattern: None,
+            pitch: chip8_constants::DEFAULT_PITCH,
             keypad: [false; 16],
-            key_pressed: false,
-            display: [false; 64 * 32],
+            key_wait: KeyWait::Idle,
+            display: vec![0; chip8_constants::WIDTH * chip8_constants::HEIGHT],
+            planes: 0b01,
+            hires: false,
+            rpl_flags: [0; 16],
+            halted: false,
             opcode: 0,
+            quirks,
+            drawn: false,
+            rng: Box::new(SeededRng::from_entropy()),
+            rom_hash: [0; 20],
+            breakpoints: Breakpoints::default(),
+            resume_from: None,
+            watch_hit: None,
             lookup: [
                 instructions::_0xxx::_0xxx,
                 instructions::_1xxx::_1xxx,
@@ -164,17 +304,198 @@ impl Chip8 {
                 instructions::_Exxx::_Exxx,
                 instructions::_Fxxx::_Fxxx,
             ],
-
-            sound_sink: Arc::new(Mutex::new(sound_sink)),
-            _audio_stream: stream,
         }
     }
 
-    /// Returns a reference to the display buffer (64x32 pixels).
-    pub fn get_display(&self) -> &[bool; 64 * 32] {
+    /// Creates a new `Chip8` instance whose random number generator is seeded with `seed`.
+    ///
+    /// Two machines created with the same quirks and seed run a program identically, given the
+    /// same inputs.
+    ///
+    /// # Arguments
+    ///
+    /// * `quirks` - The interpretation of the ambiguous instructions (e.g. `Quirks::SUPER_CHIP`).
+    /// * `seed` - The seed of the random number generator used by `RND Vx, byte`.
+    pub fn with_seed(quirks: Quirks, seed: u64) -> Self {
+        let mut chip8 = Self::with_quirks(quirks);
+        chip8.seed_rng(seed);
+
+        chip8
+    }
+
+    /// Returns a reference to the display buffer, row by row.
+    ///
+    /// Its dimensions are given by [`Chip8::get_display_size`].
+    /// Each pixel is a color index from 0 to 3: bit 0 is set if the pixel is on in plane 1,
+    /// bit 1 if it is on in plane 2. Programs that do not use XO-CHIP planes only produce 0 and 1.
+    pub fn get_display(&self) -> &[u8] {
         &self.display
     }
 
+    /// Returns the dimensions (width, height) of the display buffer.
+    ///
+    /// This is 64x32 pixels, or 128x64 pixels in SUPER-CHIP high-resolution mode.
+    pub fn get_display_size(&self) -> (usize, usize) {
+        if self.hires {
+            (chip8_constants::HIRES_WIDTH, chip8_constants::HIRES_HEIGHT)
+        } else {
+            (chip8_constants::WIDTH, chip8_constants::HEIGHT)
+        }
+    }
+
+    /// Returns the bitmask of the selected XO-CHIP planes.
+    pub fn get_planes(&self) -> u8 {
+        self.planes
+    }
+
+    /// Returns `true` if the display is in SUPER-CHIP high-resolution mode.
+    pub fn is_hires(&self) -> bool {
+        self.hires
+    }
+
+    /// Returns a reference to the SUPER-CHIP RPL user flags.
+    pub fn get_rpl_flags(&self) -> &[u8; 16] {
+        &self.rpl_flags
+    }
+
+    /// Returns `true` once the program exited with `00FD`.
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Returns a reference to the general-purpose registers (`V0`-`VF`).
+    pub fn get_registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    /// Returns a reference to the memory.
+    pub fn get_memory(&self) -> &[u8] {
+        &self.memory
+    }
+
+    /// Returns a mutable reference to the memory, e.g. to patch a program or set up a test.
+    ///
+    /// The writes are not seen by the memory watchpoints.
+    pub fn get_memory_mut(&mut self) -> &mut [u8] {
+        &mut self.memory
+    }
+
+    /// Returns the value of the index register `I`.
+    pub fn get_index(&self) -> u16 {
+        self.index
+    }
+
+    /// Returns the program counter.
+    pub fn get_pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// Returns a reference to the stack.
+    pub fn get_stack(&self) -> &[u16; 16] {
+        &self.stack
+    }
+
+    /// Returns the stack pointer.
+    pub fn get_sp(&self) -> u8 {
+        self.sp
+    }
+
+    /// Returns the value of the delay timer.
+    pub fn get_delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    /// Returns the value of the sound timer.
+    ///
+    /// The frontend should play a sound while this value is non-zero.
+    pub fn get_sound_timer(&self) -> u8 {
+        self.sound_timer
+    }
+
+    /// Returns the XO-CHIP audio pattern, or `None` if the program did not load one.
+    ///
+    /// The pattern holds 128 1-bit samples (most significant bit first), played in a loop
+    /// at [`Chip8::get_playback_rate`] while the sound timer is active.
+    pub fn get_audio_pattern(&self) -> Option<&[u8; chip8_constants::AUDIO_PATTERN_SIZE]> {
+        self.audio_pattern.as_ref()
+    }
+
+    /// Returns the value of the XO-CHIP pitch register.
+    pub fn get_pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Returns the audio pattern playback rate in samples per second.
+    ///
+    /// This is `4000 * 2 ^ ((pitch - 64) / 48)`, i.e. 4000 Hz at the default pitch.
+    pub fn get_playback_rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    /// Returns a reference to the keypad state.
+    pub fn get_keypad(&self) -> &[bool; 16] {
+        &self.keypad
+    }
+
+    /// Returns the progress of `Fx0A`.
+    pub fn get_key_wait(&self) -> KeyWait {
+        self.key_wait
+    }
+
+    /// Returns `true` if the CPU is blocked by `Fx0A`, waiting for a key to be pressed or released.
+    ///
+    /// The program makes no progress until the key events arrive, a frontend may e.g. skip the
+    /// remaining cycles of the frame.
+    pub fn is_waiting_for_key(&self) -> bool {
+        matches!(self.key_wait, KeyWait::Press | KeyWait::Release(_))
+    }
+
+    /// Returns the last executed opcode.
+    pub fn get_opcode(&self) -> u16 {
+        self.opcode
+    }
+
+    /// Returns the quirks currently in use.
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Returns the SHA-1 hash of the loaded ROM (all zeros if no ROM was loaded).
+    ///
+    /// It identifies a program regardless of its file name, e.g. to remember per-ROM settings.
+    pub fn get_rom_hash(&self) -> &[u8; 20] {
+        &self.rom_hash
+    }
+
+    /// Seeds the random number generator, making `RND Vx, byte` reproducible.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed; two machines seeded with the same value generate the same numbers.
+    ///
+    /// This replaces a random source set with [`Chip8::set_rng`] by the default [`SeededRng`].
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.rng = Box::new(SeededRng::new(seed));
+    }
+
+    /// Replaces the random number source, e.g. to inject a known sequence of bytes in tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The new source, used starting with the next `RND Vx, byte`.
+    pub fn set_rng(&mut self, rng: impl RandomSource + 'static) {
+        self.rng = Box::new(rng);
+    }
+
+    /// Changes the interpretation of the ambiguous instructions.
+    ///
+    /// # Arguments
+    ///
+    /// * `quirks` - The new quirks, used starting with the next instruction.
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
     /// Loads a CHIP-8 ROM into memory.
     ///
     /// # Arguments
@@ -182,68 +503,312 @@ impl Chip8 {
     ///
     /// # Errors
     ///
-    /// Returns an `std::io::Error` if the file cannot be read.
-    pub fn load_rom(&mut self, path: &str) -> Result<(), std::io::Error> {
+    /// Returns `Chip8Error::Io` if the file cannot be read, or `Chip8Error::RomTooLarge`
+    /// if it does not fit in memory.
+    pub fn load_rom(&mut self, path: &str) -> Result<(), Chip8Error> {
         let rom = std::fs::read(path)?;
 
-        for (i, &byte) in rom.iter().enumerate() {
-            self.memory[chip8_constants::START_ADDRESS + i] = byte;
+        self.load_rom_bytes(&rom)
+    }
+
+    /// Loads a CHIP-8 ROM already in memory.
+    ///
+    /// # Arguments
+    /// * `rom` - The bytes of the ROM, copied at `START_ADDRESS`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::RomTooLarge` if the ROM does not fit in memory.
+    pub fn load_rom_bytes(&mut self, rom: &[u8]) -> Result<(), Chip8Error> {
+        let end = chip8_constants::START_ADDRESS + rom.len();
+        if end > chip8_constants::RAM_SIZE {
+            return Err(Chip8Error::RomTooLarge { size: rom.len() });
         }
 
+        self.memory[chip8_constants::START_ADDRESS..end].copy_from_slice(rom);
+        self.rom_hash = Sha1::digest(rom).into();
+
         Ok(())
     }
 
-    /// Loads the built-in CHIP-8 fontset into memory.
+    /// Loads the built-in CHIP-8 fontset and the SUPER-CHIP big fontset into memory.
     pub fn load_fontset(&mut self) {
         for (i, &byte) in chip8_constants::FONTSET.iter().enumerate() {
             self.memory[chip8_constants::FONTSET_START_ADDRESS + i] = byte;
         }
+
+        for (i, &byte) in chip8_constants::BIG_FONTSET.iter().enumerate() {
+            self.memory[chip8_constants::BIG_FONTSET_START_ADDRESS + i] = byte;
+        }
+    }
+
+    /// Switches between the low-resolution (64x32) and high-resolution (128x64) display.
+    ///
+    /// The display is cleared.
+    fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+
+        let (width, height) = self.get_display_size();
+        self.display = vec![0; width * height];
+    }
+
+    /// Scrolls the selected planes of the display.
+    ///
+    /// # Arguments
+    ///
+    /// * `dx` - The number of pixels to scroll right (negative to scroll left).
+    /// * `dy` - The number of pixels to scroll down (negative to scroll up).
+    ///
+    /// Pixels scrolled in from the edges are off.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (width, height) = self.get_display_size();
+        let previous = self.display.clone();
+
+        for y in 0..height {
+            for x in 0..width {
+                let (source_x, source_y) = (x as isize - dx, y as isize - dy);
+                let source = if (0..width as isize).contains(&source_x)
+                    && (0..height as isize).contains(&source_y)
+                {
+                    previous[source_y as usize * width + source_x as usize]
+                } else {
+                    0
+                };
+
+                let pixel = &mut self.display[y * width + x];
+                *pixel = (*pixel & !self.planes) | (source & self.planes);
+            }
+        }
+    }
+
+    /// Skips the next instruction, used by the conditional skip opcodes.
+    ///
+    /// The XO-CHIP `F000 nnnn` instruction is 4 bytes long and is skipped entirely.
+    fn skip_next_instruction(&mut self) {
+        let next = self.fetch(self.pc as usize).unwrap_or(0);
+
+        let length = if next == 0xF000 { 4 } else { 2 };
+        self.pc = self.pc.wrapping_add(length);
     }
 
-    /// Updates the state of a specific key.
+    /// Updates the state of a specific key, sending a key-down or key-up event if it changed.
+    ///
+    /// This suits frontends polling the keyboard once per frame.
     ///
     /// # Arguments
     ///
     /// * `key` - The CHIP-8 key index (0x0 to 0xF).
     /// * `pressed` - `true` if the key is pressed, `false` otherwise.
     pub fn update_key(&mut self, key: u8, pressed: bool) {
-        self.keypad[key as usize] = pressed;
+        if pressed {
+            self.key_down(key);
+        } else {
+            self.key_up(key);
+        }
     }
 
-    /// Executes a single CHIP-8 CPU cycle.
-    pub fn step(&mut self) {
-        self.pc %= chip8_constants::RAM_SIZE as u16;
-        self.opcode =
-            (self.memory[self.pc as usize] as u16) << 8 | self.memory[self.pc as usize + 1] as u16;
-        self.pc += 2;
-
-        let instruction = Instruction {
-            opcode: self.opcode,
-            x: ((self.opcode & 0x0F00) >> 8) as u8,
-            y: ((self.opcode & 0x00F0) >> 4) as u8,
-            n: (self.opcode & 0x000F) as u8,
-            nn: (self.opcode & 0x00FF) as u8,
-            nnn: self.opcode & 0x0FFF,
+    /// Handles a key-down event: presses the key (0x0 to 0xF) if it is not already held.
+    ///
+    /// While `Fx0A` waits for a press, this selects the key it waits to be released.
+    pub fn key_down(&mut self, key: u8) {
+        if self.keypad[key as usize] {
+            return;
+        }
+
+        self.keypad[key as usize] = true;
+        if self.key_wait == KeyWait::Press {
+            self.key_wait = KeyWait::Release(key);
+        }
+    }
+
+    /// Handles a key-up event: releases the key (0x0 to 0xF).
+    ///
+    /// Releasing the key selected by `Fx0A` lets the instruction complete.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::Chip8Builder;
+    ///
+    /// // LD V0, K with the key 1 already held
+    /// let mut chip8 = Chip8Builder::new().keys(&[0x1]).instruction(0xF00A).build();
+    /// chip8.step().unwrap();
+    /// assert!(chip8.is_waiting_for_key());
+    ///
+    /// chip8.key_down(0x2);
+    /// chip8.key_up(0x1); // Held before the instruction, ignored
+    /// chip8.step().unwrap();
+    /// assert!(chip8.is_waiting_for_key());
+    ///
+    /// chip8.key_up(0x2);
+    /// chip8.step().unwrap();
+    /// assert!(!chip8.is_waiting_for_key());
+    /// assert_eq!(chip8.get_registers()[0], 0x2);
+    /// assert_eq!(chip8.get_pc(), 0x202);
+    /// ```
+    pub fn key_up(&mut self, key: u8) {
+        self.keypad[key as usize] = false;
+        if self.key_wait == KeyWait::Release(key) {
+            self.key_wait = KeyWait::Released(key);
+        }
+    }
+
+    /// Reads a 16-bit word of the instruction stream from memory.
+    ///
+    /// Unlike `read_memory`, this is not checked by the memory watchpoints.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::MemoryOutOfBounds` if `addr` or `addr + 1` is outside of the RAM.
+    fn fetch(&self, addr: usize) -> Result<u16, Chip8Error> {
+        let byte = |addr: usize| {
+            self.memory
+                .get(addr)
+                .copied()
+                .ok_or(Chip8Error::MemoryOutOfBounds { addr })
         };
 
-        (self.lookup[((self.opcode & 0xF000) >> 12) as usize])(self, instruction);
+        Ok((byte(addr)? as u16) << 8 | byte(addr + 1)? as u16)
+    }
 
+    /// Reads a byte from memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::MemoryOutOfBounds` if `addr` is outside of the RAM.
+    fn read_memory(&mut self, addr: usize) -> Result<u8, Chip8Error> {
+        let value = self
+            .memory
+            .get(addr)
+            .copied()
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+
+        if self.breakpoints.watches_memory(addr, false) {
+            self.watch_hit.get_or_insert(BreakReason::MemoryRead {
+                address: addr as u16,
+            });
+        }
+
+        Ok(value)
+    }
+
+    /// Writes a byte to memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Chip8Error::MemoryOutOfBounds` if `addr` is outside of the RAM.
+    fn write_memory(&mut self, addr: usize, value: u8) -> Result<(), Chip8Error> {
+        let byte = self
+            .memory
+            .get_mut(addr)
+            .ok_or(Chip8Error::MemoryOutOfBounds { addr })?;
+        *byte = value;
+
+        if self.breakpoints.watches_memory(addr, true) {
+            self.watch_hit.get_or_insert(BreakReason::MemoryWrite {
+                address: addr as u16,
+                value,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Executes a single CHIP-8 CPU cycle.
+    ///
+    /// If a breakpoint is set at `pc`, the instruction is not executed and
+    /// `StepOutcome::BreakpointHit` is returned; the next call executes it.
+    /// If the instruction triggers a watchpoint, it is executed and `StepOutcome::BreakpointHit`
+    /// is returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if the instruction cannot be executed. The machine is left in the
+    /// state it had when the fault occurred.
+    pub fn step(&mut self) -> Result<StepOutcome, Chip8Error> {
+        if self.halted {
+            return Ok(StepOutcome::Halted);
+        }
+
+        if self.breakpoints.is_empty() {
+            return self.execute();
+        }
+
+        if let Some(reason) = self.check_breakpoint() {
+            return Ok(StepOutcome::BreakpointHit(reason));
+        }
+
+        let (registers, conditions) = (self.registers, self.evaluate_conditions());
+        self.watch_hit = None;
+        let outcome = self.execute()?;
+
+        match self.check_watchpoints(&registers, conditions) {
+            Some(reason) => Ok(StepOutcome::BreakpointHit(reason)),
+            None => Ok(outcome),
+        }
+    }
+
+    /// Fetches, decodes and executes the instruction at `pc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `Chip8Error` if the instruction cannot be executed.
+    fn execute(&mut self) -> Result<StepOutcome, Chip8Error> {
+        self.opcode = self.fetch(self.pc as usize)?;
+        self.pc = self.pc.wrapping_add(2);
+
+        let instruction = Instruction::new(self.opcode);
+
+        (self.lookup[((self.opcode & 0xF000) >> 12) as usize])(self, instruction)?;
+
+        if self.halted {
+            return Ok(StepOutcome::Halted);
+        }
+
+        Ok(StepOutcome::Executed)
+    }
+
+    /// Decrements the delay and sound timers.
+    ///
+    /// This must be called at `TIMER_FREQUENCY` Hz (60 Hz), regardless of how many
+    /// instructions are executed in between.
+    pub fn tick_timers(&mut self) {
         if self.delay_timer > 0 {
             self.delay_timer -= 1;
         }
 
         if self.sound_timer > 0 {
-            let sound_sink = self.sound_sink.lock().unwrap();
-            if sound_sink.empty() {
-                let file =
-                    std::fs::File::open("assets/audio/orb.mp3").expect("Failed to open audio file");
-                let source = Decoder::new(std::io::BufReader::new(file))
-                    .expect("Failed to create audio decoder");
-
-                sound_sink.append(source);
-                sound_sink.play();
-            }
             self.sound_timer -= 1;
         }
     }
+
+    /// Runs one 60 Hz frame: executes `cycles` instructions, then ticks the timers once.
+    ///
+    /// With the `display_wait` quirk, the frame ends early after the first sprite is drawn.
+    /// The frame also ends early once the program exited or a breakpoint was hit.
+    ///
+    /// # Arguments
+    ///
+    /// * `cycles` - The number of instructions to execute during the frame (e.g. `CYCLES_PER_FRAME`).
+    ///
+    /// # Returns
+    ///
+    /// The outcome of the last executed instruction.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first `Chip8Error` raised, the remaining cycles and the timer tick are skipped.
+    pub fn run_frame(&mut self, cycles: usize) -> Result<StepOutcome, Chip8Error> {
+        let mut outcome = StepOutcome::Executed;
+        self.drawn = false;
+        for _ in 0..cycles {
+            outcome = self.step()?;
+            if outcome != StepOutcome::Executed || (self.quirks.display_wait && self.drawn) {
+                break;
+            }
+        }
+
+        self.tick_timers();
+
+        Ok(outcome)
+    }
 }
@@ -0,0 +1,438 @@
+//! # Control-Flow Analysis
+//!
+//! This module tells the code of a ROM apart from its data, which a linear disassembly (see
+//! [`disassemble_rom`](super::disassembler::disassemble_rom)) cannot do.
+//!
+//! The analysis follows the execution paths from `START_ADDRESS`: jumps (`1nnn`), calls (`2nnn`)
+//! and the instruction after them, both outcomes of the skip instructions, and the end of the
+//! paths at `00EE`, `00FD` and invalid opcodes. The reached instructions are grouped into the basic
+//! blocks of a control-flow graph, which can be exported to the Graphviz DOT format.
+//!
+//! The bytes pointed to by `Annn` (and `F000 nnnn`) that are not code are data: each data region
+//! extends from its reference up to the next code byte or the next reference. The remaining bytes
+//! are unreachable. The targets of the computed jumps (`Bnnn`) depend on `V0` and are not
+//! followed: the jumps are flagged instead, the code they reach may be reported as unreachable.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use super::disassembler::{add_labels, disassemble_at, ListingLine};
+use crate::constants::chip8_constants::START_ADDRESS;
+
+/// What a byte of the ROM was found to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteKind {
+    /// Part of a reachable instruction.
+    Code,
+
+    /// Pointed to by `Annn` or `F000 nnnn`.
+    Data,
+
+    /// Neither reached nor referenced.
+    Unreachable,
+}
+
+/// How the execution goes from a basic block to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The next instruction is executed.
+    Fallthrough,
+
+    /// A `JP` is taken.
+    Jump,
+
+    /// A `CALL` is taken, the execution returns to the instruction after it.
+    Call,
+
+    /// A skip instruction skips the next instruction.
+    Skip,
+}
+
+/// A sequence of instructions always executed from the first to the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The addresses of the instructions.
+    pub instructions: Vec<u16>,
+
+    /// The start of the blocks executed next, with the kind of the transition.
+    pub successors: Vec<(u16, EdgeKind)>,
+
+    /// Whether the block ends with a computed jump (`Bnnn`), whose targets are unknown.
+    pub computed_jump: bool,
+}
+
+/// The result of the analysis of a ROM.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::analysis::{analyze, ByteKind, EdgeKind};
+///
+/// let rom = [
+///     0xA2, 0x0A, // 200: LD I, 0x20A
+///     0x30, 0x00, // 202: SE V0, 0x00
+///     0x12, 0x02, // 204: JP 0x202
+///     0x12, 0x06, // 206: JP 0x206
+///     0x00, 0xEE, // 208: RET (unreachable)
+///     0xFF, 0x81, // 20A: sprite data
+/// ];
+/// let analysis = analyze(&rom);
+///
+/// assert_eq!(analysis.kinds[0], ByteKind::Code);
+/// assert_eq!(analysis.kinds[10], ByteKind::Data);
+/// assert_eq!(analysis.get_unreachable(), [0x208..0x20A]);
+/// assert_eq!(
+///     analysis.blocks[&0x202].successors,
+///     [(0x204, EdgeKind::Fallthrough), (0x206, EdgeKind::Skip)]
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// The analyzed ROM.
+    pub rom: Vec<u8>,
+
+    /// The kind of every byte of the ROM.
+    pub kinds: Vec<ByteKind>,
+
+    /// The basic blocks, by start address.
+    pub blocks: BTreeMap<u16, BasicBlock>,
+
+    /// The addresses loaded into `I` by the reachable code.
+    pub data_references: BTreeSet<u16>,
+
+    /// The addresses of the reachable computed jumps (`Bnnn`).
+    pub computed_jumps: BTreeSet<u16>,
+
+    /// The addresses of the reachable opcodes that are not instructions.
+    pub invalid: BTreeSet<u16>,
+}
+
+/// How an instruction passes the execution on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    /// To the next instruction.
+    Next,
+
+    /// To the given address.
+    Jump(usize),
+
+    /// To the given subroutine, then to the next instruction.
+    Call(usize),
+
+    /// To the next instruction or the one after it.
+    Skip,
+
+    /// Nowhere known: return, exit or computed jump.
+    Stop,
+}
+
+/// Returns how the instruction `opcode` passes the execution on.
+fn flow(opcode: u16) -> Flow {
+    let nnn = (opcode & 0x0FFF) as usize;
+
+    match opcode & 0xF000 {
+        0x0000 if matches!(opcode, 0x00EE | 0x00FD) => Flow::Stop,
+        0x1000 => Flow::Jump(nnn),
+        0x2000 => Flow::Call(nnn),
+        0x3000 | 0x4000 => Flow::Skip,
+        0x5000 | 0x9000 if opcode & 0x000F == 0 => Flow::Skip,
+        0xB000 => Flow::Stop,
+        0xE000 if matches!(opcode & 0x00FF, 0x9E | 0xA1) => Flow::Skip,
+        _ => Flow::Next,
+    }
+}
+
+/// Analyzes the control flow of a ROM loaded at `START_ADDRESS`.
+pub fn analyze(rom: &[u8]) -> Analysis {
+    let end = START_ADDRESS + rom.len();
+    let decode = |address: usize| {
+        let offset = address.checked_sub(START_ADDRESS)?;
+        let (text, length) = disassemble_at(rom, offset)?;
+        let opcode = u16::from_be_bytes([rom[offset], rom[offset + 1]]);
+
+        Some((opcode, length, text))
+    };
+
+    let mut instructions = BTreeMap::new();
+    let mut leaders = BTreeSet::from([START_ADDRESS]);
+    let mut data_references = BTreeSet::new();
+    let mut computed_jumps = BTreeSet::new();
+    let mut invalid = BTreeSet::new();
+
+    // Follow every path from the start
+    let mut pending = vec![START_ADDRESS];
+    while let Some(address) = pending.pop() {
+        if !(START_ADDRESS..end).contains(&address)
+            || instructions.contains_key(&address)
+            || invalid.contains(&(address as u16))
+        {
+            continue;
+        }
+
+        let Some((opcode, length, text)) = decode(address) else {
+            invalid.insert(address as u16);
+            continue;
+        };
+        if text.starts_with("DW") {
+            invalid.insert(address as u16);
+            continue;
+        }
+        instructions.insert(address, (opcode, length));
+
+        if opcode & 0xF000 == 0xA000 {
+            data_references.insert(opcode & 0x0FFF);
+        }
+        if length == 4 {
+            let offset = address - START_ADDRESS;
+            data_references.insert(u16::from_be_bytes([rom[offset + 2], rom[offset + 3]]));
+        }
+
+        let next = address + length;
+        match flow(opcode) {
+            Flow::Next => pending.push(next),
+            Flow::Jump(target) => {
+                leaders.insert(target);
+                pending.push(target);
+            }
+            Flow::Call(target) => {
+                leaders.extend([target, next]);
+                pending.extend([next, target]);
+            }
+            Flow::Skip => {
+                let skipped = next + decode(next).map_or(2, |(_, length, _)| length);
+                leaders.extend([next, skipped]);
+                pending.extend([skipped, next]);
+            }
+            Flow::Stop if opcode & 0xF000 == 0xB000 => {
+                computed_jumps.insert(address as u16);
+            }
+            Flow::Stop => {}
+        }
+    }
+
+    // Code bytes, then data regions up to the next code byte or reference
+    let mut kinds = vec![ByteKind::Unreachable; rom.len()];
+    for (&address, &(_, length)) in &instructions {
+        let offset = address - START_ADDRESS;
+        kinds[offset..(offset + length).min(rom.len())].fill(ByteKind::Code);
+    }
+    for &reference in &data_references {
+        let Some(start) = (reference as usize).checked_sub(START_ADDRESS) else {
+            continue;
+        };
+
+        for (offset, kind) in kinds.iter_mut().enumerate().skip(start) {
+            let is_reference =
+                offset != start && data_references.contains(&((START_ADDRESS + offset) as u16));
+            if *kind == ByteKind::Code || is_reference {
+                break;
+            }
+            *kind = ByteKind::Data;
+        }
+    }
+
+    // Basic blocks, ending at control flow instructions and before leaders
+    let mut blocks = BTreeMap::new();
+    let mut current: Option<(u16, BasicBlock)> = None;
+    let mut expected = START_ADDRESS;
+    for (&address, &(opcode, length)) in &instructions {
+        // An instruction overlapping the previous one starts a new block
+        if address != expected {
+            if let Some((start, mut block)) = current.take() {
+                block.successors = vec![(expected as u16, EdgeKind::Fallthrough)];
+                blocks.insert(start, block);
+            }
+        }
+
+        let next = address + length;
+        expected = next;
+        let (_, block) = current.get_or_insert_with(|| {
+            let block = BasicBlock {
+                instructions: Vec::new(),
+                successors: Vec::new(),
+                computed_jump: false,
+            };
+            (address as u16, block)
+        });
+        block.instructions.push(address as u16);
+
+        let successors = match flow(opcode) {
+            Flow::Next if leaders.contains(&next) || !instructions.contains_key(&next) => {
+                if instructions.contains_key(&next) {
+                    vec![(next, EdgeKind::Fallthrough)]
+                } else {
+                    Vec::new()
+                }
+            }
+            Flow::Next => continue,
+            Flow::Jump(target) => vec![(target, EdgeKind::Jump)],
+            Flow::Call(target) => vec![(target, EdgeKind::Call), (next, EdgeKind::Fallthrough)],
+            Flow::Skip => {
+                let skipped = next + decode(next).map_or(2, |(_, length, _)| length);
+                vec![(next, EdgeKind::Fallthrough), (skipped, EdgeKind::Skip)]
+            }
+            Flow::Stop => {
+                block.computed_jump = opcode & 0xF000 == 0xB000;
+                Vec::new()
+            }
+        };
+        block.successors = successors
+            .into_iter()
+            .map(|(address, kind)| (address as u16, kind))
+            .collect();
+
+        if let Some((start, block)) = current.take() {
+            blocks.insert(start, block);
+        }
+    }
+    if let Some((start, block)) = current {
+        blocks.insert(start, block);
+    }
+
+    Analysis {
+        rom: rom.to_vec(),
+        kinds,
+        blocks,
+        data_references,
+        computed_jumps,
+        invalid,
+    }
+}
+
+impl Analysis {
+    /// Returns the address ranges of the unreachable bytes.
+    pub fn get_unreachable(&self) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+
+        for (offset, kind) in self.kinds.iter().enumerate() {
+            if *kind != ByteKind::Unreachable {
+                continue;
+            }
+
+            let address = START_ADDRESS + offset;
+            match ranges.last_mut() {
+                Some(range) if range.end == address => range.end += 1,
+                _ => ranges.push(address..address + 1),
+            }
+        }
+
+        ranges
+    }
+
+    /// Returns the listing of the ROM, with the data and unreachable bytes written as `DB` rows
+    /// of up to 4 bytes instead of being disassembled.
+    ///
+    /// The targets of the jumps, calls and `LD I` are labelled as in
+    /// [`disassemble_rom`](super::disassembler::disassemble_rom).
+    pub fn listing(&self) -> Vec<ListingLine> {
+        let starts: BTreeSet<usize> = self
+            .blocks
+            .values()
+            .flat_map(|block| &block.instructions)
+            .map(|&address| address as usize)
+            .collect();
+
+        let mut lines = Vec::new();
+        let mut offset = 0;
+        while offset < self.rom.len() {
+            let address = START_ADDRESS + offset;
+            if starts.contains(&address) {
+                if let Some((mnemonic, length)) = disassemble_at(&self.rom, offset) {
+                    lines.push(ListingLine {
+                        address: address as u16,
+                        bytes: self.rom[offset..offset + length].to_vec(),
+                        mnemonic,
+                        label: None,
+                    });
+                    offset += length;
+                    continue;
+                }
+            }
+
+            // A row of bytes of the same kind, cut before instructions and references
+            let kind = self.kinds[offset];
+            let mut end = offset + 1;
+            while end < self.rom.len()
+                && end - offset < 4
+                && self.kinds[end] == kind
+                && !starts.contains(&(START_ADDRESS + end))
+                && !self
+                    .data_references
+                    .contains(&((START_ADDRESS + end) as u16))
+            {
+                end += 1;
+            }
+
+            let bytes = &self.rom[offset..end];
+            let values: Vec<String> = bytes.iter().map(|byte| format!("0x{byte:02X}")).collect();
+            let comment = match kind {
+                ByteKind::Unreachable => "  ; unreachable",
+                _ => "",
+            };
+            lines.push(ListingLine {
+                address: address as u16,
+                bytes: bytes.to_vec(),
+                mnemonic: format!("DB {}{comment}", values.join(", ")),
+                label: None,
+            });
+            offset = end;
+        }
+
+        add_labels(&mut lines, |line| starts.contains(&(line.address as usize)));
+
+        lines
+    }
+
+    /// Exports the control-flow graph in the Graphviz DOT format.
+    ///
+    /// Every basic block is a node listing its instructions. The blocks ending with a computed
+    /// jump are drawn in red, the calls are dashed edges and the skips dotted edges.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::chip8::analysis::analyze;
+    ///
+    /// let dot = analyze(&[0x22, 0x04, 0x12, 0x00, 0x00, 0xEE]).to_dot();
+    ///
+    /// assert!(dot.starts_with("digraph cfg {"));
+    /// assert!(dot.contains("n200 -> n204 [style=dashed, label=\"call\"];"));
+    /// assert!(dot.contains("n202 -> n200 [label=\"jump\"];"));
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+
+        for (start, block) in &self.blocks {
+            let label: String = block
+                .instructions
+                .iter()
+                .map(|&address| {
+                    let offset = address as usize - START_ADDRESS;
+                    let text = disassemble_at(&self.rom, offset)
+                        .map_or_else(String::new, |(text, _)| text);
+                    format!("{address:03X}  {text}\\l")
+                })
+                .collect();
+            let color = if block.computed_jump {
+                ", color=red"
+            } else {
+                ""
+            };
+            dot += &format!("    n{start:03X} [label=\"{label}\"{color}];\n");
+        }
+
+        for (start, block) in &self.blocks {
+            for (target, kind) in &block.successors {
+                let style = match kind {
+                    EdgeKind::Fallthrough => "",
+                    EdgeKind::Jump => " [label=\"jump\"]",
+                    EdgeKind::Call => " [style=dashed, label=\"call\"]",
+                    EdgeKind::Skip => " [style=dotted, label=\"skip\"]",
+                };
+                dot += &format!("    n{start:03X} -> n{target:03X}{style};\n");
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
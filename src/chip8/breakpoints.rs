@@ -0,0 +1,713 @@
+//! # Breakpoints and Watchpoints
+//!
+//! This module defines the debugging stops checked by [`Chip8::step`]:
+//! - **breakpoints** stop before the instruction at an address is executed, optionally only when
+//!   a [`Condition`] holds (e.g. `V3 == 0x10`);
+//! - **conditions** stop after the instruction that made them true, wherever it is;
+//! - **memory watchpoints** stop after an instruction read or wrote an address range
+//!   (instruction fetches are not watched);
+//! - **register watchpoints** stop after an instruction changed a register.
+//!
+//! A stop is reported as [`StepOutcome::BreakpointHit`](super::StepOutcome::BreakpointHit) with
+//! its [`BreakReason`]. Stepping again resumes the execution, even at a breakpoint.
+
+use std::collections::BTreeMap;
+
+use super::Chip8;
+
+/// A value of the machine that a [`Condition`] compares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// A general-purpose register (`V0`-`VF`).
+    Register(u8),
+
+    /// The index register (`I`).
+    Index,
+
+    /// The delay timer (`DT`).
+    DelayTimer,
+
+    /// The sound timer (`ST`).
+    SoundTimer,
+
+    /// A constant value.
+    Constant(u16),
+}
+
+impl Operand {
+    /// Returns the current value of the operand.
+    fn value(self, chip8: &Chip8) -> u16 {
+        match self {
+            Self::Register(register) => chip8.registers[register as usize & 0xF] as u16,
+            Self::Index => chip8.index,
+            Self::DelayTimer => chip8.delay_timer as u16,
+            Self::SoundTimer => chip8.sound_timer as u16,
+            Self::Constant(value) => value,
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Register(register) => write!(f, "V{register:X}"),
+            Self::Index => write!(f, "I"),
+            Self::DelayTimer => write!(f, "DT"),
+            Self::SoundTimer => write!(f, "ST"),
+            Self::Constant(value) => write!(f, "0x{value:X}"),
+        }
+    }
+}
+
+impl std::str::FromStr for Operand {
+    type Err = String;
+
+    /// Parses `V0`-`VF`, `I`, `DT`, `ST` or a number (decimal, or hexadecimal prefixed with `0x` or `#`).
+    fn from_str(operand: &str) -> Result<Self, Self::Err> {
+        match operand.to_ascii_uppercase().as_str() {
+            "I" => Ok(Self::Index),
+            "DT" => Ok(Self::DelayTimer),
+            "ST" => Ok(Self::SoundTimer),
+            register if register.len() == 2 && register.starts_with('V') => {
+                u8::from_str_radix(&register[1..], 16)
+                    .map(Self::Register)
+                    .map_err(|_| format!("invalid register `{operand}`"))
+            }
+            _ => parse_number(operand).map(Self::Constant),
+        }
+    }
+}
+
+/// A comparison between two operands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// `==`
+    Equal,
+
+    /// `!=`
+    NotEqual,
+
+    /// `<`
+    Less,
+
+    /// `<=`
+    LessOrEqual,
+
+    /// `>`
+    Greater,
+
+    /// `>=`
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// All the comparisons with their operator, the two-character operators first.
+    const OPERATORS: [(&'static str, Self); 6] = [
+        ("==", Self::Equal),
+        ("!=", Self::NotEqual),
+        ("<=", Self::LessOrEqual),
+        (">=", Self::GreaterOrEqual),
+        ("<", Self::Less),
+        (">", Self::Greater),
+    ];
+
+    /// Returns the operator of the comparison.
+    fn operator(self) -> &'static str {
+        Self::OPERATORS
+            .iter()
+            .find(|(_, comparison)| *comparison == self)
+            .map_or("", |(operator, _)| operator)
+    }
+}
+
+/// A condition on the machine state, such as `V3 == 0x10`.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::breakpoints::Condition;
+/// use rust_chip8::Chip8;
+///
+/// let condition: Condition = "V0 >= 0x10".parse().unwrap();
+/// assert_eq!(condition.to_string(), "V0 >= 0x10");
+///
+/// let mut chip8 = Chip8::new();
+/// assert!(!condition.evaluate(&chip8));
+/// chip8.load_rom_bytes(&[0x60, 0x2A]).unwrap(); // LD V0, 0x2A
+/// chip8.step().unwrap();
+/// assert!(condition.evaluate(&chip8));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Condition {
+    /// The left operand.
+    pub left: Operand,
+
+    /// The comparison of the operands.
+    pub comparison: Comparison,
+
+    /// The right operand.
+    pub right: Operand,
+}
+
+impl Condition {
+    /// Returns `true` if the condition holds for the current state of `chip8`.
+    pub fn evaluate(&self, chip8: &Chip8) -> bool {
+        let (left, right) = (self.left.value(chip8), self.right.value(chip8));
+
+        match self.comparison {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+        }
+    }
+}
+
+impl std::fmt::Display for Condition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.left,
+            self.comparison.operator(),
+            self.right
+        )
+    }
+}
+
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    /// Parses `<operand> <comparison> <operand>`, e.g. `V3 == 0x10`, `I >= 0x300` or `V1 < V2`.
+    fn from_str(condition: &str) -> Result<Self, Self::Err> {
+        let (operator, comparison) = Comparison::OPERATORS
+            .into_iter()
+            .find(|(operator, _)| condition.contains(operator))
+            .ok_or_else(|| format!("condition `{condition}` has no comparison operator"))?;
+        let (left, right) = condition.split_once(operator).unwrap();
+
+        Ok(Self {
+            left: left.trim().parse()?,
+            comparison,
+            right: right.trim().parse()?,
+        })
+    }
+}
+
+/// The kind of memory accesses a [`MemoryWatchpoint`] stops on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryAccess {
+    /// Reads only.
+    Read,
+
+    /// Writes only.
+    Write,
+
+    /// Reads and writes.
+    ReadWrite,
+}
+
+/// A watchpoint on a range of memory addresses.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::breakpoints::{MemoryAccess, MemoryWatchpoint};
+///
+/// let watchpoint: MemoryWatchpoint = "0x200-0x2FF:w".parse().unwrap();
+/// assert_eq!((watchpoint.start, watchpoint.end), (0x200, 0x2FF));
+/// assert_eq!(watchpoint.access, MemoryAccess::Write);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWatchpoint {
+    /// The first watched address.
+    pub start: u16,
+
+    /// The last watched address (included).
+    pub end: u16,
+
+    /// The watched accesses.
+    pub access: MemoryAccess,
+}
+
+impl MemoryWatchpoint {
+    /// Returns `true` if the watchpoint stops on a read (or a write if `write` is set) at `address`.
+    fn matches(&self, address: usize, write: bool) -> bool {
+        let access = match self.access {
+            MemoryAccess::Read => !write,
+            MemoryAccess::Write => write,
+            MemoryAccess::ReadWrite => true,
+        };
+
+        access && (self.start as usize..=self.end as usize).contains(&address)
+    }
+}
+
+impl std::fmt::Display for MemoryWatchpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let access = match self.access {
+            MemoryAccess::Read => "r",
+            MemoryAccess::Write => "w",
+            MemoryAccess::ReadWrite => "rw",
+        };
+
+        write!(f, "0x{:03X}-0x{:03X}:{access}", self.start, self.end)
+    }
+}
+
+impl std::str::FromStr for MemoryWatchpoint {
+    type Err = String;
+
+    /// Parses `<start>[-<end>][:r|w|rw]`, watching reads and writes by default.
+    fn from_str(watchpoint: &str) -> Result<Self, Self::Err> {
+        let (range, access) = watchpoint.split_once(':').unwrap_or((watchpoint, "rw"));
+        let access = match access.to_ascii_lowercase().as_str() {
+            "r" => MemoryAccess::Read,
+            "w" => MemoryAccess::Write,
+            "rw" | "wr" => MemoryAccess::ReadWrite,
+            _ => {
+                return Err(format!(
+                    "invalid memory access `{access}`, expected r, w or rw"
+                ))
+            }
+        };
+
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let (start, end) = (parse_number(start.trim())?, parse_number(end.trim())?);
+        if start > end {
+            return Err(format!("invalid address range `{range}`"));
+        }
+
+        Ok(Self { start, end, access })
+    }
+}
+
+/// The reason why the execution stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakReason {
+    /// The execution reached a breakpoint, the instruction at `address` was not executed yet.
+    Breakpoint {
+        /// The address of the breakpoint.
+        address: u16,
+    },
+
+    /// A condition became true.
+    Condition(Condition),
+
+    /// A watched address was read.
+    MemoryRead {
+        /// The address that was read.
+        address: u16,
+    },
+
+    /// A watched address was written.
+    MemoryWrite {
+        /// The address that was written.
+        address: u16,
+
+        /// The written value.
+        value: u8,
+    },
+
+    /// A watched register changed.
+    RegisterChanged {
+        /// The register index (0x0 to 0xF).
+        register: u8,
+
+        /// The value before the instruction.
+        old: u8,
+
+        /// The value after the instruction.
+        new: u8,
+    },
+}
+
+impl std::fmt::Display for BreakReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Breakpoint { address } => write!(f, "breakpoint at {address:03X}"),
+            Self::Condition(condition) => write!(f, "condition `{condition}` is true"),
+            Self::MemoryRead { address } => write!(f, "memory read at {address:03X}"),
+            Self::MemoryWrite { address, value } => {
+                write!(f, "memory write of {value:02X} at {address:03X}")
+            }
+            Self::RegisterChanged { register, old, new } => {
+                write!(f, "V{register:X} changed from {old:02X} to {new:02X}")
+            }
+        }
+    }
+}
+
+/// The breakpoints and watchpoints of a machine.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Breakpoints {
+    /// The breakpoint addresses, with their optional condition.
+    addresses: BTreeMap<u16, Option<Condition>>,
+
+    /// The conditions stopping wherever they become true.
+    conditions: Vec<Condition>,
+
+    /// The memory watchpoints.
+    memory: Vec<MemoryWatchpoint>,
+
+    /// The watched registers, one bit per register.
+    registers: u16,
+}
+
+impl Breakpoints {
+    /// Adds a breakpoint at `address`, replacing the existing one.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the instruction to stop before.
+    /// * `condition` - If set, the breakpoint only stops when the condition holds.
+    pub fn add_breakpoint(&mut self, address: u16, condition: Option<Condition>) {
+        self.addresses.insert(address, condition);
+    }
+
+    /// Removes the breakpoint at `address`, returns `false` if there was none.
+    pub fn remove_breakpoint(&mut self, address: u16) -> bool {
+        self.addresses.remove(&address).is_some()
+    }
+
+    /// Adds a condition, stopping after any instruction making it true.
+    ///
+    /// Only the first 64 conditions are checked.
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions.push(condition);
+    }
+
+    /// Adds a memory watchpoint.
+    pub fn add_memory_watchpoint(&mut self, watchpoint: MemoryWatchpoint) {
+        self.memory.push(watchpoint);
+    }
+
+    /// Watches the changes of the register `register` (0x0 to 0xF).
+    pub fn watch_register(&mut self, register: u8) {
+        self.registers |= 1 << (register & 0xF);
+    }
+
+    /// Stops watching the changes of the register `register` (0x0 to 0xF).
+    pub fn unwatch_register(&mut self, register: u8) {
+        self.registers &= !(1 << (register & 0xF));
+    }
+
+    /// Returns the breakpoint addresses, with their optional condition.
+    pub fn get_breakpoints(&self) -> impl Iterator<Item = (u16, Option<&Condition>)> {
+        self.addresses
+            .iter()
+            .map(|(&address, condition)| (address, condition.as_ref()))
+    }
+
+    /// Returns the conditions.
+    pub fn get_conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Returns the memory watchpoints.
+    pub fn get_memory_watchpoints(&self) -> &[MemoryWatchpoint] {
+        &self.memory
+    }
+
+    /// Returns the watched registers (0x0 to 0xF).
+    pub fn get_watched_registers(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..16).filter(|register| self.registers & 1 << register != 0)
+    }
+
+    /// Returns `true` if there are no breakpoints nor watchpoints.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+            && self.conditions.is_empty()
+            && self.memory.is_empty()
+            && self.registers == 0
+    }
+
+    /// Removes all the breakpoints and watchpoints.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Returns `true` if a memory watchpoint stops on a read (or a write if `write` is set) at `address`.
+    pub(super) fn watches_memory(&self, address: usize, write: bool) -> bool {
+        self.memory
+            .iter()
+            .any(|watchpoint| watchpoint.matches(address, write))
+    }
+}
+
+impl Chip8 {
+    /// Returns the breakpoints and watchpoints.
+    pub fn get_breakpoints(&self) -> &Breakpoints {
+        &self.breakpoints
+    }
+
+    /// Returns a mutable reference to the breakpoints and watchpoints.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::chip8::breakpoints::BreakReason;
+    /// use rust_chip8::{Chip8, StepOutcome};
+    ///
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom_bytes(&[0x60, 0x01, 0x61, 0x02]).unwrap(); // LD V0, 1; LD V1, 2
+    /// chip8.get_breakpoints_mut().add_breakpoint(0x202, None);
+    ///
+    /// assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    /// assert_eq!(
+    ///     chip8.step().unwrap(),
+    ///     StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x202 })
+    /// );
+    /// assert_eq!(chip8.get_registers()[1], 0);
+    ///
+    /// // Stepping again resumes
+    /// assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    /// assert_eq!(chip8.get_registers()[1], 2);
+    /// ```
+    pub fn get_breakpoints_mut(&mut self) -> &mut Breakpoints {
+        &mut self.breakpoints
+    }
+
+    /// Returns the breakpoint stopping before the instruction at `pc`, if any.
+    ///
+    /// A breakpoint that was just reported is skipped until the execution leaves its address,
+    /// so that stepping again resumes, even from an `Fx0A` repeating itself while it waits for
+    /// a key.
+    pub(super) fn check_breakpoint(&mut self) -> Option<BreakReason> {
+        let pc = self.pc;
+        if self.resume_from == Some(pc) {
+            return None;
+        }
+        self.resume_from = None;
+
+        let condition = self.breakpoints.addresses.get(&pc)?;
+        if !condition.is_none_or(|condition| condition.evaluate(self)) {
+            return None;
+        }
+
+        self.resume_from = Some(pc);
+
+        Some(BreakReason::Breakpoint { address: pc })
+    }
+
+    /// Returns the watchpoint or condition triggered by the last instruction, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `registers` - The registers before the instruction.
+    /// * `conditions` - The values of the conditions before the instruction, one bit per condition.
+    pub(super) fn check_watchpoints(
+        &mut self,
+        registers: &[u8; 16],
+        conditions: u64,
+    ) -> Option<BreakReason> {
+        if let Some(reason) = self.watch_hit.take() {
+            return Some(reason);
+        }
+
+        for register in self.breakpoints.get_watched_registers() {
+            let (old, new) = (
+                registers[register as usize],
+                self.registers[register as usize],
+            );
+            if old != new {
+                return Some(BreakReason::RegisterChanged { register, old, new });
+            }
+        }
+
+        self.breakpoints
+            .conditions
+            .iter()
+            .take(64)
+            .enumerate()
+            .find(|&(i, condition)| conditions & 1 << i == 0 && condition.evaluate(self))
+            .map(|(_, &condition)| BreakReason::Condition(condition))
+    }
+
+    /// Returns the values of the conditions, one bit per condition (the first 64 ones).
+    pub(super) fn evaluate_conditions(&self) -> u64 {
+        self.breakpoints
+            .conditions
+            .iter()
+            .take(64)
+            .enumerate()
+            .filter(|(_, condition)| condition.evaluate(self))
+            .fold(0, |bits, (i, _)| bits | 1 << i)
+    }
+}
+
+/// Parses a number, decimal or hexadecimal if prefixed with `0x` or `#`.
+pub fn parse_number(number: &str) -> Result<u16, String> {
+    let hex = number
+        .strip_prefix("0x")
+        .or_else(|| number.strip_prefix("0X"))
+        .or_else(|| number.strip_prefix('#'));
+
+    match hex {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => number.parse(),
+    }
+    .map_err(|_| format!("invalid number `{number}`"))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{BreakReason, Chip8Builder, StepOutcome};
+
+    #[test]
+    fn breakpoint_on_a_key_wait_is_reported_once() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0xF3, 0x0A, 0x12, 0x00]) // LD V3, K; JP 0x200
+            .build();
+        chip8.get_breakpoints_mut().add_breakpoint(0x200, None);
+
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x200 })
+        );
+        for _ in 0..10 {
+            assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+            assert!(chip8.is_waiting_for_key());
+        }
+
+        chip8.key_down(0x4);
+        chip8.key_up(0x4);
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.get_registers()[0x3], 0x4);
+        assert_eq!(chip8.get_pc(), 0x202);
+
+        // Coming back to the breakpoint reports it again
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x200 })
+        );
+    }
+
+    #[test]
+    fn conditional_breakpoint_stops_only_when_the_condition_holds() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x71, 0x01, 0x12, 0x00]) // ADD V1, 1; JP 0x200
+            .build();
+        let condition = "V1 == 2".parse().unwrap();
+        chip8
+            .get_breakpoints_mut()
+            .add_breakpoint(0x202, Some(condition));
+
+        for _ in 0..3 {
+            assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        }
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x202 })
+        );
+        assert_eq!(chip8.get_registers()[0x1], 2);
+    }
+
+    #[test]
+    fn condition_stops_once_when_it_becomes_true() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x73, 0x08, 0x73, 0x00, 0x73, 0x08]) // ADD V3, 8; ADD V3, 0; ADD V3, 8
+            .build();
+        let condition = "V3 >= 0x08".parse().unwrap();
+        chip8.get_breakpoints_mut().add_condition(condition);
+
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::Condition(condition))
+        );
+        assert_eq!(chip8.get_pc(), 0x202);
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    }
+
+    #[test]
+    fn memory_watchpoint_stops_on_a_write_over_the_code() {
+        // LD [I], V2 with I pointing at the instructions that follow it
+        let mut chip8 = Chip8Builder::new()
+            .registers([0x12, 0x04, 0xAA, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+            .index(0x202)
+            .memory(0x200, &[0xF2, 0x55, 0x00, 0xE0, 0x00, 0xE0])
+            .build();
+        let watchpoint = "0x203-0x20F:w".parse().unwrap();
+        chip8
+            .get_breakpoints_mut()
+            .add_memory_watchpoint(watchpoint);
+
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::MemoryWrite {
+                address: 0x203,
+                value: 0x04,
+            })
+        );
+        assert_eq!(chip8.get_memory()[0x202..0x205], [0x12, 0x04, 0xAA]);
+    }
+
+    #[test]
+    fn memory_watchpoint_ignores_other_accesses() {
+        // LD [I], V1 outside of the range, then LD V1, [I] and the fetches inside of it
+        let mut chip8 = Chip8Builder::new()
+            .index(0x300)
+            .memory(0x200, &[0xF1, 0x55, 0xF1, 0x65])
+            .build();
+        let writes = "0x200-0x2FF:w".parse().unwrap();
+        chip8.get_breakpoints_mut().add_memory_watchpoint(writes);
+        let reads = "0x200-0x2FF:r".parse().unwrap();
+        chip8.get_breakpoints_mut().add_memory_watchpoint(reads);
+
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    }
+
+    #[test]
+    fn register_watchpoint_stops_when_the_register_changes() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x65, 0x07, 0x65, 0x07, 0x64, 0x01]) // LD V5, 7; LD V5, 7; LD V4, 1
+            .build();
+        chip8.get_breakpoints_mut().watch_register(0x5);
+
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::RegisterChanged {
+                register: 0x5,
+                old: 0,
+                new: 7,
+            })
+        );
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+    }
+
+    #[test]
+    fn stepping_again_resumes_from_a_breakpoint() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x60, 0x01, 0x61, 0x02]) // LD V0, 1; LD V1, 2
+            .build();
+        chip8.get_breakpoints_mut().add_breakpoint(0x202, None);
+
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(
+            chip8.step().unwrap(),
+            StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x202 })
+        );
+        assert_eq!(chip8.get_registers()[0x1], 0);
+
+        assert_eq!(chip8.step().unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.get_registers()[0x1], 2);
+        assert_eq!(chip8.get_pc(), 0x204);
+    }
+
+    #[test]
+    fn loading_a_state_reports_the_breakpoint_again() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x60, 0x01]) // LD V0, 1
+            .build();
+        chip8.get_breakpoints_mut().add_breakpoint(0x200, None);
+        let state = chip8.save_state();
+
+        let hit = StepOutcome::BreakpointHit(BreakReason::Breakpoint { address: 0x200 });
+        assert_eq!(chip8.step().unwrap(), hit);
+        chip8.load_state(&state).unwrap();
+        assert_eq!(chip8.step().unwrap(), hit);
+    }
+}
@@ -0,0 +1,187 @@
+//! # CHIP-8 Builder
+//!
+//! [`Chip8Builder`] creates a [`Chip8`] in a given state (registers, memory, `I`, `PC`, keys...)
+//! without running a program to get there. It is meant for tests and tools that need to start
+//! the execution from the middle of a program.
+
+use super::{Chip8, Quirks, RandomSource};
+
+/// Builds a [`Chip8`] in a given state.
+///
+/// The machine starts as [`Chip8::new`] creates it, each method then overrides a part of its state.
+///
+/// # Example
+/// ```
+/// use rust_chip8::Chip8Builder;
+///
+/// let mut chip8 = Chip8Builder::new()
+///     .register(0x1, 0xFF)
+///     .register(0x2, 0x01)
+///     .instruction(0x8124) // ADD V1, V2
+///     .build();
+/// chip8.step().unwrap();
+///
+/// assert_eq!(chip8.get_registers()[0x1], 0x00);
+/// assert_eq!(chip8.get_registers()[0xF], 1);
+/// ```
+#[derive(Debug)]
+pub struct Chip8Builder {
+    /// The machine being built.
+    chip8: Chip8,
+}
+
+impl Chip8Builder {
+    /// Creates a builder for a machine with the default quirks, the fontset loaded and the
+    /// random number generator seeded with 0.
+    pub fn new() -> Self {
+        let mut chip8 = Chip8::new();
+        chip8.load_fontset();
+        chip8.seed_rng(0);
+
+        Self { chip8 }
+    }
+
+    /// Sets the quirks.
+    pub fn quirks(mut self, quirks: Quirks) -> Self {
+        self.chip8.quirks = quirks;
+        self
+    }
+
+    /// Sets the value of the register `Vx`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `x` is greater than `0xF`.
+    pub fn register(mut self, x: u8, value: u8) -> Self {
+        self.chip8.registers[x as usize] = value;
+        self
+    }
+
+    /// Sets the values of all the registers (`V0`-`VF`).
+    pub fn registers(mut self, registers: [u8; 16]) -> Self {
+        self.chip8.registers = registers;
+        self
+    }
+
+    /// Copies `bytes` to memory starting at `address`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bytes do not fit in memory.
+    pub fn memory(mut self, address: u16, bytes: &[u8]) -> Self {
+        let address = address as usize;
+        self.chip8.memory[address..address + bytes.len()].copy_from_slice(bytes);
+        self
+    }
+
+    /// Writes the opcode `opcode` at the program counter, to be executed by the next step.
+    ///
+    /// Set the program counter first with [`Chip8Builder::pc`] if it is not `START_ADDRESS`.
+    pub fn instruction(self, opcode: u16) -> Self {
+        let pc = self.chip8.pc;
+        self.memory(pc, &opcode.to_be_bytes())
+    }
+
+    /// Sets the index register `I`.
+    pub fn index(mut self, index: u16) -> Self {
+        self.chip8.index = index;
+        self
+    }
+
+    /// Sets the program counter.
+    pub fn pc(mut self, pc: u16) -> Self {
+        self.chip8.pc = pc;
+        self
+    }
+
+    /// Pushes return addresses on the stack, the last one being on top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the stack overflows.
+    pub fn stack(mut self, addresses: &[u16]) -> Self {
+        for &address in addresses {
+            self.chip8.stack[self.chip8.sp as usize] = address;
+            self.chip8.sp += 1;
+        }
+        self
+    }
+
+    /// Sets the keys held down.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key is greater than `0xF`.
+    pub fn keys(mut self, keys: &[u8]) -> Self {
+        for &key in keys {
+            self.chip8.keypad[key as usize] = true;
+        }
+        self
+    }
+
+    /// Sets the delay timer.
+    pub fn delay_timer(mut self, value: u8) -> Self {
+        self.chip8.delay_timer = value;
+        self
+    }
+
+    /// Sets the sound timer.
+    pub fn sound_timer(mut self, value: u8) -> Self {
+        self.chip8.sound_timer = value;
+        self
+    }
+
+    /// Switches to the SUPER-CHIP high-resolution display (clearing it).
+    pub fn hires(mut self, hires: bool) -> Self {
+        self.chip8.set_hires(hires);
+        self
+    }
+
+    /// Selects the XO-CHIP planes affected by drawing, clearing and scrolling.
+    pub fn planes(mut self, planes: u8) -> Self {
+        self.chip8.planes = planes & 0b11;
+        self
+    }
+
+    /// Sets the value of the pixel at (`x`, `y`), from 0 to 3 (see [`Chip8::get_display`]).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside of the display.
+    pub fn pixel(mut self, x: usize, y: usize, value: u8) -> Self {
+        let (width, height) = self.chip8.get_display_size();
+        assert!(x < width && y < height, "pixel ({x}, {y}) is off screen");
+
+        self.chip8.display[y * width + x] = value;
+        self
+    }
+
+    /// Sets the SUPER-CHIP RPL user flags.
+    pub fn rpl_flags(mut self, flags: [u8; 16]) -> Self {
+        self.chip8.rpl_flags = flags;
+        self
+    }
+
+    /// Seeds the random number generator.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.chip8.seed_rng(seed);
+        self
+    }
+
+    /// Replaces the random number source, e.g. with a [`FixedSequence`](super::rng::FixedSequence).
+    pub fn rng(mut self, rng: impl RandomSource + 'static) -> Self {
+        self.chip8.set_rng(rng);
+        self
+    }
+
+    /// Returns the machine.
+    pub fn build(self) -> Chip8 {
+        self.chip8
+    }
+}
+
+impl Default for Chip8Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,263 @@
+//! # CHIP-8 Disassembler
+//!
+//! This module turns opcodes back into the mnemonics used in the documentation of the instruction
+//! handlers (`LD Vx, Vy`, `DRW Vx, Vy, n`, ...), covering the CHIP-8, SUPER-CHIP and XO-CHIP
+//! instruction sets.
+//!
+//! Addresses are written as 3 hexadecimal digits (`0x2A0`), immediate bytes as 2 hexadecimal
+//! digits (`0x2A`) and nibbles in decimal. Opcodes that the emulator does not execute are written
+//! as raw data (`DW 0x5AB1`).
+//!
+//! [`disassemble_rom`] produces a listing of a whole ROM, naming the targets of the jumps and
+//! calls with generated labels (`label_2A0`, `sub_2C4`).
+
+use std::collections::BTreeMap;
+
+use super::Instruction;
+use crate::constants::chip8_constants::START_ADDRESS;
+
+/// Returns the mnemonic of an opcode.
+///
+/// The XO-CHIP `F000 nnnn` instruction is 4 bytes long, its address is not part of `opcode`:
+/// use [`disassemble_at`] to include it.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::disassembler::disassemble;
+///
+/// assert_eq!(disassemble(0x00E0), "CLS");
+/// assert_eq!(disassemble(0x6A2F), "LD VA, 0x2F");
+/// assert_eq!(disassemble(0xD125), "DRW V1, V2, 5");
+/// assert_eq!(disassemble(0x5AB1), "DW 0x5AB1");
+/// ```
+pub fn disassemble(opcode: u16) -> String {
+    let Instruction {
+        x, y, n, nn, nnn, ..
+    } = Instruction::new(opcode);
+
+    match opcode & 0xF000 {
+        0x0000 => match opcode {
+            0x00C0..=0x00CF => format!("SCD {n}"),
+            0x00D0..=0x00DF => format!("SCU {n}"),
+            0x00E0 => "CLS".to_string(),
+            0x00EE => "RET".to_string(),
+            0x00FB => "SCR".to_string(),
+            0x00FC => "SCL".to_string(),
+            0x00FD => "EXIT".to_string(),
+            0x00FE => "LOW".to_string(),
+            0x00FF => "HIGH".to_string(),
+            _ => data(opcode),
+        },
+        0x1000 => format!("JP 0x{nnn:03X}"),
+        0x2000 => format!("CALL 0x{nnn:03X}"),
+        0x3000 => format!("SE V{x:X}, 0x{nn:02X}"),
+        0x4000 => format!("SNE V{x:X}, 0x{nn:02X}"),
+        0x5000 => match n {
+            0x0 => format!("SE V{x:X}, V{y:X}"),
+            0x2 => format!("SAVE V{x:X} - V{y:X}"),
+            0x3 => format!("LOAD V{x:X} - V{y:X}"),
+            _ => data(opcode),
+        },
+        0x6000 => format!("LD V{x:X}, 0x{nn:02X}"),
+        0x7000 => format!("ADD V{x:X}, 0x{nn:02X}"),
+        0x8000 => match n {
+            0x0 => format!("LD V{x:X}, V{y:X}"),
+            0x1 => format!("OR V{x:X}, V{y:X}"),
+            0x2 => format!("AND V{x:X}, V{y:X}"),
+            0x3 => format!("XOR V{x:X}, V{y:X}"),
+            0x4 => format!("ADD V{x:X}, V{y:X}"),
+            0x5 => format!("SUB V{x:X}, V{y:X}"),
+            0x6 => format!("SHR V{x:X}, V{y:X}"),
+            0x7 => format!("SUBN V{x:X}, V{y:X}"),
+            0xE => format!("SHL V{x:X}, V{y:X}"),
+            _ => data(opcode),
+        },
+        0x9000 => format!("SNE V{x:X}, V{y:X}"),
+        0xA000 => format!("LD I, 0x{nnn:03X}"),
+        0xB000 => format!("JP V0, 0x{nnn:03X}"),
+        0xC000 => format!("RND V{x:X}, 0x{nn:02X}"),
+        0xD000 => format!("DRW V{x:X}, V{y:X}, {n}"),
+        0xE000 => match nn {
+            0x9E => format!("SKP V{x:X}"),
+            0xA1 => format!("SKNP V{x:X}"),
+            _ => data(opcode),
+        },
+        _ => match nn {
+            0x00 if x == 0 => "LD I, long".to_string(),
+            0x01 => format!("PLANE {}", x & 0b11),
+            0x02 if x == 0 => "AUDIO".to_string(),
+            0x07 => format!("LD V{x:X}, DT"),
+            0x0A => format!("LD V{x:X}, K"),
+            0x15 => format!("LD DT, V{x:X}"),
+            0x18 => format!("LD ST, V{x:X}"),
+            0x1E => format!("ADD I, V{x:X}"),
+            0x29 => format!("LD F, V{x:X}"),
+            0x30 => format!("LD HF, V{x:X}"),
+            0x33 => format!("LD B, V{x:X}"),
+            0x3A => format!("PITCH V{x:X}"),
+            0x55 => format!("LD [I], V{x:X}"),
+            0x65 => format!("LD V{x:X}, [I]"),
+            0x75 => format!("LD R, V{x:X}"),
+            0x85 => format!("LD V{x:X}, R"),
+            _ => data(opcode),
+        },
+    }
+}
+
+/// Returns the mnemonic of the instruction at `address` in `memory`, and its length in bytes.
+///
+/// Unlike [`disassemble`], the address of the 4 bytes long `F000 nnnn` instruction is included.
+/// Returns `None` if the instruction does not fit in `memory`.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::disassembler::disassemble_at;
+///
+/// let memory = [0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0];
+/// assert_eq!(disassemble_at(&memory, 0), Some(("LD I, long 0x1234".to_string(), 4)));
+/// assert_eq!(disassemble_at(&memory, 4), Some(("CLS".to_string(), 2)));
+/// assert_eq!(disassemble_at(&memory, 5), None);
+/// ```
+pub fn disassemble_at(memory: &[u8], address: usize) -> Option<(String, usize)> {
+    let word = |address: usize| {
+        let bytes = memory.get(address..address + 2)?;
+
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    };
+
+    match word(address)? {
+        0xF000 => {
+            let long = word(address + 2)?;
+
+            Some((format!("LD I, long 0x{long:04X}"), 4))
+        }
+        opcode => Some((disassemble(opcode), 2)),
+    }
+}
+
+/// A line of the listing of a ROM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListingLine {
+    /// The address of the instruction in memory.
+    pub address: u16,
+
+    /// The raw bytes of the instruction (2, or 4 for `F000 nnnn`, or 1 for a trailing odd byte).
+    pub bytes: Vec<u8>,
+
+    /// The mnemonic of the instruction, with the jump and call targets replaced by their label.
+    pub mnemonic: String,
+
+    /// The label of the address, if it is the target of a jump or a call.
+    pub label: Option<String>,
+}
+
+impl std::fmt::Display for ListingLine {
+    /// Formats the line as `ADDR  BYTES  MNEMONIC`, preceded by a `label:` line if any.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(label) = &self.label {
+            writeln!(f, "{label}:")?;
+        }
+
+        let bytes: String = self
+            .bytes
+            .iter()
+            .map(|byte| format!("{byte:02X}"))
+            .collect();
+        write!(f, "{:03X}  {bytes:<8}  {}", self.address, self.mnemonic)
+    }
+}
+
+/// Disassembles a whole ROM, loaded at [`START_ADDRESS`].
+///
+/// The ROM is read linearly, data is therefore disassembled as if it was code.
+/// The targets of `JP`, `CALL`, `JP V0` and `LD I` that start a line are given a label: `sub_NNN`
+/// for subroutines, `label_NNN` for the other jumps and `data_NNN` for `LD I`.
+/// The [`analysis`](super::analysis) module tells code and data apart instead.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::disassembler::disassemble_rom;
+///
+/// // CALL 0x206; JP 0x200; RET
+/// let listing = disassemble_rom(&[0x22, 0x06, 0x12, 0x00, 0x00, 0x00, 0x00, 0xEE]);
+/// let text: Vec<String> = listing.iter().map(ToString::to_string).collect();
+///
+/// assert_eq!(text[0], "label_200:\n200  2206      CALL sub_206");
+/// assert_eq!(text[1], "202  1200      JP label_200");
+/// assert_eq!(text[3], "sub_206:\n206  00EE      RET");
+/// ```
+pub fn disassemble_rom(rom: &[u8]) -> Vec<ListingLine> {
+    let origin = START_ADDRESS;
+
+    // First pass: split the ROM into instructions
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    while offset < rom.len() {
+        let (mnemonic, length) =
+            disassemble_at(rom, offset).unwrap_or_else(|| (format!("DB 0x{:02X}", rom[offset]), 1));
+        lines.push(ListingLine {
+            address: (origin + offset) as u16,
+            bytes: rom[offset..offset + length].to_vec(),
+            mnemonic,
+            label: None,
+        });
+        offset += length;
+    }
+
+    // Second pass: name the targets of the jumps and calls
+    add_labels(&mut lines, |_| true);
+
+    lines
+}
+
+/// Names the targets of the jumps, calls and `LD I` of the listing with generated labels:
+/// `sub_NNN` for subroutines, `label_NNN` for the other jumps and `data_NNN` for `LD I`.
+///
+/// Only the targets that start a line are named, and only the lines for which `is_instruction`
+/// returns `true` are considered as instructions.
+pub(crate) fn add_labels(lines: &mut [ListingLine], is_instruction: impl Fn(&ListingLine) -> bool) {
+    let target = |line: &ListingLine| {
+        if line.bytes.len() != 2 || !is_instruction(line) {
+            return None;
+        }
+
+        let opcode = u16::from_be_bytes([line.bytes[0], line.bytes[1]]);
+        let prefix = match opcode & 0xF000 {
+            0x2000 => "sub",
+            0x1000 | 0xB000 => "label",
+            0xA000 => "data",
+            _ => return None,
+        };
+
+        Some((opcode & 0x0FFF, prefix))
+    };
+
+    // Calls take precedence over jumps, which take precedence over data
+    let rank = |prefix: &str| ["data", "label", "sub"].iter().position(|p| *p == prefix);
+    let mut labels: BTreeMap<u16, &str> = BTreeMap::new();
+    for (address, prefix) in lines.iter().filter_map(target) {
+        let entry = labels.entry(address).or_insert(prefix);
+        if rank(prefix) > rank(entry) {
+            *entry = prefix;
+        }
+    }
+    labels.retain(|address, _| lines.iter().any(|line| line.address == *address));
+    let labels: BTreeMap<u16, String> = labels
+        .into_iter()
+        .map(|(address, prefix)| (address, format!("{prefix}_{address:03X}")))
+        .collect();
+
+    for line in lines.iter_mut() {
+        if let Some((address, _)) = target(line) {
+            if let Some(label) = labels.get(&address) {
+                line.mnemonic = line.mnemonic.replace(&format!("0x{address:03X}"), label);
+            }
+        }
+        line.label = labels.get(&line.address).cloned();
+    }
+}
+
+/// Returns the mnemonic of an opcode that is not an instruction.
+fn data(opcode: u16) -> String {
+    format!("DW 0x{opcode:04X}")
+}
@@ -0,0 +1,83 @@
+//! # CHIP-8 Errors
+//!
+//! This module defines [`Chip8Error`], the error returned when the emulated program does something
+//! the machine cannot execute (unknown opcode, stack fault, out of bounds memory access, ...).
+//! The frontend can use it to show a crash screen instead of aborting.
+
+use crate::constants::chip8_constants;
+
+/// An error raised while loading or executing a CHIP-8 program.
+#[derive(Debug)]
+pub enum Chip8Error {
+    /// The opcode at `pc` does not correspond to any known instruction.
+    UnknownOpcode {
+        /// The address of the faulting instruction.
+        pc: u16,
+
+        /// The raw 16-bit opcode.
+        opcode: u16,
+    },
+
+    /// A `CALL` was executed with all 16 stack levels already in use.
+    StackOverflow {
+        /// The address of the faulting instruction.
+        pc: u16,
+    },
+
+    /// A `RET` was executed with an empty stack.
+    StackUnderflow {
+        /// The address of the faulting instruction.
+        pc: u16,
+    },
+
+    /// An instruction tried to access memory outside of the RAM.
+    MemoryOutOfBounds {
+        /// The address that was accessed.
+        addr: usize,
+    },
+
+    /// The ROM does not fit in the memory available after `START_ADDRESS`.
+    RomTooLarge {
+        /// The size of the ROM in bytes.
+        size: usize,
+    },
+
+    /// The ROM file could not be read.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for Chip8Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {opcode:04X} at {pc:03X}")
+            }
+            Self::StackOverflow { pc } => write!(f, "stack overflow at {pc:03X}"),
+            Self::StackUnderflow { pc } => write!(f, "stack underflow at {pc:03X}"),
+            Self::MemoryOutOfBounds { addr } => {
+                write!(f, "memory access out of bounds at {addr:X}")
+            }
+            Self::RomTooLarge { size } => write!(
+                f,
+                "ROM is {size} bytes, at most {} bytes can be loaded",
+                chip8_constants::RAM_SIZE - chip8_constants::START_ADDRESS
+            ),
+            Self::Io(err) => write!(f, "failed to read ROM: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Chip8Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Chip8Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -9,7 +9,7 @@
 //!
 //! | Opcode Range | Functionality                                            |
 //! |--------------|--------------                                            |
-//! | `0x0XXX`     | System instructions (clear screen, return, etc.)         |
+//! | `0x0XXX`     | System instructions (clear screen, return, scroll, etc.) |
 //! | `0x1XXX`     | Jump instructions (`JP addr`)                            |
 //! | `0x2XXX`     | Subroutine calls (`CALL addr`)                           |
 //! | `0x3XXX`     | Skip instructions (`SE Vx, byte`)                        |
@@ -37,8 +37,15 @@
 #![allow(non_snake_case)]
 
 /// System instructions (`0x0XXX`)
+/// - `00Cn` - Scroll down n pixels (`SCD nibble`)
+/// - `00Dn` - Scroll up n pixels (`SCU nibble`)
 /// - `00E0` - Clear screen (`CLS`)
 /// - `00EE` - Return from subroutine (`RET`)
+/// - `00FB` - Scroll right 4 pixels (`SCR`)
+/// - `00FC` - Scroll left 4 pixels (`SCL`)
+/// - `00FD` - Exit the interpreter (`EXIT`)
+/// - `00FE` - Low-resolution mode (`LOW`)
+/// - `00FF` - High-resolution mode (`HIGH`)
 pub mod _0xxx;
 
 /// Jump instructions (`0x1XXX`)
@@ -59,6 +66,8 @@ pub mod _4xxx;
 
 /// Skip if registers match (`0x5XXX`)
 /// - `5xy0` - Skip next instruction if Vx == Vy (`SE Vx, Vy`)
+/// - `5xy2` - Store Vx to Vy in memory (`SAVE Vx - Vy`)
+/// - `5xy3` - Load Vx to Vy from memory (`LOAD Vx - Vy`)
 pub mod _5xxx;
 
 /// Load immediate values (`0x6XXX`)
@@ -99,6 +108,7 @@ pub mod _Cxxx;
 
 /// Display/draw instructions (`0xDXXX`)
 /// - `Dxyn` - Draw sprite at `(Vx, Vy)` with `n` height (`DRW Vx, Vy, nibble`)
+/// - `Dxy0` - Draw 16x16 sprite at `(Vx, Vy)` (`DRW Vx, Vy, 0`)
 pub mod _Dxxx;
 
 /// Key input instructions (`0xEXXX`)
@@ -107,13 +117,20 @@ pub mod _Dxxx;
 pub mod _Exxx;
 
 /// Miscellaneous operations (`0xFXXX`)
+/// - `F000 nnnn` - Load a 16-bit address into the index register (`LD I, long addr`)
+/// - `Fn01` - Select the drawing planes (`PLANE n`)
+/// - `F002` - Load the audio pattern (`AUDIO`)
 /// - `Fx07` - Get delay timer value (`LD Vx, DT`)
 /// - `Fx0A` - Wait for key press (`LD Vx, K`)
 /// - `Fx15` - Set delay timer (`LD DT, Vx`)
 /// - `Fx18` - Set sound timer (`LD ST, Vx`)
 /// - `Fx1E` - Add to index register (`ADD I, Vx`)
 /// - `Fx29` - Font character (`LD F, Vx`)
+/// - `Fx30` - Big font character (`LD HF, Vx`)
 /// - `Fx33` - Binary-coded decimal conversion (`LD B, Vx`)
+/// - `Fx3A` - Set the audio pitch (`PITCH Vx`)
 /// - `Fx55` - Store registers in memory (`LD [I], Vx`)
 /// - `Fx65` - Load registers from memory (`LD Vx, [I]`)
+/// - `Fx75` - Store registers in RPL flags (`LD R, Vx`)
+/// - `Fx85` - Load registers from RPL flags (`LD Vx, R`)
 pub mod _Fxxx;
@@ -1,25 +1,231 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
+
+/// 0x00Cn - SCD nibble
+///
+/// Scroll the display down by n pixels (SUPER-CHIP).
+fn SCD_nibble(chip8: &mut Chip8, instruction: Instruction) {
+    chip8.scroll(0, instruction.n as isize);
+}
+
+/// 0x00Dn - SCU nibble
+///
+/// Scroll the display up by n pixels (XO-CHIP).
+fn SCU_nibble(chip8: &mut Chip8, instruction: Instruction) {
+    chip8.scroll(0, -(instruction.n as isize));
+}
 
 /// 0x00E0 - CLS
 ///
-/// Clear the display.
-const fn CLS(chip8: &mut Chip8) {
-    chip8.display = [false; 64 * 32];
+/// Clear the display (only the selected planes with XO-CHIP).
+fn CLS(chip8: &mut Chip8) {
+    let planes = chip8.planes;
+
+    for pixel in chip8.display.iter_mut() {
+        *pixel &= !planes;
+    }
 }
 
 /// 0x00EE - RET
 ///
 /// Return from a subroutine.
-fn RET(chip8: &mut Chip8) {
-    chip8.sp = chip8.sp.wrapping_sub(1);
+fn RET(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+    if chip8.sp == 0 {
+        return Err(Chip8Error::StackUnderflow {
+            pc: chip8.pc.wrapping_sub(2),
+        });
+    }
+
+    chip8.sp -= 1;
     chip8.pc = chip8.stack[chip8.sp as usize];
+
+    Ok(())
+}
+
+/// 0x00FB - SCR
+///
+/// Scroll the display right by 4 pixels (SUPER-CHIP).
+fn SCR(chip8: &mut Chip8) {
+    chip8.scroll(4, 0);
+}
+
+/// 0x00FC - SCL
+///
+/// Scroll the display left by 4 pixels (SUPER-CHIP).
+fn SCL(chip8: &mut Chip8) {
+    chip8.scroll(-4, 0);
+}
+
+/// 0x00FD - EXIT
+///
+/// Exit the interpreter (SUPER-CHIP).
+const fn EXIT(chip8: &mut Chip8) {
+    chip8.halted = true;
+}
+
+/// 0x00FE - LOW
+///
+/// Switch to the low-resolution 64x32 display (SUPER-CHIP).
+fn LOW(chip8: &mut Chip8) {
+    chip8.set_hires(false);
+}
+
+/// 0x00FF - HIGH
+///
+/// Switch to the high-resolution 128x64 display (SUPER-CHIP).
+fn HIGH(chip8: &mut Chip8) {
+    chip8.set_hires(true);
 }
 
 /// Handle `0x0???` opcodes.
-pub fn _0xxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _0xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     match instruction.opcode {
+        0x00C0..=0x00CF => SCD_nibble(chip8, instruction),
+        0x00D0..=0x00DF => SCU_nibble(chip8, instruction),
         0x00E0 => CLS(chip8),
-        0x00EE => RET(chip8),
-        _ => panic!("Unknown opcode [0x0???]: {:X}", instruction.opcode),
+        0x00EE => return RET(chip8),
+        0x00FB => SCR(chip8),
+        0x00FC => SCL(chip8),
+        0x00FD => EXIT(chip8),
+        0x00FE => LOW(chip8),
+        0x00FF => HIGH(chip8),
+        _ => {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: chip8.pc.wrapping_sub(2),
+                opcode: instruction.opcode,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Chip8Error};
+
+    #[test]
+    fn cls_clears_the_selected_planes() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(0, 0, 0b11)
+            .pixel(63, 31, 0b01)
+            .planes(0b01)
+            .instruction(0x00E0)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_display()[0], 0b10);
+        assert!(chip8.get_display()[1..].iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn ret_pops_the_return_address() {
+        let mut chip8 = Chip8Builder::new()
+            .stack(&[0x300, 0x400])
+            .instruction(0x00EE)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0x400);
+        assert_eq!(chip8.get_sp(), 1);
+    }
+
+    #[test]
+    fn ret_with_an_empty_stack_underflows() {
+        let mut chip8 = Chip8Builder::new().instruction(0x00EE).build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(err, Chip8Error::StackUnderflow { pc: 0x200 }));
+    }
+
+    #[test]
+    fn scroll_down_and_up() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(5, 0, 1)
+            .instruction(0x00C3) // SCD 3
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_display()[3 * 64 + 5], 1);
+        assert_eq!(chip8.get_display()[5], 0);
+
+        let mut chip8 = Chip8Builder::new()
+            .pixel(5, 2, 1)
+            .instruction(0x00D2) // SCU 2
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_display()[5], 1);
+        assert_eq!(chip8.get_display()[2 * 64 + 5], 0);
+    }
+
+    #[test]
+    fn scroll_right_and_left_drop_the_pixels_past_the_edges() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(0, 0, 1)
+            .pixel(62, 0, 1)
+            .instruction(0x00FB) // SCR
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_display()[4], 1);
+        assert_eq!(chip8.get_display().iter().filter(|&&p| p != 0).count(), 1);
+
+        let mut chip8 = Chip8Builder::new()
+            .pixel(1, 0, 1)
+            .pixel(10, 0, 1)
+            .instruction(0x00FC) // SCL
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_display()[6], 1);
+        assert_eq!(chip8.get_display().iter().filter(|&&p| p != 0).count(), 1);
+    }
+
+    #[test]
+    fn scroll_only_moves_the_selected_planes() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(0, 0, 0b11)
+            .planes(0b10)
+            .instruction(0x00C1) // SCD 1
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_display()[0], 0b01);
+        assert_eq!(chip8.get_display()[64], 0b10);
+    }
+
+    #[test]
+    fn exit_halts_the_machine() {
+        let mut chip8 = Chip8Builder::new().instruction(0x00FD).build();
+        chip8.step().unwrap();
+
+        assert!(chip8.is_halted());
+    }
+
+    #[test]
+    fn high_and_low_switch_the_resolution() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(0, 0, 1)
+            .memory(0x200, &[0x00, 0xFF, 0x00, 0xFE]) // HIGH; LOW
+            .build();
+
+        chip8.step().unwrap();
+        assert!(chip8.is_hires());
+        assert_eq!(chip8.get_display().len(), 128 * 64);
+        assert!(chip8.get_display().iter().all(|&pixel| pixel == 0));
+
+        chip8.step().unwrap();
+        assert!(!chip8.is_hires());
+        assert_eq!(chip8.get_display_size(), (64, 32));
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chip8 = Chip8Builder::new().instruction(0x0123).build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(
+            err,
+            Chip8Error::UnknownOpcode {
+                pc: 0x200,
+                opcode: 0x0123
+            }
+        ));
     }
 }
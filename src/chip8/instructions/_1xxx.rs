@@ -1,4 +1,4 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x1nnn - JP addr
 ///
@@ -8,6 +8,22 @@ fn JP_addr(chip8: &mut Chip8, instruction: Instruction) {
 }
 
 /// 0x1nnn - JP addr
-pub fn _1xxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _1xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     JP_addr(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn jp_addr() {
+        let mut chip8 = Chip8Builder::new().instruction(0x1ABC).build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0xABC);
+        assert_eq!(chip8.get_sp(), 0);
+    }
 }
@@ -1,15 +1,50 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x2nnn - CALL addr
 ///
 /// Call subroutine at address nnn.
-fn CALL_addr(chip8: &mut Chip8, instruction: Instruction) {
+fn CALL_addr(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    if chip8.sp as usize >= chip8.stack.len() {
+        return Err(Chip8Error::StackOverflow {
+            pc: chip8.pc.wrapping_sub(2),
+        });
+    }
+
     chip8.stack[chip8.sp as usize] = chip8.pc;
-    chip8.sp = chip8.sp.wrapping_add(1);
+    chip8.sp += 1;
     chip8.pc = instruction.nnn;
+
+    Ok(())
 }
 
 /// 0x2nnn - CALL addr
-pub fn _2xxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _2xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     CALL_addr(chip8, instruction)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Chip8Error};
+
+    #[test]
+    fn call_pushes_the_return_address() {
+        let mut chip8 = Chip8Builder::new().instruction(0x2ABC).build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0xABC);
+        assert_eq!(chip8.get_sp(), 1);
+        assert_eq!(chip8.get_stack()[0], 0x202);
+    }
+
+    #[test]
+    fn call_with_a_full_stack_overflows() {
+        let mut chip8 = Chip8Builder::new()
+            .stack(&[0x300; 16])
+            .instruction(0x2ABC)
+            .build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(err, Chip8Error::StackOverflow { pc: 0x200 }));
+        assert_eq!(chip8.get_sp(), 16);
+    }
+}
@@ -1,15 +1,49 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x3xnn - SE Vx, byte
 ///
 /// Skip next instruction if Vx = nn.
 fn SE_Vx_byte(chip8: &mut Chip8, instruction: Instruction) {
     if chip8.registers[instruction.x as usize] == instruction.nn {
-        chip8.pc = chip8.pc.wrapping_add(2);
+        chip8.skip_next_instruction();
     }
 }
 
 /// 0x3xnn - SE Vx, byte
-pub fn _3xxx(chip8: &mut Chip8, instruction: Instruction) {
-    SE_Vx_byte(chip8, instruction)
+pub fn _3xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    SE_Vx_byte(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn se_vx_byte_skips_if_equal() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x3, 0x42)
+            .instruction(0x3342)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x3, 0x41)
+            .instruction(0x3342)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
+
+    #[test]
+    fn skips_the_whole_long_load() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x30, 0x00, 0xF0, 0x00, 0x12, 0x34]) // SE V0, 0; LD I, long 0x1234
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0x206);
+    }
 }
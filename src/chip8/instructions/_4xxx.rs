@@ -1,15 +1,39 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x4xnn - SNE Vx, byte
 ///
 /// Skip next instruction if Vx != nn.
 fn SNE_Vx_byte(chip8: &mut Chip8, instruction: Instruction) {
     if chip8.registers[instruction.x as usize] != instruction.nn {
-        chip8.pc = chip8.pc.wrapping_add(2);
+        chip8.skip_next_instruction();
     }
 }
 
 /// 0x4xnn - SNE Vx, byte
-pub fn _4xxx(chip8: &mut Chip8, instruction: Instruction) {
-    SNE_Vx_byte(chip8, instruction)
+pub fn _4xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    SNE_Vx_byte(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn sne_vx_byte_skips_if_not_equal() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0xA, 0x10)
+            .instruction(0x4A11)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0xA, 0x11)
+            .instruction(0x4A11)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
 }
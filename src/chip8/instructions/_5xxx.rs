@@ -1,15 +1,145 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x5xy0 - SE Vx, Vy
 ///
 /// Skip next instruction if Vx = Vy.
 fn SE_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
     if chip8.registers[instruction.x as usize] == chip8.registers[instruction.y as usize] {
-        chip8.pc = chip8.pc.wrapping_add(2);
+        chip8.skip_next_instruction();
     }
 }
 
-/// 0x5xy0 - SE Vx, Vy
-pub fn _5xxx(chip8: &mut Chip8, instruction: Instruction) {
-    SE_Vx_Vy(chip8, instruction)
+/// Returns the registers from Vx to Vy, in order (descending if x > y).
+fn register_range(instruction: Instruction) -> Vec<usize> {
+    let (x, y) = (instruction.x as usize, instruction.y as usize);
+
+    if x <= y {
+        (x..=y).collect()
+    } else {
+        (y..=x).rev().collect()
+    }
+}
+
+/// 0x5xy2 - SAVE Vx - Vy
+///
+/// Store Vx to Vy in memory starting at address I, I is left unchanged (XO-CHIP).
+fn SAVE_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    for (i, register) in register_range(instruction).into_iter().enumerate() {
+        chip8.write_memory(chip8.index as usize + i, chip8.registers[register])?;
+    }
+
+    Ok(())
+}
+
+/// 0x5xy3 - LOAD Vx - Vy
+///
+/// Read Vx to Vy from memory starting at address I, I is left unchanged (XO-CHIP).
+fn LOAD_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    for (i, register) in register_range(instruction).into_iter().enumerate() {
+        chip8.registers[register] = chip8.read_memory(chip8.index as usize + i)?;
+    }
+
+    Ok(())
+}
+
+/// Handle `0x5???` opcodes.
+pub fn _5xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    match instruction.n {
+        0x0 => SE_Vx_Vy(chip8, instruction),
+        0x2 => return SAVE_Vx_Vy(chip8, instruction),
+        0x3 => return LOAD_Vx_Vy(chip8, instruction),
+        _ => {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: chip8.pc.wrapping_sub(2),
+                opcode: instruction.opcode,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Chip8Error};
+
+    #[test]
+    fn se_vx_vy_skips_if_equal() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 7)
+            .register(0x2, 7)
+            .instruction(0x5120)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 7)
+            .instruction(0x5120)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
+
+    #[test]
+    fn save_stores_the_range_without_moving_i() {
+        let mut chip8 = Chip8Builder::new()
+            .registers([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15])
+            .index(0x400)
+            .instruction(0x5242) // SAVE V2 - V4
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_memory()[0x400..0x404], [2, 3, 4, 0]);
+        assert_eq!(chip8.get_index(), 0x400);
+    }
+
+    #[test]
+    fn save_in_descending_order() {
+        let mut chip8 = Chip8Builder::new()
+            .registers([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15])
+            .index(0x400)
+            .instruction(0x5422) // SAVE V4 - V2
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_memory()[0x400..0x403], [4, 3, 2]);
+    }
+
+    #[test]
+    fn load_reads_the_range_without_moving_i() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x400, &[0xAA, 0xBB, 0xCC])
+            .index(0x400)
+            .instruction(0x5DF3) // LOAD VD - VF
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_registers()[0xD..], [0xAA, 0xBB, 0xCC]);
+        assert_eq!(chip8.get_index(), 0x400);
+    }
+
+    #[test]
+    fn save_past_the_end_of_memory() {
+        let mut chip8 = Chip8Builder::new()
+            .index(0xFFFF)
+            .instruction(0x5012) // SAVE V0 - V1
+            .build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(
+            err,
+            Chip8Error::MemoryOutOfBounds { addr: 0x10000 }
+        ));
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chip8 = Chip8Builder::new().instruction(0x5121).build();
+
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode { opcode: 0x5121, .. })
+        ));
+    }
 }
@@ -1,4 +1,4 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x6xnn - LD Vx, byte
 ///
@@ -8,6 +8,22 @@ fn LD_Vx_byte(chip8: &mut Chip8, instruction: Instruction) {
 }
 
 /// 0x6xnn - LD Vx, byte
-pub fn _6xxx(chip8: &mut Chip8, instruction: Instruction) {
-    LD_Vx_byte(chip8, instruction)
+pub fn _6xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    LD_Vx_byte(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn ld_vx_byte() {
+        let mut chip8 = Chip8Builder::new().instruction(0x6F42).build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_registers()[0xF], 0x42);
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
 }
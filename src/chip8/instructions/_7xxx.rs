@@ -1,4 +1,4 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x7xnn - ADD Vx, byte
 ///
@@ -9,6 +9,26 @@ fn ADD_Vx_byte(chip8: &mut Chip8, instruction: Instruction) {
 }
 
 /// 0x6xnn - LD Vx, byte
-pub fn _7xxx(chip8: &mut Chip8, instruction: Instruction) {
-    ADD_Vx_byte(chip8, instruction)
+pub fn _7xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    ADD_Vx_byte(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn add_vx_byte_wraps_without_touching_vf() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x2, 0xFF)
+            .register(0xF, 0x55)
+            .instruction(0x7202)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_registers()[0x2], 0x01);
+        assert_eq!(chip8.get_registers()[0xF], 0x55);
+    }
 }
@@ -1,4 +1,4 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x8xy0 - LD Vx, Vy
 ///
@@ -10,22 +10,37 @@ fn LD_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
 /// 0x8xy1 - OR Vx, Vy
 ///
 /// Vx = Vx | Vy.
+/// With the `vf_reset` quirk, VF is set to 0.
 fn OR_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
     chip8.registers[instruction.x as usize] |= chip8.registers[instruction.y as usize];
+
+    if chip8.quirks.vf_reset {
+        chip8.registers[0xF] = 0;
+    }
 }
 
 /// 0x8xy2 - AND Vx, Vy
 ///
 /// Vx = Vx & Vy.
+/// With the `vf_reset` quirk, VF is set to 0.
 fn AND_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
     chip8.registers[instruction.x as usize] &= chip8.registers[instruction.y as usize];
+
+    if chip8.quirks.vf_reset {
+        chip8.registers[0xF] = 0;
+    }
 }
 
 /// 0x8xy3 - XOR Vx, Vy
 ///
 /// Vx = Vx ^ Vy.
+/// With the `vf_reset` quirk, VF is set to 0.
 fn XOR_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
     chip8.registers[instruction.x as usize] ^= chip8.registers[instruction.y as usize];
+
+    if chip8.quirks.vf_reset {
+        chip8.registers[0xF] = 0;
+    }
 }
 
 /// 0x8xy4 - ADD Vx, Vy
@@ -54,12 +69,17 @@ fn SUB_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
 
 /// 0x8xy6 - SHR Vx
 ///
-/// Vx = Vx >> 1.
-/// Set VF to the least significant bit of Vx before the shift.
+/// Vx = Vy >> 1 (Vx = Vx >> 1 with the `shift` quirk).
+/// Set VF to the least significant bit of the shifted value.
 fn SHR_Vx(chip8: &mut Chip8, instruction: Instruction) {
-    let temp = chip8.registers[instruction.x as usize];
+    let source = if chip8.quirks.shift {
+        instruction.x
+    } else {
+        instruction.y
+    };
+    let temp = chip8.registers[source as usize];
 
-    chip8.registers[instruction.x as usize] >>= 1;
+    chip8.registers[instruction.x as usize] = temp >> 1;
     chip8.registers[0xF] = temp & 0x1;
 }
 
@@ -76,17 +96,22 @@ fn SUBN_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
 
 /// 0x8xyE - SHL Vx
 ///
-/// Vx = Vx << 1.
-/// Set VF to the most significant bit of Vx before the shift.
+/// Vx = Vy << 1 (Vx = Vx << 1 with the `shift` quirk).
+/// Set VF to the most significant bit of the shifted value.
 fn SHL_Vx(chip8: &mut Chip8, instruction: Instruction) {
-    let temp = chip8.registers[instruction.x as usize];
+    let source = if chip8.quirks.shift {
+        instruction.x
+    } else {
+        instruction.y
+    };
+    let temp = chip8.registers[source as usize];
 
-    chip8.registers[instruction.x as usize] <<= 1;
+    chip8.registers[instruction.x as usize] = temp << 1;
     chip8.registers[0xF] = (temp & (1 << 7)) >> 7;
 }
 
 /// Handle 0x8??? opcodes.
-pub fn _8xxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _8xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     match instruction.n {
         0x0 => LD_Vx_Vy(chip8, instruction),
         0x1 => OR_Vx_Vy(chip8, instruction),
@@ -98,6 +123,165 @@ pub fn _8xxx(chip8: &mut Chip8, instruction: Instruction) {
         0x7 => SUBN_Vx_Vy(chip8, instruction),
         0xE => SHL_Vx(chip8, instruction),
 
-        _ => panic!("Unknown opcode [0x8???]: {:X}", instruction.opcode),
+        _ => {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: chip8.pc.wrapping_sub(2),
+                opcode: instruction.opcode,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Chip8Error, Quirks};
+
+    /// Executes `opcode` with the given registers and quirks, returns the registers.
+    fn run(opcode: u16, registers: &[(u8, u8)], quirks: Quirks) -> [u8; 16] {
+        let mut builder = Chip8Builder::new().quirks(quirks).instruction(opcode);
+        for &(x, value) in registers {
+            builder = builder.register(x, value);
+        }
+
+        let mut chip8 = builder.build();
+        chip8.step().unwrap();
+        *chip8.get_registers()
+    }
+
+    #[test]
+    fn ld_vx_vy() {
+        let registers = run(0x8120, &[(0x2, 0x33)], Quirks::default());
+
+        assert_eq!(registers[0x1], 0x33);
+    }
+
+    #[test]
+    fn logic_operations() {
+        let quirks = Quirks::XO_CHIP;
+        let operands = [(0x1, 0b1100), (0x2, 0b1010), (0xF, 0x77)];
+
+        assert_eq!(run(0x8121, &operands, quirks)[0x1], 0b1110);
+        assert_eq!(run(0x8122, &operands, quirks)[0x1], 0b1000);
+        assert_eq!(run(0x8123, &operands, quirks)[0x1], 0b0110);
+        assert_eq!(run(0x8123, &operands, quirks)[0xF], 0x77);
+    }
+
+    #[test]
+    fn logic_operations_reset_vf_with_the_vf_reset_quirk() {
+        let quirks = Quirks::COSMAC_VIP;
+        let operands = [(0x1, 0b1100), (0x2, 0b1010), (0xF, 0x77)];
+
+        for opcode in [0x8121, 0x8122, 0x8123] {
+            assert_eq!(run(opcode, &operands, quirks)[0xF], 0);
+        }
+    }
+
+    #[test]
+    fn add_vx_vy_sets_the_carry() {
+        let registers = run(0x8124, &[(0x1, 0xF0), (0x2, 0x20)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0x10, 1));
+
+        let registers = run(
+            0x8124,
+            &[(0x1, 0x10), (0x2, 0x20), (0xF, 1)],
+            Quirks::default(),
+        );
+        assert_eq!((registers[0x1], registers[0xF]), (0x30, 0));
+    }
+
+    #[test]
+    fn add_vf_vy_keeps_the_carry_over_the_result() {
+        let registers = run(0x8F14, &[(0xF, 0xF0), (0x1, 0x20)], Quirks::default());
+        assert_eq!(registers[0xF], 1);
+
+        let registers = run(0x8F14, &[(0xF, 0x10), (0x1, 0x20)], Quirks::default());
+        assert_eq!(registers[0xF], 0);
+    }
+
+    #[test]
+    fn add_vx_vf_reads_vf_before_writing_the_carry() {
+        let registers = run(0x81F4, &[(0x1, 0x01), (0xF, 0xFF)], Quirks::default());
+
+        assert_eq!((registers[0x1], registers[0xF]), (0x00, 1));
+    }
+
+    #[test]
+    fn sub_vx_vy_sets_not_borrow() {
+        let registers = run(0x8125, &[(0x1, 0x30), (0x2, 0x10)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0x20, 1));
+
+        let registers = run(0x8125, &[(0x1, 0x10), (0x2, 0x30)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0xE0, 0));
+
+        let registers = run(0x8125, &[(0x1, 0x10), (0x2, 0x10)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0x00, 1));
+    }
+
+    #[test]
+    fn sub_vf_vy_keeps_the_flag_over_the_result() {
+        let registers = run(0x8F15, &[(0xF, 0x30), (0x1, 0x10)], Quirks::default());
+        assert_eq!(registers[0xF], 1);
+
+        let registers = run(0x8F15, &[(0xF, 0x10), (0x1, 0x30)], Quirks::default());
+        assert_eq!(registers[0xF], 0);
+    }
+
+    #[test]
+    fn subn_vx_vy_sets_not_borrow() {
+        let registers = run(0x8127, &[(0x1, 0x10), (0x2, 0x30)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0x20, 1));
+
+        let registers = run(0x8127, &[(0x1, 0x30), (0x2, 0x10)], Quirks::default());
+        assert_eq!((registers[0x1], registers[0xF]), (0xE0, 0));
+    }
+
+    #[test]
+    fn subn_vf_vy_keeps_the_flag_over_the_result() {
+        let registers = run(0x8F17, &[(0xF, 0x10), (0x1, 0x30)], Quirks::default());
+
+        assert_eq!(registers[0xF], 1);
+    }
+
+    #[test]
+    fn shifts_use_vy() {
+        let quirks = Quirks::COSMAC_VIP;
+
+        let registers = run(0x8126, &[(0x1, 0xFF), (0x2, 0b101)], quirks);
+        assert_eq!((registers[0x1], registers[0xF]), (0b10, 1));
+
+        let registers = run(0x812E, &[(0x1, 0xFF), (0x2, 0b0100_0000)], quirks);
+        assert_eq!((registers[0x1], registers[0xF]), (0b1000_0000, 0));
+    }
+
+    #[test]
+    fn shifts_use_vx_with_the_shift_quirk() {
+        let quirks = Quirks::SUPER_CHIP;
+
+        let registers = run(0x8126, &[(0x1, 0b110), (0x2, 0xFF)], quirks);
+        assert_eq!((registers[0x1], registers[0xF]), (0b11, 0));
+
+        let registers = run(0x812E, &[(0x1, 0b1000_0001), (0x2, 0)], quirks);
+        assert_eq!((registers[0x1], registers[0xF]), (0b10, 1));
+    }
+
+    #[test]
+    fn shifting_vf_keeps_the_flag_over_the_result() {
+        let registers = run(0x8F06, &[(0xF, 0b11)], Quirks::SUPER_CHIP);
+        assert_eq!(registers[0xF], 1);
+
+        let registers = run(0x8F0E, &[(0xF, 0b0111_1111)], Quirks::SUPER_CHIP);
+        assert_eq!(registers[0xF], 0);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chip8 = Chip8Builder::new().instruction(0x8128).build();
+
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode { opcode: 0x8128, .. })
+        ));
     }
 }
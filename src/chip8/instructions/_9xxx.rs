@@ -1,15 +1,40 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0x9xy0 - SNE Vx, Vy
 ///
 /// Skip next instruction if Vx != Vy.
 fn SNE_Vx_Vy(chip8: &mut Chip8, instruction: Instruction) {
     if chip8.registers[instruction.x as usize] != chip8.registers[instruction.y as usize] {
-        chip8.pc = chip8.pc.wrapping_add(2);
+        chip8.skip_next_instruction();
     }
 }
 
 /// 0x9xy0 - SNE Vx, Vy
-pub fn _9xxx(chip8: &mut Chip8, instruction: Instruction) {
-    SNE_Vx_Vy(chip8, instruction)
+pub fn _9xxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    SNE_Vx_Vy(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn sne_vx_vy_skips_if_not_equal() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 1)
+            .instruction(0x9120)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 1)
+            .register(0x2, 1)
+            .instruction(0x9120)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
 }
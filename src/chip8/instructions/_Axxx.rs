@@ -1,4 +1,4 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0xAnnn - LD I, addr
 ///
@@ -8,6 +8,21 @@ const fn LD_I_addr(chip8: &mut Chip8, instruction: Instruction) {
 }
 
 /// 0xAnnn - LD I, addr
-pub fn _Axxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _Axxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     LD_I_addr(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn ld_i_addr() {
+        let mut chip8 = Chip8Builder::new().instruction(0xA123).build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_index(), 0x123);
+    }
 }
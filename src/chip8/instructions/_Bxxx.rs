@@ -1,13 +1,49 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0xBnnn - JP V0, addr
 ///
 /// Jump to location nnn + V0.
+/// With the `jump` quirk, jump to location nnn + Vx instead (`JP Vx, addr`).
 pub fn JP_V0_addr(chip8: &mut Chip8, instruction: Instruction) {
-    chip8.pc = instruction.nnn + chip8.registers[0] as u16;
+    let register = if chip8.quirks.jump { instruction.x } else { 0 };
+
+    chip8.pc = instruction.nnn + chip8.registers[register as usize] as u16;
 }
 
 /// 0xBnnn - JP V0, addr
-pub fn _Bxxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _Bxxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     JP_V0_addr(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Quirks};
+
+    #[test]
+    fn jp_v0_addr() {
+        let mut chip8 = Chip8Builder::new()
+            .quirks(Quirks::COSMAC_VIP)
+            .register(0x0, 0x10)
+            .register(0x3, 0x20)
+            .instruction(0xB300)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0x310);
+    }
+
+    #[test]
+    fn jp_vx_addr_with_the_jump_quirk() {
+        let mut chip8 = Chip8Builder::new()
+            .quirks(Quirks::SUPER_CHIP)
+            .register(0x0, 0x10)
+            .register(0x3, 0x20)
+            .instruction(0xB300)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pc(), 0x320);
+    }
 }
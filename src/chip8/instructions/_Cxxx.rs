@@ -1,13 +1,75 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
 
 /// 0xCxnn - RND Vx, byte
 ///
 /// Set Vx = random byte AND nn.
 pub fn RND_Vx_byte(chip8: &mut Chip8, instruction: Instruction) {
-    chip8.registers[instruction.x as usize] = rand::random::<u8>() & instruction.nn;
+    chip8.registers[instruction.x as usize] = chip8.rng.next_byte() & instruction.nn;
 }
 
 /// 0xCxnn - RND Vx, byte
-pub fn _Cxxx(chip8: &mut Chip8, instruction: Instruction) {
-    RND_Vx_byte(chip8, instruction)
+pub fn _Cxxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    RND_Vx_byte(chip8, instruction);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::rng::FixedSequence;
+    use crate::chip8::Chip8Builder;
+
+    #[test]
+    fn rnd_is_masked() {
+        for seed in 0..32 {
+            let mut chip8 = Chip8Builder::new().seed(seed).instruction(0xC50A).build();
+            chip8.step().unwrap();
+
+            assert_eq!(chip8.get_registers()[0x5] & !0x0A, 0);
+        }
+    }
+
+    #[test]
+    fn rnd_is_reproducible_with_a_seed() {
+        let run = || {
+            let mut chip8 = Chip8Builder::new().seed(42).instruction(0xC0FF).build();
+            chip8.step().unwrap();
+            chip8.get_registers()[0x0]
+        };
+
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn rnd_uses_the_injected_source() {
+        let mut chip8 = Chip8Builder::new()
+            .rng(FixedSequence::new(&[0x5A]))
+            .instruction(0xC30F)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_registers()[0x3], 0x0A);
+    }
+
+    #[test]
+    fn rnd_is_replayed_from_a_save_state() {
+        let mut chip8 = Chip8Builder::new()
+            .seed(3)
+            .memory(0x200, &[0xC0, 0xFF, 0x12, 0x00]) // RND V0, 0xFF; JP 0x200
+            .build();
+        let state = chip8.save_state();
+
+        let mut bytes = Vec::new();
+        for _ in 0..8 {
+            chip8.step().unwrap();
+            bytes.push(chip8.get_registers()[0x0]);
+        }
+
+        chip8.load_state(&state).unwrap();
+        for &byte in bytes.iter().step_by(2) {
+            chip8.step().unwrap();
+            chip8.step().unwrap();
+            assert_eq!(chip8.get_registers()[0x0], byte);
+        }
+    }
 }
@@ -1,32 +1,210 @@
-use super::super::{chip8_constants, Chip8, Instruction};
+use super::super::{chip8_constants, Chip8, Chip8Error, Instruction};
 
 /// 0xDxyn - DRW Vx, Vy, n
 ///
 /// Display a sprite at coordinate (Vx, Vy) that has a width of 8 pixels and a height of n pixels.
+/// When n is 0, display a 16x16 sprite instead (SUPER-CHIP), stored as 16 rows of 2 bytes.
+/// With XO-CHIP, the sprite is drawn on each selected plane, the data for plane 2 following the data for plane 1.
 /// VF is set if there is a collision.
-fn DRW_Vx_Vy_n(chip8: &mut Chip8, instruction: Instruction) {
-    let x = chip8.registers[instruction.x as usize] as usize % chip8_constants::WIDTH;
-    let y = chip8.registers[instruction.y as usize] as usize % chip8_constants::HEIGHT;
+/// The sprite wraps around the screen edges, unless the `clipping` quirk is enabled.
+fn DRW_Vx_Vy_n(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    let (width, height) = chip8.get_display_size();
+    let (sprite_width, sprite_height) = match instruction.n {
+        0 => (16, 16),
+        n => (8, n as usize),
+    };
+    let bytes_per_row = sprite_width / 8;
+
+    let x = chip8.registers[instruction.x as usize] as usize % width;
+    let y = chip8.registers[instruction.y as usize] as usize % height;
     chip8.registers[0xF] = 0;
+    chip8.drawn = true;
+
+    let mut address = chip8.index as usize;
+    for plane in 0..chip8_constants::PLANES {
+        let mask = 1 << plane;
+        if chip8.planes & mask == 0 {
+            continue;
+        }
+
+        for row in 0..sprite_height {
+            let mut pixels = 0u16;
+            for _ in 0..bytes_per_row {
+                pixels = (pixels << 8) | chip8.read_memory(address)? as u16;
+                address += 1;
+            }
 
-    for row in 0..instruction.n as usize {
-        let pixel = chip8.memory[(chip8.index + row as u16) as usize];
-        for bit in 0..8 {
-            if (pixel & ((1 << 7) >> bit)) != 0 {
-                let x = (x + bit) % chip8_constants::WIDTH;
-                let y = (y + row) % chip8_constants::HEIGHT;
-                let index = (y * chip8_constants::WIDTH).wrapping_add(x);
+            for bit in 0..sprite_width {
+                if (pixels & ((1 << (sprite_width - 1)) >> bit)) != 0 {
+                    if chip8.quirks.clipping && (x + bit >= width || y + row >= height) {
+                        continue;
+                    }
 
-                if chip8.display[index] {
-                    chip8.registers[0xF] = 1;
+                    let x = (x + bit) % width;
+                    let y = (y + row) % height;
+                    let index = (y * width).wrapping_add(x);
+
+                    if chip8.display[index] & mask != 0 {
+                        chip8.registers[0xF] = 1;
+                    }
+                    chip8.display[index] ^= mask;
                 }
-                chip8.display[index] ^= true;
             }
         }
     }
+
+    Ok(())
 }
 
 /// 0xDxxx - DRW Vx, Vy, n
-pub fn _Dxxx(chip8: &mut Chip8, instruction: Instruction) {
-    DRW_Vx_Vy_n(chip8, instruction);
+pub fn _Dxxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    DRW_Vx_Vy_n(chip8, instruction)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8, Chip8Builder, Chip8Error, Quirks};
+
+    /// Returns the coordinates of the pixels on.
+    fn lit(chip8: &Chip8) -> Vec<(usize, usize)> {
+        let (width, _) = chip8.get_display_size();
+
+        (chip8.get_display().iter().enumerate())
+            .filter(|(_, &pixel)| pixel != 0)
+            .map(|(i, _)| (i % width, i / width))
+            .collect()
+    }
+
+    #[test]
+    fn drw_draws_a_sprite() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 10)
+            .register(0x2, 5)
+            .register(0xF, 0x42)
+            .memory(0x300, &[0b1000_0001, 0b0100_0000])
+            .index(0x300)
+            .instruction(0xD122)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(10, 5), (17, 5), (11, 6)]);
+        assert_eq!(chip8.get_registers()[0xF], 0);
+    }
+
+    #[test]
+    fn drw_xors_and_reports_collisions() {
+        let mut chip8 = Chip8Builder::new()
+            .pixel(0, 0, 1)
+            .memory(0x300, &[0b1100_0000])
+            .index(0x300)
+            .instruction(0xD001)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(1, 0)]);
+        assert_eq!(chip8.get_registers()[0xF], 1);
+    }
+
+    #[test]
+    fn drw_wraps_the_coordinates() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x0, 64 + 3)
+            .register(0x1, 32 + 1)
+            .memory(0x300, &[0b1000_0000])
+            .index(0x300)
+            .instruction(0xD011)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(3, 1)]);
+    }
+
+    #[test]
+    fn drw_clips_with_the_clipping_quirk() {
+        let mut chip8 = Chip8Builder::new()
+            .quirks(Quirks::COSMAC_VIP)
+            .register(0x0, 62)
+            .register(0x1, 31)
+            .memory(0x300, &[0xFF, 0xFF])
+            .index(0x300)
+            .instruction(0xD012)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(62, 31), (63, 31)]);
+    }
+
+    #[test]
+    fn drw_wraps_the_sprite_without_the_clipping_quirk() {
+        let mut chip8 = Chip8Builder::new()
+            .quirks(Quirks::XO_CHIP)
+            .register(0x0, 63)
+            .register(0x1, 31)
+            .memory(0x300, &[0b1100_0000, 0b1000_0000])
+            .index(0x300)
+            .instruction(0xD012)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(63, 0), (0, 31), (63, 31)]);
+    }
+
+    #[test]
+    fn drw_draws_16x16_sprites() {
+        let mut sprite = [0; 32];
+        sprite[0] = 0x80;
+        sprite[31] = 0x01;
+        let mut chip8 = Chip8Builder::new()
+            .hires(true)
+            .register(0x0, 100)
+            .memory(0x300, &sprite)
+            .index(0x300)
+            .instruction(0xD010)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(lit(&chip8), [(100, 0), (115, 15)]);
+    }
+
+    #[test]
+    fn drw_draws_each_selected_plane() {
+        let mut chip8 = Chip8Builder::new()
+            .planes(0b11)
+            .memory(0x300, &[0b1100_0000, 0b0110_0000])
+            .index(0x300)
+            .instruction(0xD001)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_display()[..3], [0b01, 0b11, 0b10]);
+    }
+
+    #[test]
+    fn drw_collisions_on_vf_as_a_coordinate() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0xF, 2)
+            .pixel(2, 2, 1)
+            .memory(0x300, &[0b1000_0000])
+            .index(0x300)
+            .instruction(0xDFF1)
+            .build();
+        chip8.step().unwrap();
+
+        assert!(lit(&chip8).is_empty());
+        assert_eq!(chip8.get_registers()[0xF], 1);
+    }
+
+    #[test]
+    fn drw_past_the_end_of_memory() {
+        let mut chip8 = Chip8Builder::new()
+            .index(0xFFFF)
+            .instruction(0xD002)
+            .build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(
+            err,
+            Chip8Error::MemoryOutOfBounds { addr: 0x10000 }
+        ));
+    }
 }
@@ -1,11 +1,16 @@
-use super::super::{Chip8, Instruction};
+use super::super::{Chip8, Chip8Error, Instruction};
+
+/// Returns the key tested by `Ex9E`/`ExA1`: the lowest nibble of Vx, as on the original interpreter.
+fn key(chip8: &Chip8, instruction: Instruction) -> usize {
+    (chip8.registers[instruction.x as usize] & 0xF) as usize
+}
 
 /// 0xEx9E - SKP Vx
 ///
 /// Skip next instruction if key with the value of Vx is pressed.
 fn SKP_Vx(chip8: &mut Chip8, instruction: Instruction) {
-    if chip8.keypad[chip8.registers[instruction.x as usize] as usize] {
-        chip8.pc = chip8.pc.wrapping_add(2);
+    if chip8.keypad[key(chip8, instruction)] {
+        chip8.skip_next_instruction();
     }
 }
 
@@ -13,16 +18,104 @@ fn SKP_Vx(chip8: &mut Chip8, instruction: Instruction) {
 ///
 /// Skip next instruction if key with the value of Vx is not pressed.
 fn SKNP_Vx(chip8: &mut Chip8, instruction: Instruction) {
-    if !chip8.keypad[chip8.registers[instruction.x as usize] as usize] {
-        chip8.pc = chip8.pc.wrapping_add(2);
+    if !chip8.keypad[key(chip8, instruction)] {
+        chip8.skip_next_instruction();
     }
 }
 
 /// Execute `0xExxx` instructions.
-pub fn _Exxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _Exxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     match instruction.nn {
         0x9E => SKP_Vx(chip8, instruction),
         0xA1 => SKNP_Vx(chip8, instruction),
-        _ => panic!("Unknown opcode [0xE???]: {:X}", instruction.opcode),
+        _ => {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: chip8.pc.wrapping_sub(2),
+                opcode: instruction.opcode,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{Chip8Builder, Chip8Error};
+
+    #[test]
+    fn skp_vx_skips_if_the_key_is_pressed() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0xB)
+            .keys(&[0xB])
+            .instruction(0xE49E)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0xB)
+            .keys(&[0xA])
+            .instruction(0xE49E)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
+
+    #[test]
+    fn sknp_vx_skips_if_the_key_is_not_pressed() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0xB)
+            .keys(&[0xB])
+            .instruction(0xE4A1)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0xB)
+            .instruction(0xE4A1)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+    }
+
+    #[test]
+    fn skp_vx_uses_the_lowest_nibble_of_vx() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0xFB)
+            .keys(&[0xB])
+            .instruction(0xE49E)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+    }
+
+    #[test]
+    fn sknp_vx_uses_the_lowest_nibble_of_vx() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0x1B)
+            .keys(&[0xB])
+            .instruction(0xE4A1)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x4, 0x10)
+            .instruction(0xE4A1)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x204);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chip8 = Chip8Builder::new().instruction(0xE400).build();
+
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode { opcode: 0xE400, .. })
+        ));
     }
 }
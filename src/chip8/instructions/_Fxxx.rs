@@ -1,4 +1,35 @@
-use super::super::{chip8_constants, Chip8, Instruction};
+use super::super::{chip8_constants, Chip8, Chip8Error, Instruction, KeyWait};
+
+/// 0xF000 nnnn - LD I, long addr
+///
+/// I = nnnn, the 16-bit address stored in the next 2 bytes (XO-CHIP).
+fn LD_I_long(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+    chip8.index = chip8.fetch(chip8.pc as usize)?;
+    chip8.pc = chip8.pc.wrapping_add(2);
+
+    Ok(())
+}
+
+/// 0xFn01 - PLANE n
+///
+/// Select the planes affected by drawing, clearing and scrolling, n being a bitmask (XO-CHIP).
+const fn PLANE_n(chip8: &mut Chip8, instruction: Instruction) {
+    chip8.planes = instruction.x & 0b11;
+}
+
+/// 0xF002 - AUDIO
+///
+/// Load the 16-byte audio pattern from memory starting at address I (XO-CHIP).
+fn AUDIO(chip8: &mut Chip8) -> Result<(), Chip8Error> {
+    let mut pattern = [0; chip8_constants::AUDIO_PATTERN_SIZE];
+    for (i, byte) in pattern.iter_mut().enumerate() {
+        *byte = chip8.read_memory(chip8.index as usize + i)?;
+    }
+
+    chip8.audio_pattern = Some(pattern);
+
+    Ok(())
+}
 
 /// 0xFx07 - LD Vx, DT
 ///
@@ -9,20 +40,28 @@ fn LD_Vx_DT(chip8: &mut Chip8, instruction: Instruction) {
 
 /// 0xFx0A - LD Vx, K
 ///
-/// Wait for a key press, store the value of the key in Vx.
+/// Wait for a key to be pressed then released, store the value of the key in Vx.
+///
+/// The instruction repeats until the key is released, the progress being tracked by the key
+/// events (see [`Chip8::key_down`] and [`Chip8::key_up`]). With the `key_wait_beep` quirk,
+/// it beeps while the key is held.
 fn LD_Vx_K(chip8: &mut Chip8, instruction: Instruction) {
-    for i in 0..16 {
-        if chip8.keypad[i] {
-            chip8.registers[instruction.x as usize] = i as u8;
-            chip8.key_pressed = true;
-            break;
+    match chip8.key_wait {
+        KeyWait::Released(key) => {
+            chip8.registers[instruction.x as usize] = key;
+            chip8.key_wait = KeyWait::Idle;
+        }
+        KeyWait::Idle => {
+            chip8.key_wait = KeyWait::Press;
+            chip8.pc = chip8.pc.wrapping_sub(2);
+        }
+        KeyWait::Press => chip8.pc = chip8.pc.wrapping_sub(2),
+        KeyWait::Release(_) => {
+            if chip8.quirks.key_wait_beep {
+                chip8.sound_timer = chip8.sound_timer.max(chip8_constants::KEY_BEEP_TIME);
+            }
+            chip8.pc = chip8.pc.wrapping_sub(2);
         }
-    }
-
-    if chip8.key_pressed && chip8.keypad.iter().all(|&x| !x) {
-        chip8.key_pressed = false;
-    } else {
-        chip8.pc = chip8.pc.wrapping_sub(2);
     }
 }
 
@@ -57,50 +96,413 @@ fn LD_F_Vx(chip8: &mut Chip8, instruction: Instruction) {
         .wrapping_add((chip8.registers[instruction.x as usize] as u16).wrapping_mul(5));
 }
 
+/// 0xFx30 - LD HF, Vx
+///
+/// I = big digit Vx sprite address (SUPER-CHIP).
+fn LD_HF_Vx(chip8: &mut Chip8, instruction: Instruction) {
+    chip8.index = (chip8_constants::BIG_FONTSET_START_ADDRESS as u16)
+        .wrapping_add((chip8.registers[instruction.x as usize] as u16).wrapping_mul(10));
+}
+
 /// 0xFx33 - LD B, Vx
 ///
 /// Store BCD representation of Vx in memory locations I, I+1, and I+2.
-fn LD_B_Vx(chip8: &mut Chip8, instruction: Instruction) {
+fn LD_B_Vx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     let mut x = chip8.registers[instruction.x as usize];
     let index = chip8.index as usize;
 
-    chip8.memory[index.wrapping_add(2)] = x % 10;
+    chip8.write_memory(index + 2, x % 10)?;
     x /= 10;
-    chip8.memory[index.wrapping_add(1)] = x % 10;
+    chip8.write_memory(index + 1, x % 10)?;
     x /= 10;
-    chip8.memory[index] = x % 10;
+    chip8.write_memory(index, x % 10)
+}
+
+/// 0xFx3A - PITCH Vx
+///
+/// Set the pitch register to Vx (XO-CHIP).
+fn PITCH_Vx(chip8: &mut Chip8, instruction: Instruction) {
+    chip8.pitch = chip8.registers[instruction.x as usize];
 }
 
 /// 0xFx55 - LD [I], Vx
 ///
 /// Store V0 to Vx in memory starting at address I.
-fn LD_I_Vx(chip8: &mut Chip8, instruction: Instruction) {
-    for i in 0..=instruction.x {
-        chip8.memory[(chip8.index + i as u16) as usize] = chip8.registers[i as usize];
+/// With the `memory_increment` quirk, I is set to I + x + 1.
+fn LD_I_Vx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    for i in 0..=instruction.x as usize {
+        chip8.write_memory(chip8.index as usize + i, chip8.registers[i])?;
     }
+
+    if chip8.quirks.memory_increment {
+        chip8.index = chip8.index.wrapping_add(instruction.x as u16 + 1);
+    }
+
+    Ok(())
 }
 
 /// 0xFx65 - LD Vx, [I]
 ///
 /// Read V0 to Vx from memory starting at address I.
-fn LD_Vx_I(chip8: &mut Chip8, instruction: Instruction) {
-    for i in 0..=instruction.x {
-        chip8.registers[i as usize] = chip8.memory[(chip8.index + i as u16) as usize];
+/// With the `memory_increment` quirk, I is set to I + x + 1.
+fn LD_Vx_I(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
+    for i in 0..=instruction.x as usize {
+        chip8.registers[i] = chip8.read_memory(chip8.index as usize + i)?;
     }
+
+    if chip8.quirks.memory_increment {
+        chip8.index = chip8.index.wrapping_add(instruction.x as u16 + 1);
+    }
+
+    Ok(())
+}
+
+/// 0xFx75 - LD R, Vx
+///
+/// Store V0 to Vx in the RPL user flags (SUPER-CHIP).
+fn LD_R_Vx(chip8: &mut Chip8, instruction: Instruction) {
+    let count = instruction.x as usize + 1;
+
+    chip8.rpl_flags[..count].copy_from_slice(&chip8.registers[..count]);
+}
+
+/// 0xFx85 - LD Vx, R
+///
+/// Read V0 to Vx from the RPL user flags (SUPER-CHIP).
+fn LD_Vx_R(chip8: &mut Chip8, instruction: Instruction) {
+    let count = instruction.x as usize + 1;
+
+    chip8.registers[..count].copy_from_slice(&chip8.rpl_flags[..count]);
 }
 
 /// Execute 0xFxxx instruction.
-pub fn _Fxxx(chip8: &mut Chip8, instruction: Instruction) {
+pub fn _Fxxx(chip8: &mut Chip8, instruction: Instruction) -> Result<(), Chip8Error> {
     match instruction.nn {
+        0x00 if instruction.x == 0 => return LD_I_long(chip8),
+        0x01 => PLANE_n(chip8, instruction),
+        0x02 if instruction.x == 0 => return AUDIO(chip8),
         0x07 => LD_Vx_DT(chip8, instruction),
         0x0A => LD_Vx_K(chip8, instruction),
         0x15 => LD_DT_Vx(chip8, instruction),
         0x18 => LD_ST_Vx(chip8, instruction),
         0x1E => ADD_I_Vx(chip8, instruction),
         0x29 => LD_F_Vx(chip8, instruction),
-        0x33 => LD_B_Vx(chip8, instruction),
-        0x55 => LD_I_Vx(chip8, instruction),
-        0x65 => LD_Vx_I(chip8, instruction),
-        _ => panic!("Unknown opcode [0xF???]: {:X}", instruction.opcode),
+        0x30 => LD_HF_Vx(chip8, instruction),
+        0x33 => return LD_B_Vx(chip8, instruction),
+        0x3A => PITCH_Vx(chip8, instruction),
+        0x55 => return LD_I_Vx(chip8, instruction),
+        0x65 => return LD_Vx_I(chip8, instruction),
+        0x75 => LD_R_Vx(chip8, instruction),
+        0x85 => LD_Vx_R(chip8, instruction),
+        _ => {
+            return Err(Chip8Error::UnknownOpcode {
+                pc: chip8.pc.wrapping_sub(2),
+                opcode: instruction.opcode,
+            })
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chip8::{chip8_constants, Chip8Builder, Chip8Error, KeyWait, Quirks};
+
+    #[test]
+    fn ld_i_long() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0xF0, 0x00, 0xAB, 0xCD])
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_index(), 0xABCD);
+        assert_eq!(chip8.get_pc(), 0x204);
+    }
+
+    #[test]
+    fn plane_n() {
+        let mut chip8 = Chip8Builder::new().instruction(0xF201).build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_planes(), 0b10);
+    }
+
+    #[test]
+    fn audio_loads_the_pattern() {
+        let pattern: Vec<u8> = (0..16).collect();
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x300, &pattern)
+            .index(0x300)
+            .instruction(0xF002)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_audio_pattern().unwrap()[..], pattern[..]);
+    }
+
+    #[test]
+    fn timers() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x3, 20)
+            .register(0x4, 30)
+            .delay_timer(7)
+            .memory(0x200, &[0xF3, 0x15, 0xF4, 0x18, 0xF5, 0x07]) // LD DT, V3; LD ST, V4; LD V5, DT
+            .build();
+        for _ in 0..3 {
+            chip8.step().unwrap();
+        }
+
+        assert_eq!(chip8.get_delay_timer(), 20);
+        assert_eq!(chip8.get_sound_timer(), 30);
+        assert_eq!(chip8.get_registers()[0x5], 20);
+    }
+
+    #[test]
+    fn ld_vx_k_waits_for_a_press_and_a_release() {
+        let mut chip8 = Chip8Builder::new().instruction(0xF30A).build();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x200);
+
+        chip8.update_key(0x9, true);
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x200);
+        assert_eq!(chip8.get_key_wait(), KeyWait::Release(0x9));
+        assert_eq!(chip8.get_registers()[0x3], 0);
+
+        chip8.update_key(0x9, false);
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_pc(), 0x202);
+        assert_eq!(chip8.get_registers()[0x3], 0x9);
+        assert_eq!(chip8.get_key_wait(), KeyWait::Idle);
+    }
+
+    #[test]
+    fn ld_vx_k_ignores_the_keys_held_before() {
+        let mut chip8 = Chip8Builder::new().keys(&[0x1]).instruction(0xF30A).build();
+        chip8.step().unwrap();
+
+        // A second key pressed while the first one is held is the one waited for
+        chip8.key_down(0x2);
+        chip8.key_up(0x1);
+        chip8.step().unwrap();
+        assert!(chip8.is_waiting_for_key());
+        assert_eq!(chip8.get_pc(), 0x200);
+
+        chip8.key_down(0x1);
+        chip8.key_up(0x2);
+        chip8.step().unwrap();
+        assert!(!chip8.is_waiting_for_key());
+        assert_eq!(chip8.get_registers()[0x3], 0x2);
+        assert_eq!(chip8.get_pc(), 0x202);
+    }
+
+    #[test]
+    fn ld_vx_k_resumes_from_a_save_state() {
+        let mut chip8 = Chip8Builder::new().instruction(0xF30A).build();
+        chip8.step().unwrap();
+        chip8.key_down(0x7);
+        let state = chip8.save_state();
+
+        chip8.key_up(0x7);
+        chip8.load_state(&state).unwrap();
+        assert_eq!(chip8.get_key_wait(), KeyWait::Release(0x7));
+
+        chip8.key_up(0x7);
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_registers()[0x3], 0x7);
+    }
+
+    #[test]
+    fn key_wait_beep_while_the_key_is_held() {
+        for key_wait_beep in [false, true] {
+            let mut chip8 = Chip8Builder::new()
+                .quirks(Quirks {
+                    key_wait_beep,
+                    ..Quirks::COSMAC_VIP
+                })
+                .delay_timer(10)
+                .memory(0x200, &[0xF3, 0x0A, 0x12, 0x02]) // LD V3, K; JP 0x202
+                .build();
+            chip8.run_frame(5).unwrap();
+            assert_eq!(chip8.get_sound_timer(), 0);
+
+            // The timers keep running while the key is held, the sound timer being kept up
+            chip8.key_down(0x5);
+            for _ in 0..10 {
+                chip8.run_frame(5).unwrap();
+            }
+            assert_eq!(chip8.get_delay_timer(), 0);
+            let beep = chip8_constants::KEY_BEEP_TIME - 1;
+            assert_eq!(
+                chip8.get_sound_timer(),
+                if key_wait_beep { beep } else { 0 }
+            );
+
+            // The beep fades out after the release
+            chip8.key_up(0x5);
+            for _ in 0..chip8_constants::KEY_BEEP_TIME {
+                chip8.run_frame(5).unwrap();
+            }
+            assert_eq!(chip8.get_sound_timer(), 0);
+            assert_eq!(chip8.get_registers()[0x3], 0x5);
+        }
+    }
+
+    #[test]
+    fn add_i_vx_wraps_without_touching_vf() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x1, 0x10)
+            .index(0xFFF8)
+            .instruction(0xF11E)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_index(), 0x0008);
+        assert_eq!(chip8.get_registers()[0xF], 0);
+    }
+
+    #[test]
+    fn font_addresses() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x2, 0xA)
+            .memory(0x200, &[0xF2, 0x29])
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(
+            chip8.get_index() as usize,
+            chip8_constants::FONTSET_START_ADDRESS + 50
+        );
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x2, 0x3)
+            .instruction(0xF230)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(
+            chip8.get_index() as usize,
+            chip8_constants::BIG_FONTSET_START_ADDRESS + 30
+        );
+    }
+
+    #[test]
+    fn ld_b_vx_stores_the_decimal_digits() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x6, 254)
+            .index(0x300)
+            .instruction(0xF633)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_memory()[0x300..0x303], [2, 5, 4]);
+        assert_eq!(chip8.get_index(), 0x300);
+    }
+
+    #[test]
+    fn ld_b_vx_at_the_top_of_memory() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x6, 123)
+            .index(0xFFFD)
+            .instruction(0xF633)
+            .build();
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_memory()[0xFFFD..], [1, 2, 3]);
+
+        let mut chip8 = Chip8Builder::new()
+            .register(0x6, 123)
+            .index(0xFFFE)
+            .instruction(0xF633)
+            .build();
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(
+            err,
+            Chip8Error::MemoryOutOfBounds { addr: 0x10000 }
+        ));
+        assert_eq!(chip8.get_memory()[0xFFFE..], [0, 0]);
+    }
+
+    #[test]
+    fn pitch_vx() {
+        let mut chip8 = Chip8Builder::new()
+            .register(0x0, 112)
+            .instruction(0xF03A)
+            .build();
+        chip8.step().unwrap();
+
+        assert_eq!(chip8.get_pitch(), 112);
+    }
+
+    #[test]
+    fn ld_i_vx_stores_v0_to_vx() {
+        for (quirks, index) in [(Quirks::COSMAC_VIP, 0x303), (Quirks::SUPER_CHIP, 0x300)] {
+            let mut chip8 = Chip8Builder::new()
+                .quirks(quirks)
+                .registers([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16])
+                .index(0x300)
+                .instruction(0xF255)
+                .build();
+            chip8.step().unwrap();
+
+            assert_eq!(chip8.get_memory()[0x300..0x304], [1, 2, 3, 0]);
+            assert_eq!(chip8.get_index(), index);
+        }
+    }
+
+    #[test]
+    fn ld_vx_i_reads_v0_to_vx() {
+        for (quirks, index) in [(Quirks::XO_CHIP, 0x310), (Quirks::CHIP_48, 0x300)] {
+            let memory: Vec<u8> = (0x10..0x20).collect();
+            let mut chip8 = Chip8Builder::new()
+                .quirks(quirks)
+                .memory(0x300, &memory)
+                .index(0x300)
+                .instruction(0xFF65)
+                .build();
+            chip8.step().unwrap();
+
+            assert_eq!(chip8.get_registers()[..], memory[..]);
+            assert_eq!(chip8.get_index(), index);
+        }
+    }
+
+    #[test]
+    fn ld_i_vx_past_the_end_of_memory() {
+        let mut chip8 = Chip8Builder::new()
+            .index(0xFFFF)
+            .instruction(0xF155)
+            .build();
+
+        let err = chip8.step().unwrap_err();
+        assert!(matches!(
+            err,
+            Chip8Error::MemoryOutOfBounds { addr: 0x10000 }
+        ));
+    }
+
+    #[test]
+    fn rpl_flags() {
+        let mut chip8 = Chip8Builder::new()
+            .registers([9; 16])
+            .rpl_flags([1; 16])
+            .memory(0x200, &[0xF2, 0x85, 0xF1, 0x75]) // LD V2, R; LD R, V1
+            .build();
+
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_registers()[..4], [1, 1, 1, 9]);
+
+        chip8.get_memory_mut()[0x202] = 0xF3;
+        chip8.step().unwrap();
+        assert_eq!(chip8.get_rpl_flags()[..5], [1, 1, 1, 9, 1]);
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        let mut chip8 = Chip8Builder::new().instruction(0xF1FF).build();
+
+        assert!(matches!(
+            chip8.step(),
+            Err(Chip8Error::UnknownOpcode { opcode: 0xF1FF, .. })
+        ));
     }
 }
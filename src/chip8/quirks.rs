@@ -0,0 +1,249 @@
+//! # CHIP-8 Quirks
+//!
+//! Several CHIP-8 instructions behave differently depending on the interpreter a ROM was written for.
+//! This module defines [`Quirks`], the set of switches selecting one interpretation for each of them,
+//! along with presets for the most common platforms, selectable by name through [`Platform`].
+//!
+//! | Quirk              | COSMAC VIP | CHIP-48 | SUPER-CHIP | XO-CHIP |
+//! |--------------------|------------|---------|------------|---------|
+//! | `shift`            | no         | yes     | yes        | no      |
+//! | `memory_increment` | yes        | no      | no         | yes     |
+//! | `vf_reset`         | yes        | no      | no         | no      |
+//! | `jump`             | no         | yes     | yes        | no      |
+//! | `clipping`         | yes        | yes     | yes        | no      |
+//! | `display_wait`     | yes        | no      | no         | no      |
+//! | `key_wait_beep`    | yes        | no      | no         | no      |
+
+/// Selects the behavior of the ambiguous CHIP-8 instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift Vx in place and ignore Vy.
+    ///
+    /// When disabled, Vy is shifted and the result is stored in Vx.
+    pub shift: bool,
+
+    /// `Fx55`/`Fx65` leave `I` pointing after the last accessed byte (`I = I + x + 1`).
+    ///
+    /// When disabled, `I` is left unchanged.
+    pub memory_increment: bool,
+
+    /// `8xy1`/`8xy2`/`8xy3` reset VF to 0.
+    pub vf_reset: bool,
+
+    /// `Bnnn` jumps to `nnn + Vx` (where x is the highest nibble of nnn) instead of `nnn + V0`.
+    pub jump: bool,
+
+    /// `Dxyn` clips sprites at the screen edges.
+    ///
+    /// When disabled, the parts of the sprite going past an edge wrap around to the other side.
+    pub clipping: bool,
+
+    /// `Dxyn` waits for the next frame before the program continues (at most one draw per frame).
+    ///
+    /// This is applied by [`Chip8::run_frame`](super::Chip8::run_frame).
+    pub display_wait: bool,
+
+    /// `Fx0A` beeps while the key it waits for is held, as on the COSMAC VIP.
+    ///
+    /// The sound timer is kept at least at `KEY_BEEP_TIME` until the key is released, the timers
+    /// keep running meanwhile.
+    pub key_wait_beep: bool,
+}
+
+impl Quirks {
+    /// The original COSMAC VIP interpreter.
+    pub const COSMAC_VIP: Self = Self {
+        shift: false,
+        memory_increment: true,
+        vf_reset: true,
+        jump: false,
+        clipping: true,
+        display_wait: true,
+        key_wait_beep: true,
+    };
+
+    /// The CHIP-48 interpreter for the HP-48 calculators.
+    pub const CHIP_48: Self = Self {
+        shift: true,
+        memory_increment: false,
+        vf_reset: false,
+        jump: true,
+        clipping: true,
+        display_wait: false,
+        key_wait_beep: false,
+    };
+
+    /// The SUPER-CHIP 1.1 interpreter.
+    pub const SUPER_CHIP: Self = Self {
+        shift: true,
+        memory_increment: false,
+        vf_reset: false,
+        jump: true,
+        clipping: true,
+        display_wait: false,
+        key_wait_beep: false,
+    };
+
+    /// The XO-CHIP extension, as implemented by Octo.
+    pub const XO_CHIP: Self = Self {
+        shift: false,
+        memory_increment: true,
+        vf_reset: false,
+        jump: false,
+        clipping: false,
+        display_wait: false,
+        key_wait_beep: false,
+    };
+
+    /// The names of the quirks, as accepted by [`Quirks::get`] and [`Quirks::get_mut`].
+    pub const NAMES: [&'static str; 7] = [
+        "shift",
+        "memory_increment",
+        "vf_reset",
+        "jump",
+        "clipping",
+        "display_wait",
+        "key_wait_beep",
+    ];
+
+    /// Returns the value of the quirk called `name`, or `None` if there is no such quirk.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        let mut quirks = *self;
+
+        quirks.get_mut(name).map(|quirk| *quirk)
+    }
+
+    /// Returns a mutable reference to the quirk called `name`, or `None` if there is no such quirk.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::Quirks;
+    ///
+    /// let mut quirks = Quirks::COSMAC_VIP;
+    /// *quirks.get_mut("shift").unwrap() = true;
+    /// assert!(quirks.shift);
+    /// ```
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut bool> {
+        match name {
+            "shift" => Some(&mut self.shift),
+            "memory_increment" => Some(&mut self.memory_increment),
+            "vf_reset" => Some(&mut self.vf_reset),
+            "jump" => Some(&mut self.jump),
+            "clipping" => Some(&mut self.clipping),
+            "display_wait" => Some(&mut self.display_wait),
+            "key_wait_beep" => Some(&mut self.key_wait_beep),
+            _ => None,
+        }
+    }
+
+    /// Packs the quirks in a byte, one bit each in the order of [`Quirks::NAMES`].
+    ///
+    /// This is the encoding used by the save states and the movies.
+    pub fn to_bits(&self) -> u8 {
+        Self::NAMES
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| self.get(name) == Some(true))
+            .fold(0, |bits, (i, _)| bits | 1 << i)
+    }
+
+    /// Unpacks quirks packed by [`Quirks::to_bits`], the unused bits are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::Quirks;
+    ///
+    /// let bits = Quirks::SUPER_CHIP.to_bits();
+    /// assert_eq!(Quirks::from_bits(bits), Quirks::SUPER_CHIP);
+    /// ```
+    pub fn from_bits(bits: u8) -> Self {
+        let mut quirks = Self::default();
+        for (i, name) in Self::NAMES.iter().enumerate() {
+            *quirks.get_mut(name).unwrap() = bits & 1 << i != 0;
+        }
+
+        quirks
+    }
+}
+
+impl Default for Quirks {
+    /// Defaults to the original COSMAC VIP behavior.
+    fn default() -> Self {
+        Self::COSMAC_VIP
+    }
+}
+
+/// A platform with a named quirk preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Platform {
+    /// The original COSMAC VIP interpreter (`vip`).
+    #[default]
+    CosmacVip,
+
+    /// The CHIP-48 interpreter (`chip48`).
+    Chip48,
+
+    /// The SUPER-CHIP 1.1 interpreter (`schip`).
+    SuperChip,
+
+    /// The XO-CHIP extension (`xochip`).
+    XoChip,
+}
+
+impl Platform {
+    /// All the platforms.
+    pub const ALL: [Self; 4] = [Self::CosmacVip, Self::Chip48, Self::SuperChip, Self::XoChip];
+
+    /// Returns the quirk preset of the platform.
+    pub const fn quirks(self) -> Quirks {
+        match self {
+            Self::CosmacVip => Quirks::COSMAC_VIP,
+            Self::Chip48 => Quirks::CHIP_48,
+            Self::SuperChip => Quirks::SUPER_CHIP,
+            Self::XoChip => Quirks::XO_CHIP,
+        }
+    }
+
+    /// Returns the short name of the platform, as accepted by `from_str`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::CosmacVip => "vip",
+            Self::Chip48 => "chip48",
+            Self::SuperChip => "schip",
+            Self::XoChip => "xochip",
+        }
+    }
+}
+
+impl std::fmt::Display for Platform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The error returned when parsing an unknown platform name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPlatform(pub String);
+
+impl std::fmt::Display for UnknownPlatform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unknown platform `{}`, expected one of: vip, chip48, schip, xochip",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownPlatform {}
+
+impl std::str::FromStr for Platform {
+    type Err = UnknownPlatform;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|platform| platform.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| UnknownPlatform(name.to_string()))
+    }
+}
@@ -0,0 +1,148 @@
+//! # Random Number Generation
+//!
+//! `RND Vx, byte` draws its bytes from a [`RandomSource`] owned by the [`Chip8`](super::Chip8).
+//! The default source is [`SeededRng`], a small generator whose whole state is a 64-bit word:
+//! a run is reproducible from its seed, and the state is saved in the save states so that a
+//! restored program generates the same numbers again.
+//!
+//! Tests can inject a known sequence of bytes with [`FixedSequence`].
+
+/// A source of random bytes for `RND Vx, byte`.
+pub trait RandomSource: std::fmt::Debug + Send {
+    /// Returns the next random byte.
+    fn next_byte(&mut self) -> u8;
+
+    /// Serializes the state of the source, stored in the save states.
+    ///
+    /// Sources without a state to save return an empty vector (the default).
+    fn save(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores a state serialized by [`RandomSource::save`].
+    ///
+    /// Returns `false`, leaving the source unchanged, if the state is invalid for this source.
+    /// The default implementation only accepts an empty state.
+    fn restore(&mut self, state: &[u8]) -> bool {
+        state.is_empty()
+    }
+}
+
+/// The default random source, a SplitMix64 generator.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::rng::{RandomSource, SeededRng};
+///
+/// let (mut a, mut b) = (SeededRng::new(7), SeededRng::new(7));
+/// assert_eq!(a.next_byte(), b.next_byte());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng {
+    /// The internal state, advanced by each byte drawn.
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a generator from a seed; generators created with the same seed generate the same bytes.
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded by the operating system, for non-reproducible runs.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl RandomSource for SeededRng {
+    fn next_byte(&mut self) -> u8 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+
+        (z >> 56) as u8
+    }
+
+    fn save(&self) -> Vec<u8> {
+        self.state.to_le_bytes().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> bool {
+        match state.try_into() {
+            Ok(bytes) => {
+                self.state = u64::from_le_bytes(bytes);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// A random source repeating a fixed sequence of bytes, for tests.
+///
+/// # Example
+/// ```
+/// use rust_chip8::chip8::rng::FixedSequence;
+/// use rust_chip8::Chip8Builder;
+///
+/// let mut chip8 = Chip8Builder::new()
+///     .rng(FixedSequence::new(&[0xAB, 0xCD]))
+///     .memory(0x200, &[0xC0, 0xFF, 0xC1, 0x0F, 0xC2, 0xFF]) // RND V0/V1/V2
+///     .build();
+/// for _ in 0..3 {
+///     chip8.step().unwrap();
+/// }
+///
+/// assert_eq!(chip8.get_registers()[..3], [0xAB, 0x0D, 0xAB]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixedSequence {
+    /// The bytes returned in a loop.
+    bytes: Vec<u8>,
+
+    /// The position of the next byte.
+    position: usize,
+}
+
+impl FixedSequence {
+    /// Creates a source returning `bytes` in a loop.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bytes` is empty.
+    pub fn new(bytes: &[u8]) -> Self {
+        assert!(!bytes.is_empty(), "the sequence must not be empty");
+
+        Self {
+            bytes: bytes.to_vec(),
+            position: 0,
+        }
+    }
+}
+
+impl RandomSource for FixedSequence {
+    fn next_byte(&mut self) -> u8 {
+        let byte = self.bytes[self.position];
+        self.position = (self.position + 1) % self.bytes.len();
+
+        byte
+    }
+
+    fn save(&self) -> Vec<u8> {
+        (self.position as u32).to_le_bytes().to_vec()
+    }
+
+    fn restore(&mut self, state: &[u8]) -> bool {
+        match state.try_into().map(u32::from_le_bytes) {
+            Ok(position) if (position as usize) < self.bytes.len() => {
+                self.position = position as usize;
+                true
+            }
+            _ => false,
+        }
+    }
+}
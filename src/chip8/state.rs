@@ -0,0 +1,299 @@
+//! # Save States
+//!
+//! This module serializes the full machine state to a versioned binary format, so that a program
+//! can be resumed at the exact point it was saved.
+//!
+//! All the values are little-endian. A state starts with a header:
+//!
+//! | Offset | Size | Content                                          |
+//! |--------|------|--------------------------------------------------|
+//! | 0      | 4    | Magic number `CH8S`                              |
+//! | 4      | 2    | Format version ([`STATE_VERSION`])               |
+//! | 6      | 20   | SHA-1 hash of the ROM the state was saved with   |
+//! | 26     | 1    | Quirks, packed by [`Quirks::to_bits`]            |
+//!
+//! followed by the registers, the memory, the timers, the stack, the keypad, the display and the
+//! SUPER-CHIP/XO-CHIP state, and the state of the random number generator (its length as a
+//! 16-bit value, then the bytes of [`RandomSource::save`](super::RandomSource::save)).
+
+use super::{Chip8, KeyWait, Quirks};
+use crate::constants::chip8_constants;
+
+/// The magic number at the start of every save state.
+const STATE_MAGIC: &[u8; 4] = b"CH8S";
+
+/// The version of the save state format written by [`Chip8::save_state`].
+pub const STATE_VERSION: u16 = 3;
+
+/// An error raised while restoring a save state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateError {
+    /// The data does not start with the save state magic number.
+    BadMagic,
+
+    /// The state was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+
+    /// The state was saved with another ROM than the loaded one.
+    RomMismatch,
+
+    /// The data ends before the end of the state.
+    Truncated,
+
+    /// A value of the state is out of its range.
+    Invalid(&'static str),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a save state"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported save state version {version} (expected {STATE_VERSION})"
+            ),
+            Self::RomMismatch => write!(f, "save state was made with another ROM"),
+            Self::Truncated => write!(f, "save state is truncated"),
+            Self::Invalid(field) => write!(f, "save state has an invalid {field}"),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Reads the values of a save state in order.
+struct StateReader<'a> {
+    /// The remaining bytes.
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    /// Reads the next `len` bytes.
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], StateError> {
+        if self.data.len() < len {
+            return Err(StateError::Truncated);
+        }
+
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    /// Reads the next `N` bytes as an array.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    /// Reads a byte.
+    fn u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    /// Reads a little-endian 16-bit value.
+    fn u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    /// Reads a boolean stored as a byte.
+    fn bool(&mut self) -> Result<bool, StateError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(StateError::Invalid("boolean")),
+        }
+    }
+}
+
+impl Chip8 {
+    /// Serializes the full machine state.
+    ///
+    /// The state includes the hash of the loaded ROM and the quirks, it can be restored with
+    /// [`Chip8::load_state`] on a machine running the same ROM.
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(self.memory.len() + self.display.len() + 128);
+
+        // Header
+        state.extend_from_slice(STATE_MAGIC);
+        state.extend_from_slice(&STATE_VERSION.to_le_bytes());
+        state.extend_from_slice(&self.rom_hash);
+        state.push(self.quirks.to_bits());
+
+        // CPU
+        state.extend_from_slice(&self.registers);
+        state.extend_from_slice(&self.index.to_le_bytes());
+        state.extend_from_slice(&self.pc.to_le_bytes());
+        for address in self.stack {
+            state.extend_from_slice(&address.to_le_bytes());
+        }
+        state.push(self.sp);
+        state.extend_from_slice(&self.opcode.to_le_bytes());
+        state.push(self.halted as u8);
+        state.extend_from_slice(&self.memory);
+
+        // Timers and sound
+        state.push(self.delay_timer);
+        state.push(self.sound_timer);
+        state.push(self.pitch);
+        state.push(self.audio_pattern.is_some() as u8);
+        state.extend_from_slice(&self.audio_pattern.unwrap_or_default());
+
+        // Input
+        for &pressed in &self.keypad {
+            state.push(pressed as u8);
+        }
+        state.push(match self.key_wait {
+            KeyWait::Idle => 0,
+            KeyWait::Press => 1,
+            KeyWait::Release(key) => 0x10 | key,
+            KeyWait::Released(key) => 0x20 | key,
+        });
+
+        // Display
+        state.push(self.hires as u8);
+        state.push(self.planes);
+        state.push(self.drawn as u8);
+        state.extend_from_slice(&self.display);
+
+        state.extend_from_slice(&self.rpl_flags);
+
+        // Random number generator
+        let rng = self.rng.save();
+        state.extend_from_slice(&(rng.len() as u16).to_le_bytes());
+        state.extend_from_slice(&rng);
+
+        state
+    }
+
+    /// Restores a machine state serialized by [`Chip8::save_state`].
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - The serialized state.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `StateError` if the state is invalid or was saved with another ROM.
+    /// The machine is left unchanged in that case.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::Chip8;
+    ///
+    /// let mut chip8 = Chip8::new();
+    /// chip8.load_rom_bytes(&[0x60, 0x2A]).unwrap(); // LD V0, 0x2A
+    ///
+    /// let state = chip8.save_state();
+    /// chip8.step().unwrap();
+    /// assert_eq!(chip8.get_registers()[0], 0x2A);
+    ///
+    /// chip8.load_state(&state).unwrap();
+    /// assert_eq!(chip8.get_registers()[0], 0);
+    /// assert_eq!(chip8.get_pc(), 0x200);
+    /// ```
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), StateError> {
+        let mut reader = StateReader { data: state };
+
+        // Header
+        if reader.bytes(STATE_MAGIC.len()).ok() != Some(STATE_MAGIC.as_slice()) {
+            return Err(StateError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != STATE_VERSION {
+            return Err(StateError::UnsupportedVersion(version));
+        }
+        if reader.array::<20>()? != self.rom_hash {
+            return Err(StateError::RomMismatch);
+        }
+        let quirks = Quirks::from_bits(reader.u8()?);
+
+        // CPU
+        let registers = reader.array()?;
+        let index = reader.u16()?;
+        let pc = reader.u16()?;
+        let mut stack = [0; 16];
+        for address in &mut stack {
+            *address = reader.u16()?;
+        }
+        let sp = reader.u8()?;
+        if sp as usize > stack.len() {
+            return Err(StateError::Invalid("stack pointer"));
+        }
+        let opcode = reader.u16()?;
+        let halted = reader.bool()?;
+        let memory = reader.bytes(chip8_constants::RAM_SIZE)?;
+
+        // Timers and sound
+        let delay_timer = reader.u8()?;
+        let sound_timer = reader.u8()?;
+        let pitch = reader.u8()?;
+        let has_pattern = reader.bool()?;
+        let pattern = reader.array()?;
+
+        // Input
+        let mut keypad = [false; 16];
+        for pressed in &mut keypad {
+            *pressed = reader.bool()?;
+        }
+        let key_wait = match reader.u8()? {
+            0 => KeyWait::Idle,
+            1 => KeyWait::Press,
+            byte @ 0x10..=0x1F => KeyWait::Release(byte & 0xF),
+            byte @ 0x20..=0x2F => KeyWait::Released(byte & 0xF),
+            _ => return Err(StateError::Invalid("key wait state")),
+        };
+
+        // Display
+        let hires = reader.bool()?;
+        let planes = reader.u8()?;
+        if planes > 0b11 {
+            return Err(StateError::Invalid("plane selection"));
+        }
+        let drawn = reader.bool()?;
+        let (width, height) = if hires {
+            (chip8_constants::HIRES_WIDTH, chip8_constants::HIRES_HEIGHT)
+        } else {
+            (chip8_constants::WIDTH, chip8_constants::HEIGHT)
+        };
+        let display = reader.bytes(width * height)?;
+        if display.iter().any(|&pixel| pixel > 0b11) {
+            return Err(StateError::Invalid("display"));
+        }
+
+        let rpl_flags = reader.array()?;
+
+        // Random number generator
+        let rng_length = reader.u16()?;
+        let rng = reader.bytes(rng_length as usize)?;
+
+        // Everything was read successfully, the machine can be updated
+        if !self.rng.restore(rng) {
+            return Err(StateError::Invalid("random number generator state"));
+        }
+        self.quirks = quirks;
+        self.registers = registers;
+        self.index = index;
+        self.pc = pc;
+        self.stack = stack;
+        self.sp = sp;
+        self.opcode = opcode;
+        self.halted = halted;
+        self.memory.copy_from_slice(memory);
+        self.delay_timer = delay_timer;
+        self.sound_timer = sound_timer;
+        self.pitch = pitch;
+        self.audio_pattern = has_pattern.then_some(pattern);
+        self.keypad = keypad;
+        self.key_wait = key_wait;
+        self.hires = hires;
+        self.planes = planes;
+        self.drawn = drawn;
+        self.display = display.to_vec();
+        self.rpl_flags = rpl_flags;
+
+        // The breakpoint reported before belongs to the previous execution
+        self.resume_from = None;
+
+        Ok(())
+    }
+}
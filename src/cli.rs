@@ -0,0 +1,481 @@
+//! Command-line interface of the emulator.
+//!
+//! This module parses the command-line arguments into `Args`. The settings given on the command
+//! line take precedence over the configuration file, and are resolved together into `Options`.
+
+use std::path::PathBuf;
+
+use raylib::prelude::*;
+use rust_chip8::audio::{AudioSettings, Waveform};
+use rust_chip8::chip8::breakpoints::{parse_number as parse_address, Breakpoints, Operand};
+use rust_chip8::config::Settings;
+use rust_chip8::{constants, Platform, Quirks};
+
+use crate::gamepad::GamepadMap;
+use crate::keymap::KeyMap;
+
+/// The usage message, printed with `--help` or when the arguments are invalid.
+pub const USAGE: &str = "\
+Usage: Rust-CHIP8 [OPTIONS] <ROM file>
+
+A ROM file with the .8o extension is an Octo source, assembled before running.
+
+Options:
+  --ips <N>              Instructions executed per second (default: 1000)
+  --cycles-per-frame <N> Instructions executed per 60 Hz frame, instead of --ips
+  --scale <N>            Size of a CHIP-8 pixel on screen (default: 48)
+  --fg <RRGGBB>          Color of the pixels that are on (default: FFC82D)
+  --bg <RRGGBB>          Color of the pixels that are off (default: 2D0A2D)
+  --platform <NAME>      Quirk preset: vip, chip48, schip or xochip (default: vip)
+  --quirk <NAME>=<on|off>
+                         Override a single quirk: shift, memory_increment, vf_reset,
+                         jump, clipping, display_wait or key_wait_beep (can be repeated)
+  --volume <N>           Sound volume, from 0.0 to 1.0 (default: 0.25)
+  --tone-freq <HZ>       Frequency of the beep tone, from 20 to 20000 (default: 440)
+  --waveform <NAME>      Shape of the beep tone: square, triangle, sawtooth or sine
+                         (default: square)
+  --sound-file <FILE>    Sound file (e.g. MP3 or WAV) played instead of the beep tone
+  --mute                 Disable the sound
+  --rewind <SECONDS>     Length of the rewind history, 0 to disable (default: 10)
+  --fullscreen           Start in fullscreen
+  --keymap <KEYS>        Keyboard keys bound to the CHIP-8 keys 0 to F, as a preset
+                         (qwerty, azerty, dvorak or numpad), 16 characters
+                         (e.g. X123QWEASDZC4RFV) or 16 key names separated by spaces
+                         (e.g. \"KP_0 KP_1 ... KP_ADD\") (default: qwerty)
+  --gamepad <MAP>        Gamepad inputs bound to CHIP-8 keys, as INPUT=KEY pairs
+                         separated by spaces (e.g. \"UP=1 DOWN=4 A=5\"), with the
+                         inputs UP, DOWN, LEFT, RIGHT, A, B, X, Y, LB, RB, LT, RT,
+                         SELECT, START, L3, R3, LSTICK_UP... and RSTICK_UP...
+                         (default: D-pad and left stick on 5/7/8/9, A on 6, B on 4)
+  --seed <N>             Seed of the random number generator
+  --record <FILE>        Record the keypad of every frame to the movie FILE
+  --play <FILE>          Play back the keypad recorded in the movie FILE
+  --break <ADDR>[:<COND>] Pause before the instruction at ADDR, optionally only when
+                         the condition COND holds (e.g. 0x2A0 or \"0x2A0:V3 == 0x10\")
+  --break-if <COND>      Pause when the condition becomes true (e.g. \"I >= 0xF00\")
+  --watch <START>[-<END>][:r|w|rw]
+                         Pause when memory is read or written (e.g. 0x200-0x3FF:w)
+  --watch-reg <Vx>       Pause when a register changes (e.g. V3)
+  --config <FILE>        Configuration file
+                         (default: ~/.config/rust-chip8/config.toml)
+  --remember             Save the settings given on the command line for this ROM
+                         in the configuration file
+  -h, --help             Print this message";
+
+/// The parsed command-line arguments.
+#[derive(Debug, Clone)]
+pub struct Args {
+    /// The path of the ROM to run, or of an Octo source (`.8o`) to assemble.
+    pub rom: String,
+
+    /// The configuration file given with `--config`.
+    pub config: Option<PathBuf>,
+
+    /// Saves `settings` in the configuration file for this ROM.
+    pub remember: bool,
+
+    /// Disables the sound.
+    pub mute: bool,
+
+    /// Starts in fullscreen.
+    pub fullscreen: bool,
+
+    /// The seed of the random number generator.
+    pub seed: Option<u64>,
+
+    /// The movie file to record the run to, given with `--record`.
+    pub record: Option<PathBuf>,
+
+    /// The movie file to play back, given with `--play`.
+    pub play: Option<PathBuf>,
+
+    /// The breakpoints and watchpoints, pausing the emulator in the debugger.
+    pub breakpoints: Breakpoints,
+
+    /// The settings given on the command line, overriding the configuration file.
+    pub settings: Settings,
+}
+
+/// The result of parsing the command line.
+#[derive(Debug)]
+pub enum Command {
+    /// Run the emulator with the given arguments.
+    Run(Box<Args>),
+
+    /// Print the usage message.
+    Help,
+}
+
+/// The options of the emulator, resolved from the command line and the configuration file.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The number of instructions executed per second.
+    pub ips: f32,
+
+    /// The size of a CHIP-8 pixel on screen.
+    pub scale: f32,
+
+    /// The color of the pixels that are on.
+    pub fg: Color,
+
+    /// The color of the pixels that are off.
+    pub bg: Color,
+
+    /// The interpretation of the ambiguous instructions.
+    pub quirks: Quirks,
+
+    /// The settings of the sound output.
+    pub audio: AudioSettings,
+
+    /// The keyboard keys bound to the CHIP-8 keypad.
+    pub keymap: KeyMap,
+
+    /// The gamepad inputs bound to the CHIP-8 keypad.
+    pub gamepad: GamepadMap,
+
+    /// The length of the rewind history in seconds.
+    pub rewind: f32,
+}
+
+impl Options {
+    /// Resolves the options from `settings`, using the defaults for the unset ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message describing the first invalid setting.
+    pub fn resolve(settings: &Settings) -> Result<Self, String> {
+        let ips = match (settings.cycles_per_frame, settings.ips) {
+            (Some(cycles), _) => cycles as f32 * constants::TIMER_FREQUENCY,
+            (None, Some(ips)) => ips,
+            (None, None) => constants::EMULATOR_IPS,
+        };
+        // Below half an instruction per frame, no instruction would ever run
+        if !ips.is_finite() || (ips / constants::TIMER_FREQUENCY).round() < 1.0 {
+            return Err(format!(
+                "invalid speed `{ips}`, expected at least {} instructions per second",
+                constants::TIMER_FREQUENCY / 2.0
+            ));
+        }
+
+        let scale = settings.scale.unwrap_or(48.0);
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(format!(
+                "invalid scale `{scale}`, expected a positive number"
+            ));
+        }
+
+        let volume = settings.volume.unwrap_or(0.25);
+        if !(0.0..=1.0).contains(&volume) {
+            return Err(format!("invalid volume `{volume}`, expected 0.0 to 1.0"));
+        }
+
+        let frequency = settings.tone_freq.unwrap_or(440.0);
+        if !(20.0..=20000.0).contains(&frequency) {
+            return Err(format!(
+                "invalid tone frequency `{frequency}`, expected 20 to 20000 Hz"
+            ));
+        }
+
+        if let Some(path) = &settings.sound_file {
+            if !path.is_file() {
+                return Err(format!("sound file `{}` not found", path.display()));
+            }
+        }
+
+        let rewind = settings.rewind.unwrap_or(10.0);
+        if !rewind.is_finite() || rewind < 0.0 {
+            return Err(format!(
+                "invalid rewind length `{rewind}`, expected 0 or more"
+            ));
+        }
+
+        let platform: Platform = match &settings.platform {
+            Some(name) => name.parse().map_err(|err| format!("{err}"))?,
+            None => Platform::default(),
+        };
+        let mut quirks = platform.quirks();
+        for (name, &enabled) in &settings.quirks {
+            *quirks.get_mut(name).ok_or_else(|| unknown_quirk(name))? = enabled;
+        }
+
+        Ok(Self {
+            ips,
+            scale,
+            fg: match &settings.fg {
+                Some(color) => parse_color(color)?,
+                None => Color::new(255, 200, 45, 255),
+            },
+            bg: match &settings.bg {
+                Some(color) => parse_color(color)?,
+                None => Color::new(45, 10, 45, 255),
+            },
+            quirks,
+            audio: AudioSettings {
+                frequency,
+                volume,
+                waveform: match &settings.waveform {
+                    Some(name) => name.parse().map_err(|err| format!("{err}"))?,
+                    None => Waveform::default(),
+                },
+                sample: settings.sound_file.clone(),
+            },
+            keymap: match &settings.keymap {
+                Some(keys) => keys.parse()?,
+                None => KeyMap::default(),
+            },
+            gamepad: match &settings.gamepad {
+                Some(mapping) => mapping.parse()?,
+                None => GamepadMap::default(),
+            },
+            rewind,
+        })
+    }
+
+    /// Returns the number of instructions executed per 60 Hz frame.
+    pub fn cycles_per_frame(&self) -> usize {
+        (self.ips / constants::TIMER_FREQUENCY).round() as usize
+    }
+
+    /// Returns the number of frames kept in the rewind history.
+    pub fn rewind_frames(&self) -> usize {
+        (self.rewind * constants::TIMER_FREQUENCY).round() as usize
+    }
+}
+
+/// Parses the command-line arguments (without the program name).
+///
+/// # Errors
+///
+/// Returns a message describing the first invalid argument.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.into_iter();
+
+    let mut rom = None;
+    let mut config = None;
+    let mut remember = false;
+    let mut mute = false;
+    let mut fullscreen = false;
+    let mut seed = None;
+    let mut record = None;
+    let mut play = None;
+    let mut breakpoints = Breakpoints::default();
+    let mut settings = Settings::default();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for `{name}`"))
+        };
+
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--ips" => settings.ips = Some(parse_number(&arg, &value(&arg)?)?),
+            "--cycles-per-frame" => {
+                settings.cycles_per_frame = Some(parse_number(&arg, &value(&arg)?)?)
+            }
+            "--scale" => settings.scale = Some(parse_number(&arg, &value(&arg)?)?),
+            "--fg" => settings.fg = Some(value(&arg)?),
+            "--bg" => settings.bg = Some(value(&arg)?),
+            "--platform" => settings.platform = Some(value(&arg)?),
+            "--quirk" => {
+                let (name, enabled) = parse_quirk(&value(&arg)?)?;
+                settings.quirks.insert(name, enabled);
+            }
+            "--volume" => settings.volume = Some(parse_number(&arg, &value(&arg)?)?),
+            "--tone-freq" => settings.tone_freq = Some(parse_number(&arg, &value(&arg)?)?),
+            "--waveform" => settings.waveform = Some(value(&arg)?),
+            "--sound-file" => settings.sound_file = Some(PathBuf::from(value(&arg)?)),
+            "--rewind" => settings.rewind = Some(parse_number(&arg, &value(&arg)?)?),
+            "--mute" => mute = true,
+            "--fullscreen" => fullscreen = true,
+            "--keymap" => settings.keymap = Some(value(&arg)?),
+            "--gamepad" => settings.gamepad = Some(value(&arg)?),
+            "--seed" => seed = Some(parse_number(&arg, &value(&arg)?)?),
+            "--record" => record = Some(PathBuf::from(value(&arg)?)),
+            "--play" => play = Some(PathBuf::from(value(&arg)?)),
+            "--break" => {
+                let value = value(&arg)?;
+                let (address, condition) = match value.split_once(':') {
+                    Some((address, condition)) => (address, Some(condition.parse()?)),
+                    None => (value.as_str(), None),
+                };
+                breakpoints.add_breakpoint(parse_address(address.trim())?, condition);
+            }
+            "--break-if" => breakpoints.add_condition(value(&arg)?.parse()?),
+            "--watch" => breakpoints.add_memory_watchpoint(value(&arg)?.parse()?),
+            "--watch-reg" => match value(&arg)?.parse()? {
+                Operand::Register(register) => breakpoints.watch_register(register),
+                operand => return Err(format!("`{operand}` is not a register")),
+            },
+            "--config" => config = Some(PathBuf::from(value(&arg)?)),
+            "--remember" => remember = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown option `{arg}`")),
+            _ if rom.is_none() => rom = Some(arg),
+            _ => return Err(format!("unexpected argument `{arg}`")),
+        }
+    }
+
+    if record.is_some() && play.is_some() {
+        return Err("`--record` and `--play` cannot be used together".to_string());
+    }
+
+    // Catch invalid values before they are saved by `--remember`
+    Options::resolve(&settings)?;
+
+    Ok(Command::Run(Box::new(Args {
+        rom: rom.ok_or("missing ROM file")?,
+        config,
+        remember,
+        mute,
+        fullscreen,
+        seed,
+        record,
+        play,
+        breakpoints,
+        settings,
+    })))
+}
+
+/// Parses the numeric value of the option `name`.
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value `{value}` for `{name}`"))
+}
+
+/// Parses a `RRGGBB` hexadecimal color, optionally prefixed with `#`.
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let rgb = (hex.len() == 6)
+        .then(|| u32::from_str_radix(hex, 16).ok())
+        .flatten()
+        .ok_or_else(|| format!("invalid color `{value}`, expected RRGGBB"))?;
+
+    Ok(Color::new(
+        (rgb >> 16) as u8,
+        (rgb >> 8) as u8,
+        rgb as u8,
+        255,
+    ))
+}
+
+/// Parses a `NAME=on|off` quirk override.
+fn parse_quirk(value: &str) -> Result<(String, bool), String> {
+    let (name, enabled) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid quirk `{value}`, expected NAME=on|off"))?;
+
+    if !Quirks::NAMES.contains(&name) {
+        return Err(unknown_quirk(name));
+    }
+
+    let enabled = match enabled {
+        "on" | "true" | "1" => true,
+        "off" | "false" | "0" => false,
+        _ => return Err(format!("invalid value `{enabled}` for quirk `{name}`")),
+    };
+
+    Ok((name.to_string(), enabled))
+}
+
+/// Returns the error message for an unknown quirk `name`.
+fn unknown_quirk(name: &str) -> String {
+    format!(
+        "unknown quirk `{name}`, expected one of: {}",
+        Quirks::NAMES.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Options;
+    use rust_chip8::config::Settings;
+
+    #[test]
+    fn resolve_uses_the_defaults() {
+        let options = Options::resolve(&Settings::default()).unwrap();
+
+        assert_eq!(options.cycles_per_frame(), 17);
+        assert_eq!(options.scale, 48.0);
+    }
+
+    #[test]
+    fn resolve_prefers_the_cycles_per_frame_to_the_ips() {
+        let settings = Settings {
+            ips: Some(600.0),
+            cycles_per_frame: Some(20),
+            ..Settings::default()
+        };
+
+        assert_eq!(Options::resolve(&settings).unwrap().cycles_per_frame(), 20);
+    }
+
+    #[test]
+    fn resolve_rejects_a_speed_without_instructions() {
+        for ips in [f32::NAN, f32::INFINITY, -1000.0, 0.0, 29.0] {
+            let settings = Settings {
+                ips: Some(ips),
+                ..Settings::default()
+            };
+            assert!(Options::resolve(&settings).is_err(), "ips {ips}");
+        }
+
+        let settings = Settings {
+            cycles_per_frame: Some(0),
+            ..Settings::default()
+        };
+        assert!(Options::resolve(&settings).is_err());
+    }
+
+    #[test]
+    fn resolve_keeps_the_slowest_speed() {
+        let settings = Settings {
+            ips: Some(30.0),
+            ..Settings::default()
+        };
+
+        assert_eq!(Options::resolve(&settings).unwrap().cycles_per_frame(), 1);
+    }
+
+    #[test]
+    fn resolve_rejects_invalid_numbers() {
+        let invalid = [
+            Settings {
+                scale: Some(f32::NAN),
+                ..Settings::default()
+            },
+            Settings {
+                scale: Some(0.0),
+                ..Settings::default()
+            },
+            Settings {
+                volume: Some(1.5),
+                ..Settings::default()
+            },
+            Settings {
+                rewind: Some(f32::NAN),
+                ..Settings::default()
+            },
+        ];
+
+        for settings in invalid {
+            assert!(Options::resolve(&settings).is_err(), "{settings:?}");
+        }
+    }
+
+    #[test]
+    fn resolve_applies_the_quirk_overrides() {
+        let mut settings = Settings {
+            platform: Some("schip".to_string()),
+            ..Settings::default()
+        };
+        settings.quirks.insert("clipping".to_string(), false);
+
+        let options = Options::resolve(&settings).unwrap();
+        assert!(!options.quirks.clipping);
+        assert!(options.quirks.shift);
+
+        settings.quirks.insert("unknown".to_string(), true);
+        assert!(Options::resolve(&settings).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! # Configuration File
+//!
+//! This module reads and writes the user configuration file, a TOML file holding the default
+//! settings of the frontend and per-ROM overrides. A ROM is identified by the SHA-1 hash of its
+//! bytes (see [`Chip8::get_rom_hash`](crate::Chip8::get_rom_hash)), so its settings follow it
+//! whatever its file name is.
+//!
+//! ```toml
+//! [default]
+//! fg = "FFC82D"
+//! bg = "2D0A2D"
+//! ips = 1000
+//! volume = 0.25
+//! tone_freq = 440
+//! waveform = "triangle"
+//! keymap = "azerty"
+//!
+//! [roms.0123456789abcdef0123456789abcdef01234567]
+//! name = "Some puzzle game"
+//! cycles_per_frame = 20
+//! platform = "chip48"
+//! quirks = { shift = true }
+//!
+//! [roms.fedcba9876543210fedcba9876543210fedcba98]
+//! name = "Pong"
+//! gamepad = "UP=1 DOWN=4 LSTICK_UP=1 LSTICK_DOWN=4"
+//! ```
+//!
+//! The values are kept as written (e.g. colors are `RRGGBB` strings), the frontend validates them.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// An error raised while reading or writing the configuration file.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The file could not be read or written.
+    Io(std::io::Error),
+
+    /// The file is not a valid configuration.
+    Parse(toml::de::Error),
+
+    /// The configuration could not be serialized.
+    Serialize(toml::ser::Error),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "I/O error: {err}"),
+            Self::Parse(err) => write!(f, "invalid configuration: {err}"),
+            Self::Serialize(err) => write!(f, "failed to serialize the configuration: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse(err) => Some(err),
+            Self::Serialize(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A set of optional settings; the unset ones fall back to a lower priority source.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// A name for the ROM, only informative.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// The number of instructions executed per second.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ips: Option<f32>,
+
+    /// The number of instructions executed per 60 Hz frame, takes precedence over `ips`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cycles_per_frame: Option<u32>,
+
+    /// The size of a CHIP-8 pixel on screen.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f32>,
+
+    /// The color of the pixels that are on, as `RRGGBB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+
+    /// The color of the pixels that are off, as `RRGGBB`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+
+    /// The keyboard keys bound to the CHIP-8 keys 0 to F, a layout preset (e.g. `azerty`) or
+    /// a list of keys (e.g. `X123QWEASDZC4RFV`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keymap: Option<String>,
+
+    /// The gamepad inputs bound to CHIP-8 keys, as `INPUT=KEY` pairs separated by spaces
+    /// (e.g. `UP=1 DOWN=4`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<String>,
+
+    /// The output volume, from 0.0 (muted) to 1.0.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<f32>,
+
+    /// The frequency of the beep tone in Hz.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tone_freq: Option<f32>,
+
+    /// The shape of the beep tone (`square`, `triangle`, `sawtooth` or `sine`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waveform: Option<String>,
+
+    /// A sound file (e.g. MP3 or WAV) played in a loop instead of the beep tone.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sound_file: Option<PathBuf>,
+
+    /// The length of the rewind history in seconds (0 disables rewinding).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewind: Option<f32>,
+
+    /// The quirk preset (`vip`, `chip48`, `schip` or `xochip`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+
+    /// Overrides of single quirks, applied after the preset (see [`Quirks::NAMES`](crate::Quirks::NAMES)).
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub quirks: BTreeMap<String, bool>,
+}
+
+impl Settings {
+    /// Returns these settings overridden by the ones set in `other`.
+    ///
+    /// The speed is overridden as a whole: setting either `ips` or `cycles_per_frame` in `other`
+    /// replaces both, so a per-ROM `cycles_per_frame` is not shadowed by a default `ips`.
+    /// The quirk overrides of both are kept, the ones of `other` winning.
+    pub fn merge(mut self, other: Self) -> Self {
+        if other.ips.is_some() || other.cycles_per_frame.is_some() {
+            self.ips = other.ips;
+            self.cycles_per_frame = other.cycles_per_frame;
+        }
+
+        self.name = other.name.or(self.name);
+        self.scale = other.scale.or(self.scale);
+        self.fg = other.fg.or(self.fg);
+        self.bg = other.bg.or(self.bg);
+        self.keymap = other.keymap.or(self.keymap);
+        self.gamepad = other.gamepad.or(self.gamepad);
+        self.volume = other.volume.or(self.volume);
+        self.tone_freq = other.tone_freq.or(self.tone_freq);
+        self.waveform = other.waveform.or(self.waveform);
+        self.sound_file = other.sound_file.or(self.sound_file);
+        self.rewind = other.rewind.or(self.rewind);
+        self.platform = other.platform.or(self.platform);
+        self.quirks.extend(other.quirks);
+
+        self
+    }
+}
+
+/// The content of the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The settings used for every ROM.
+    pub default: Settings,
+
+    /// The per-ROM settings, keyed by the hexadecimal SHA-1 hash of the ROM.
+    pub roms: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    /// Returns the default location of the configuration file.
+    ///
+    /// This is `rust-chip8/config.toml` in `$XDG_CONFIG_HOME` (or `~/.config`),
+    /// or in `%APPDATA%` on Windows. Returns `None` if none of these variables are set.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+        Some(dir.join("rust-chip8").join("config.toml"))
+    }
+
+    /// Reads the configuration file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::Io` if the file cannot be read, or `ConfigError::Parse`
+    /// if it is not a valid configuration.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the configuration to `path`, creating the parent directories if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConfigError::Io` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), ConfigError> {
+        let content = toml::to_string_pretty(self).map_err(ConfigError::Serialize)?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, content)?;
+
+        Ok(())
+    }
+
+    /// Returns the settings of the ROM with the given hash: its own settings over the defaults.
+    ///
+    /// # Example
+    /// ```
+    /// use rust_chip8::config::{hash_to_hex, Config};
+    ///
+    /// let hash = [0xAB; 20];
+    /// let config: Config = format!(
+    ///     "[default]\nips = 700\nfg = \"FFFFFF\"\n\n[roms.{}]\ncycles_per_frame = 20\n",
+    ///     hash_to_hex(&hash)
+    /// )
+    /// .parse()
+    /// .unwrap();
+    ///
+    /// let settings = config.settings_for(&hash);
+    /// assert_eq!(settings.cycles_per_frame, Some(20));
+    /// assert_eq!(settings.ips, None);
+    /// assert_eq!(settings.fg.as_deref(), Some("FFFFFF"));
+    /// ```
+    pub fn settings_for(&self, rom_hash: &[u8; 20]) -> Settings {
+        let settings = self.default.clone();
+
+        match self.roms.get(&hash_to_hex(rom_hash)) {
+            Some(rom) => settings.merge(rom.clone()),
+            None => settings,
+        }
+    }
+
+    /// Returns a mutable reference to the settings of the ROM with the given hash,
+    /// creating an empty section if there is none.
+    pub fn rom_settings_mut(&mut self, rom_hash: &[u8; 20]) -> &mut Settings {
+        self.roms.entry(hash_to_hex(rom_hash)).or_default()
+    }
+}
+
+impl std::str::FromStr for Config {
+    type Err = ConfigError;
+
+    fn from_str(content: &str) -> Result<Self, Self::Err> {
+        toml::from_str(content).map_err(ConfigError::Parse)
+    }
+}
+
+/// Formats a ROM hash as lowercase hexadecimal, as used for the keys of [`Config::roms`].
+pub fn hash_to_hex(hash: &[u8; 20]) -> String {
+    hash.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -3,15 +3,25 @@
 /// How many cycles per second the emulator should execute.
 pub const EMULATOR_IPS: f32 = 1000.0;
 
+/// The **timer frequency** in Hz.
+///
+/// The delay and sound timers decrement at this rate, independently of `EMULATOR_IPS`.
+pub const TIMER_FREQUENCY: f32 = 60.0;
+
+/// The number of **cycles executed per frame**.
+///
+/// One frame lasts one timer tick, so this is `EMULATOR_IPS / TIMER_FREQUENCY`.
+pub const CYCLES_PER_FRAME: usize = (EMULATOR_IPS / TIMER_FREQUENCY) as usize;
+
 /// The **screen refresh rate** for rendering frames.
 ///
-/// This value is set to **double the emulator FPS** (`EMULATOR_FPS * 2.0`).
-pub const SCREEN_FPS: u32 = (EMULATOR_IPS * 2.0) as u32;
+/// One frame is rendered per timer tick (`TIMER_FREQUENCY`).
+pub const SCREEN_FPS: u32 = TIMER_FREQUENCY as u32;
 
 /// The **target frame time** for the emulator in seconds.
 ///
 /// This is the value that the emulator will try to match for each frame.
-pub const FRAME_TIME: f32 = 1.0 / EMULATOR_IPS;
+pub const FRAME_TIME: f32 = 1.0 / TIMER_FREQUENCY;
 
 /// The **width** of the screen in pixels.
 pub const SCREEN_WIDTH: usize = chip8_constants::WIDTH;
@@ -27,8 +37,21 @@ pub mod chip8_constants {
     /// The **height** of the display (32 pixels).
     pub const HEIGHT: usize = 32;
 
-    /// The **total size of RAM** in the system (4 KB or 4096 bytes).
-    pub const RAM_SIZE: usize = 4096;
+    /// The **width** of the SUPER-CHIP high-resolution display (128 pixels).
+    pub const HIRES_WIDTH: usize = 128;
+
+    /// The **height** of the SUPER-CHIP high-resolution display (64 pixels).
+    pub const HIRES_HEIGHT: usize = 64;
+
+    /// The **total size of RAM** in the system (64 KB or 65536 bytes).
+    ///
+    /// This is the XO-CHIP memory size, CHIP-8 and SUPER-CHIP programs only use the first 4 KB.
+    pub const RAM_SIZE: usize = 65536;
+
+    /// The **number of display planes** (XO-CHIP).
+    ///
+    /// Each pixel of the display holds one bit per plane, giving 4 colors.
+    pub const PLANES: usize = 2;
 
     /// The **starting memory address** for programs (0x200).
     ///
@@ -51,7 +74,7 @@ pub mod chip8_constants {
     /// where each character is stored as a 5-byte value.
     ///
     /// Example for `F`:
-    /// ```
+    /// ```text
     /// 0xF0 = 1111 0000
     /// 0x80 = 1000 0000
     /// 0xF0 = 1111 0000
@@ -78,4 +101,52 @@ pub mod chip8_constants {
         0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
         0xF0, 0x80, 0xF0, 0x80, 0x80, // F
     ];
+
+    /// The **size of the XO-CHIP audio pattern buffer** (16 bytes).
+    ///
+    /// The buffer holds 128 1-bit samples, played in a loop while the sound timer is active.
+    pub const AUDIO_PATTERN_SIZE: usize = 16;
+
+    /// The **default XO-CHIP pitch** (64).
+    ///
+    /// At this pitch, the audio pattern is played at 4000 samples per second.
+    pub const DEFAULT_PITCH: u8 = 64;
+
+    /// The **sound timer value kept while a key is held during `Fx0A`** (4), with the
+    /// `key_wait_beep` quirk.
+    ///
+    /// The COSMAC VIP beeps while the key is held, and for a few frames after it is released.
+    pub const KEY_BEEP_TIME: u8 = 4;
+
+    /// The **starting address of the SUPER-CHIP big fontset** in memory (0xA0).
+    ///
+    /// It is stored right after the default fontset.
+    pub const BIG_FONTSET_START_ADDRESS: usize = FONTSET_START_ADDRESS + FONTSET_SIZE;
+
+    /// The **size of the big fontset** (160 bytes).
+    ///
+    /// Each character in the big fontset is **10 bytes** (10x8 pixels).
+    pub const BIG_FONTSET_SIZE: usize = 160;
+
+    /// The **SUPER-CHIP big fontset** (16 characters, each 10 bytes).
+    ///
+    /// SUPER-CHIP only defines the digits 0-9, the letters A-F are the ones used by Octo.
+    pub const BIG_FONTSET: [u8; BIG_FONTSET_SIZE] = [
+        0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+        0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+        0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+        0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+        0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+        0x3E, 0x7C, 0xE0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+        0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+        0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C, // 9
+        0x7E, 0xFF, 0xC3, 0xC3, 0xC3, 0xFF, 0xFF, 0xC3, 0xC3, 0xC3, // A
+        0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, 0xC3, 0xC3, 0xFC, 0xFC, // B
+        0x3C, 0xFF, 0xC3, 0xC0, 0xC0, 0xC0, 0xC0, 0xC3, 0xFF, 0x3C, // C
+        0xFC, 0xFE, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xC3, 0xFE, 0xFC, // D
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, // E
+        0xFF, 0xFF, 0xC0, 0xC0, 0xFF, 0xFF, 0xC0, 0xC0, 0xC0, 0xC0, // F
+    ];
 }
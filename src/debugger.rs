@@ -0,0 +1,386 @@
+//! Interactive debugger of the emulator.
+//!
+//! This module contains the `Debugger` struct, which pauses and steps the machine from the
+//! keyboard, and draws a panel showing the CPU state next to the display:
+//! - `F9`: show or hide the panel
+//! - `F10`: step over (run a `CALL` until it returns)
+//! - `F11`: step a single instruction
+//! - `F12`: pause or continue
+//!
+//! Stepping is disabled during a movie, the recorded inputs are applied once per frame.
+
+use raylib::prelude::*;
+use rust_chip8::assembler::Program;
+use rust_chip8::chip8::disassembler::{disassemble, disassemble_at};
+use rust_chip8::{BreakReason, Chip8, Chip8Error, StepOutcome};
+
+/// The number of upcoming instructions listed in the panel.
+const UPCOMING_INSTRUCTIONS: usize = 12;
+
+/// The maximum number of instructions executed by a step over, in case the subroutine never returns.
+const STEP_OVER_LIMIT: usize = 1_000_000;
+
+/// The state of the debugger.
+#[derive(Debug, Default)]
+pub struct Debugger {
+    /// Whether the panel is shown.
+    visible: bool,
+
+    /// Whether the machine is paused.
+    paused: bool,
+
+    /// The assembled program, whose labels name the addresses.
+    program: Option<Program>,
+}
+
+impl Debugger {
+    /// Returns `true` if the panel is shown.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Returns `true` if the machine is paused, the frontend must not run frames then.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Sets the assembled program running, to show its labels.
+    pub fn set_program(&mut self, program: Program) {
+        self.program = Some(program);
+    }
+
+    /// Pauses the machine and shows the panel.
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.visible = true;
+    }
+
+    /// Returns `true` if a step key (`F10` or `F11`) was pressed this frame.
+    pub fn is_step_requested(rl_handle: &RaylibHandle) -> bool {
+        rl_handle.is_key_pressed(KeyboardKey::KEY_F10)
+            || rl_handle.is_key_pressed(KeyboardKey::KEY_F11)
+    }
+
+    /// Handles the debugger keys, stepping the machine if requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `rl_handle` - A reference to the Raylib handle, to read the keyboard.
+    /// * `chip8` - The machine to step.
+    /// * `cycles_per_frame` - The number of instructions per frame, the timers are ticked
+    ///   at this rate during a step over.
+    /// * `can_step` - Whether `F10` and `F11` may step the machine, they are ignored otherwise.
+    ///
+    /// # Returns
+    ///
+    /// The reason of the break if a step stopped on a breakpoint or a watchpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns the `Chip8Error` raised by a stepped instruction.
+    pub fn update(
+        &mut self,
+        rl_handle: &RaylibHandle,
+        chip8: &mut Chip8,
+        cycles_per_frame: usize,
+        can_step: bool,
+    ) -> Result<Option<BreakReason>, Chip8Error> {
+        let mut outcome = StepOutcome::Executed;
+
+        if rl_handle.is_key_pressed(KeyboardKey::KEY_F9) {
+            self.visible = !self.visible;
+        }
+
+        if rl_handle.is_key_pressed(KeyboardKey::KEY_F12) {
+            self.paused = !self.paused;
+            self.visible |= self.paused;
+        }
+
+        if !can_step {
+            return Ok(None);
+        }
+
+        if rl_handle.is_key_pressed(KeyboardKey::KEY_F11) {
+            self.pause();
+            outcome = chip8.step()?;
+        }
+
+        if rl_handle.is_key_pressed(KeyboardKey::KEY_F10) {
+            self.pause();
+            outcome = step_over(chip8, cycles_per_frame)?;
+        }
+
+        match outcome {
+            StepOutcome::BreakpointHit(reason) => Ok(Some(reason)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Draws the panel, if shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - A mutable reference to `RaylibDrawHandle` for rendering.
+    /// * `chip8` - The machine to inspect.
+    /// * `x` - The left edge of the panel, it extends to the right edge of the screen.
+    pub fn draw(&self, handle: &mut RaylibDrawHandle<'_>, chip8: &Chip8, x: i32) {
+        if !self.visible {
+            return;
+        }
+
+        let (width, height) = (handle.get_screen_width(), handle.get_screen_height());
+        let size = (height / 48).max(10);
+        let margin = size / 2;
+        handle.draw_rectangle(x, 0, width - x, height, Color::new(20, 20, 20, 230));
+
+        let mut y = margin;
+        let mut line = |handle: &mut RaylibDrawHandle<'_>, text: &str, color: Color| {
+            handle.draw_text(text, x + margin, y, size, color);
+            y += size + size / 4;
+        };
+
+        let (status, status_color) = if self.paused {
+            ("PAUSED", Color::ORANGE)
+        } else {
+            ("RUNNING", Color::GREEN)
+        };
+        line(handle, status, status_color);
+        line(handle, "F10 step over  F11 step  F12 pause", Color::GRAY);
+        line(handle, "", Color::WHITE);
+
+        line(
+            handle,
+            &format!(
+                "PC {:03X}  I {:03X}  SP {}",
+                chip8.get_pc(),
+                chip8.get_index(),
+                chip8.get_sp()
+            ),
+            Color::WHITE,
+        );
+        if let Some(symbol) = self
+            .program
+            .as_ref()
+            .and_then(|program| program.get_symbol(chip8.get_pc()))
+        {
+            line(handle, &format!("   {symbol}"), Color::SKYBLUE);
+        }
+        line(
+            handle,
+            &format!(
+                "DT {:02X}  ST {:02X}",
+                chip8.get_delay_timer(),
+                chip8.get_sound_timer()
+            ),
+            Color::WHITE,
+        );
+        for (i, values) in chip8.get_registers().chunks(4).enumerate() {
+            let text = values
+                .iter()
+                .enumerate()
+                .map(|(j, value)| format!("V{:X} {value:02X}", i * 4 + j))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line(handle, &text, Color::WHITE);
+        }
+
+        let stack = &chip8.get_stack()[..chip8.get_sp() as usize];
+        let stack = stack
+            .iter()
+            .map(|address| format!("{address:03X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        line(handle, &format!("Stack {stack}"), Color::WHITE);
+
+        let keys: String = chip8
+            .get_keypad()
+            .iter()
+            .enumerate()
+            .map(|(key, &pressed)| {
+                if pressed {
+                    format!("{key:X}")
+                } else {
+                    ".".to_string()
+                }
+            })
+            .collect();
+        let waiting = if chip8.is_waiting_for_key() {
+            "  waiting"
+        } else {
+            ""
+        };
+        line(handle, &format!("Keys  {keys}{waiting}"), Color::WHITE);
+
+        let breakpoints = chip8.get_breakpoints();
+        let addresses = breakpoints
+            .get_breakpoints()
+            .map(|(address, condition)| match condition {
+                Some(_) => format!("{address:03X}?"),
+                None => format!("{address:03X}"),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !addresses.is_empty() {
+            line(handle, &format!("Break {addresses}"), Color::RED);
+        }
+        for condition in breakpoints.get_conditions() {
+            line(handle, &format!("Break if {condition}"), Color::RED);
+        }
+        for watchpoint in breakpoints.get_memory_watchpoints() {
+            line(handle, &format!("Watch {watchpoint}"), Color::RED);
+        }
+        let registers = breakpoints
+            .get_watched_registers()
+            .map(|register| format!("V{register:X}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !registers.is_empty() {
+            line(handle, &format!("Watch {registers}"), Color::RED);
+        }
+        line(handle, "", Color::WHITE);
+
+        // Last executed instruction, then the upcoming ones
+        let opcode = chip8.get_opcode();
+        line(
+            handle,
+            &format!("Last  {opcode:04X}  {}", disassemble(opcode)),
+            Color::GRAY,
+        );
+
+        let memory = chip8.get_memory();
+        let mut address = chip8.get_pc() as usize;
+        for i in 0..UPCOMING_INSTRUCTIONS {
+            let Some((text, length)) = disassemble_at(memory, address) else {
+                break;
+            };
+
+            let bytes: String = memory[address..address + length]
+                .iter()
+                .map(|byte| format!("{byte:02X}"))
+                .collect();
+            let label = self.program.as_ref().and_then(|program| {
+                program
+                    .labels
+                    .iter()
+                    .find(|(_, &label)| label as usize == address)
+            });
+            if let Some((name, _)) = label {
+                line(handle, &format!("{name}:"), Color::SKYBLUE);
+            }
+
+            let (marker, color) = if i == 0 {
+                (">", Color::YELLOW)
+            } else {
+                (" ", Color::WHITE)
+            };
+            line(
+                handle,
+                &format!("{marker} {address:03X}  {bytes:<8}  {text}"),
+                color,
+            );
+
+            address += length;
+        }
+    }
+}
+
+/// Executes the next instruction, running a whole subroutine if it is a `CALL`.
+///
+/// The subroutine stops early if it waits for a key (`Fx0A`), since no key is read meanwhile.
+///
+/// # Arguments
+///
+/// * `chip8` - The machine to step.
+/// * `cycles_per_frame` - The number of instructions between two timer ticks.
+///
+/// # Errors
+///
+/// Returns the first `Chip8Error` raised.
+fn step_over(chip8: &mut Chip8, cycles_per_frame: usize) -> Result<StepOutcome, Chip8Error> {
+    let pc = chip8.get_pc() as usize;
+    let is_call = chip8
+        .get_memory()
+        .get(pc)
+        .is_some_and(|byte| byte & 0xF0 == 0x20);
+    if !is_call {
+        return chip8.step();
+    }
+
+    let (return_address, sp) = (chip8.get_pc().wrapping_add(2), chip8.get_sp());
+    let mut outcome = StepOutcome::Executed;
+    for i in 1..=STEP_OVER_LIMIT {
+        outcome = chip8.step()?;
+        if i % cycles_per_frame.max(1) == 0 {
+            chip8.tick_timers();
+        }
+
+        let returned = chip8.get_pc() == return_address && chip8.get_sp() == sp;
+        if outcome != StepOutcome::Executed || returned || chip8.is_waiting_for_key() {
+            break;
+        }
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::step_over;
+    use rust_chip8::{Chip8Builder, StepOutcome};
+
+    #[test]
+    fn step_over_runs_a_subroutine_until_it_returns() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x23, 0x00]) // CALL 0x300
+            .memory(0x300, &[0x61, 0x01, 0x24, 0x00, 0x00, 0xEE]) // LD V1, 1; CALL 0x400; RET
+            .memory(0x400, &[0x62, 0x02, 0x00, 0xEE]) // LD V2, 2; RET
+            .build();
+
+        assert_eq!(step_over(&mut chip8, 10).unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.get_pc(), 0x202);
+        assert_eq!(chip8.get_sp(), 0);
+        assert_eq!(chip8.get_registers()[0x1], 1);
+        assert_eq!(chip8.get_registers()[0x2], 2);
+    }
+
+    #[test]
+    fn step_over_ignores_a_nested_return_to_the_same_address() {
+        // The subroutine jumps back to the CALL once, its inner return lands on 0x202 with
+        // a deeper stack: ADD V1, 1; SE V1, 1; RET; JP 0x200
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x23, 0x00, 0x00, 0xEE]) // CALL 0x300; RET
+            .memory(0x300, &[0x71, 0x01, 0x31, 0x01, 0x00, 0xEE, 0x12, 0x00])
+            .stack(&[0x400])
+            .build();
+
+        assert_eq!(step_over(&mut chip8, 10).unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.get_pc(), 0x202);
+        assert_eq!(chip8.get_sp(), 1);
+        assert_eq!(chip8.get_registers()[0x1], 2);
+    }
+
+    #[test]
+    fn step_over_a_call_at_the_end_of_memory_returns_to_address_0() {
+        let mut chip8 = Chip8Builder::new()
+            .pc(0xFFFE)
+            .memory(0xFFFE, &[0x23, 0x00]) // CALL 0x300
+            .memory(0x300, &[0x00, 0xEE]) // RET
+            .build();
+
+        assert_eq!(step_over(&mut chip8, 10).unwrap(), StepOutcome::Executed);
+        assert_eq!(chip8.get_pc(), 0x0000);
+        assert_eq!(chip8.get_sp(), 0);
+    }
+
+    #[test]
+    fn step_over_stops_on_a_key_wait() {
+        let mut chip8 = Chip8Builder::new()
+            .memory(0x200, &[0x23, 0x00]) // CALL 0x300
+            .memory(0x300, &[0xF0, 0x0A, 0x00, 0xEE]) // LD V0, K; RET
+            .build();
+
+        assert_eq!(step_over(&mut chip8, 10).unwrap(), StepOutcome::Executed);
+        assert!(chip8.is_waiting_for_key());
+        assert_eq!(chip8.get_sp(), 1);
+    }
+}
@@ -1,13 +1,16 @@
 //! Drawing utilities for the emulator.
 //!
-//! This module contains the `MonoChromeDisplay` struct, which represents a simple monochrome display.
+//! This module contains the `MonoChromeDisplay` struct, which represents a simple monochrome display,
+//! the crash screen shown when the emulated program faults, and the status messages.
 
 use raylib::prelude::*;
 
 /// A monochrome display
 ///
 /// Represents a simple display with a fixed **primary** and **secondary** color.
+/// XO-CHIP programs drawing on the second plane use two additional colors, giving a 4-color palette.
 /// It supports scaling, centering, and updating pixel data.
+/// Its resolution follows the pixel data, while its size on screen stays the same.
 #[derive(Debug)]
 pub struct MonoChromeDisplay {
     /// The position of the display on the screen (top-left corner).
@@ -30,6 +33,12 @@ pub struct MonoChromeDisplay {
 
     /// The secondary color used for inactive pixels (off).
     secondary_color: Color,
+
+    /// The color used for pixels only active in the second XO-CHIP plane.
+    plane2_color: Color,
+
+    /// The color used for pixels active in both XO-CHIP planes.
+    both_planes_color: Color,
 }
 
 impl MonoChromeDisplay {
@@ -72,6 +81,8 @@ impl MonoChromeDisplay {
             texture: None,
             primary_color,
             secondary_color,
+            plane2_color: Color::new(200, 60, 45, 255),
+            both_planes_color: Color::new(255, 255, 255, 255),
         }
     }
 
@@ -94,34 +105,43 @@ impl MonoChromeDisplay {
     ///
     /// * `rl_handle` - A mutable reference to the Raylib handle.
     /// * `rl_thread` - A reference to the Raylib thread.
-    /// * `pixels` - A boolean array representing pixel states (`true` for primary color, `false` for secondary color).
+    /// * `pixels` - An array of color indices (`0` for secondary color, `1` for primary color, `2` and `3` for the plane colors).
+    /// * `size` - The dimensions (width, height) of the `pixels` array.
     ///
-    /// This function updates the texture based on the `pixels` array. It creates a new texture if none exists.
+    /// This function updates the texture based on the `pixels` array. It creates a new texture if none exists,
+    /// or if the dimensions changed (e.g. when switching between low and high resolution).
     pub fn update(
         &mut self,
         rl_handle: &mut RaylibHandle,
         rl_thread: &RaylibThread,
-        pixels: &[bool],
+        pixels: &[u8],
+        size: (usize, usize),
     ) {
+        self.resize(size);
+
         let (width, height) = (self.dimensions.x as usize, self.dimensions.y as usize);
         let mut pixel_data = vec![0; width * height * 4];
 
+        let palette = [
+            self.secondary_color,
+            self.primary_color,
+            self.plane2_color,
+            self.both_planes_color,
+        ];
+
         for (i, pixel) in pixels.iter().enumerate() {
-            if *pixel {
-                pixel_data[i * 4] = self.primary_color.r;
-                pixel_data[i * 4 + 1] = self.primary_color.g;
-                pixel_data[i * 4 + 2] = self.primary_color.b;
-                pixel_data[i * 4 + 3] = 255;
-            } else {
-                pixel_data[i * 4] = self.secondary_color.r;
-                pixel_data[i * 4 + 1] = self.secondary_color.g;
-                pixel_data[i * 4 + 2] = self.secondary_color.b;
-                pixel_data[i * 4 + 3] = 255;
-            }
+            let color = palette[(*pixel & 0b11) as usize];
+
+            pixel_data[i * 4] = color.r;
+            pixel_data[i * 4 + 1] = color.g;
+            pixel_data[i * 4 + 2] = color.b;
+            pixel_data[i * 4 + 3] = 255;
         }
 
         if let Some(texture) = &mut self.texture {
-            texture.update_texture(&pixel_data);
+            texture
+                .update_texture(&pixel_data)
+                .expect("Could not update texture");
         } else {
             self.texture = Some(
                 rl_handle
@@ -134,6 +154,25 @@ impl MonoChromeDisplay {
         }
     }
 
+    /// Changes the resolution of the display, keeping its size on screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new dimensions (width, height) of the display before scaling.
+    ///
+    /// The texture is dropped and recreated on the next update if the dimensions changed.
+    fn resize(&mut self, (width, height): (usize, usize)) {
+        let dimensions = Vector2::new(width as f32, height as f32);
+        if dimensions == self.dimensions {
+            return;
+        }
+
+        self.scale *= self.dimensions.x / dimensions.x;
+        self.dimensions = dimensions;
+        self.scaled_dimensions = dimensions * self.scale;
+        self.texture = None;
+    }
+
     /// Centers the display within the current screen dimensions.
     ///
     /// # Arguments
@@ -147,4 +186,80 @@ impl MonoChromeDisplay {
             (handle.get_screen_height() as f32 - self.scaled_dimensions.y) / 2.0,
         );
     }
+
+    /// Moves the display against the left edge of the screen, vertically centered.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - A reference to the Raylib handle.
+    ///
+    /// This leaves room on the right of the display, e.g. for the debugger panel.
+    pub fn align_left(&mut self, handle: &RaylibHandle) {
+        self.position = Vector2::new(
+            0.0,
+            (handle.get_screen_height() as f32 - self.scaled_dimensions.y) / 2.0,
+        );
+    }
+
+    /// Returns the position of the right edge of the display on the screen.
+    pub fn get_right_edge(&self) -> f32 {
+        self.position.x + self.scaled_dimensions.x
+    }
+}
+
+/// Draws the crash screen on top of the current frame.
+///
+/// # Arguments
+///
+/// * `handle` - A mutable reference to `RaylibDrawHandle` for rendering.
+/// * `message` - The error message to display.
+///
+/// The frame is darkened and the message is centered on the screen.
+pub fn draw_crash_screen(handle: &mut RaylibDrawHandle<'_>, message: &str) {
+    const TITLE: &str = "The emulator crashed";
+    const TITLE_SIZE: i32 = 80;
+    const MESSAGE_SIZE: i32 = 40;
+
+    let (width, height) = (handle.get_screen_width(), handle.get_screen_height());
+    handle.draw_rectangle(0, 0, width, height, Color::new(0, 0, 0, 200));
+
+    let title_width = handle.measure_text(TITLE, TITLE_SIZE);
+    handle.draw_text(
+        TITLE,
+        (width - title_width) / 2,
+        height / 2 - TITLE_SIZE,
+        TITLE_SIZE,
+        Color::RED,
+    );
+
+    let message_width = handle.measure_text(message, MESSAGE_SIZE);
+    handle.draw_text(
+        message,
+        (width - message_width) / 2,
+        height / 2 + MESSAGE_SIZE,
+        MESSAGE_SIZE,
+        Color::WHITE,
+    );
+}
+
+/// Draws a short status message (e.g. "Saved slot 1") in the bottom-left corner of the screen.
+///
+/// # Arguments
+///
+/// * `handle` - A mutable reference to `RaylibDrawHandle` for rendering.
+/// * `message` - The message to display.
+pub fn draw_status(handle: &mut RaylibDrawHandle<'_>, message: &str) {
+    const SIZE: i32 = 40;
+    const MARGIN: i32 = 20;
+
+    let height = handle.get_screen_height();
+    let width = handle.measure_text(message, SIZE);
+    handle.draw_rectangle(
+        0,
+        height - SIZE - 2 * MARGIN,
+        width + 2 * MARGIN,
+        SIZE + 2 * MARGIN,
+        Color::new(0, 0, 0, 200),
+    );
+    handle.draw_text(message, MARGIN, height - SIZE - MARGIN, SIZE, Color::WHITE);
 }
@@ -0,0 +1,192 @@
+//! Gamepad mapping for the CHIP-8 keypad.
+//!
+//! This module contains the `GamepadMap` struct, which binds gamepad buttons and stick directions
+//! to CHIP-8 keys. Games use different keys for the same actions (e.g. Pong moves with 1 and 4,
+//! Tetris with 4, 5 and 6), so a mapping is usually given per ROM in the configuration file.
+//!
+//! The buttons are named after the Xbox layout: `A` is the bottom face button, `Y` the top one.
+
+use raylib::prelude::*;
+use raylib::prelude::{GamepadAxis as Axis, GamepadButton as Button};
+
+/// The number of gamepads supported by raylib.
+const MAX_GAMEPADS: i32 = 4;
+
+/// How far a stick must be pushed, from 0.0 to 1.0, for its direction to count as pressed.
+const STICK_THRESHOLD: f32 = 0.5;
+
+/// An input of a gamepad that can be bound to a CHIP-8 key.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GamepadInput {
+    /// A button, including the D-pad directions.
+    Button(GamepadButton),
+
+    /// A stick pushed along an axis.
+    Stick {
+        /// The axis of the stick.
+        axis: GamepadAxis,
+
+        /// Whether the stick is pushed towards the positive end of the axis (right or down).
+        positive: bool,
+    },
+}
+
+impl GamepadInput {
+    /// Returns `true` if the input is held on the gamepad `gamepad`.
+    fn is_down(self, handle: &RaylibHandle, gamepad: i32) -> bool {
+        match self {
+            Self::Button(button) => handle.is_gamepad_button_down(gamepad, button),
+            Self::Stick { axis, positive } => {
+                let movement = handle.get_gamepad_axis_movement(gamepad, axis);
+                if positive {
+                    movement > STICK_THRESHOLD
+                } else {
+                    movement < -STICK_THRESHOLD
+                }
+            }
+        }
+    }
+}
+
+/// The names of the inputs accepted in a gamepad mapping.
+const INPUT_NAMES: [(&str, GamepadInput); 24] = [
+    ("UP", button(Button::GAMEPAD_BUTTON_LEFT_FACE_UP)),
+    ("DOWN", button(Button::GAMEPAD_BUTTON_LEFT_FACE_DOWN)),
+    ("LEFT", button(Button::GAMEPAD_BUTTON_LEFT_FACE_LEFT)),
+    ("RIGHT", button(Button::GAMEPAD_BUTTON_LEFT_FACE_RIGHT)),
+    ("A", button(Button::GAMEPAD_BUTTON_RIGHT_FACE_DOWN)),
+    ("B", button(Button::GAMEPAD_BUTTON_RIGHT_FACE_RIGHT)),
+    ("X", button(Button::GAMEPAD_BUTTON_RIGHT_FACE_LEFT)),
+    ("Y", button(Button::GAMEPAD_BUTTON_RIGHT_FACE_UP)),
+    ("LB", button(Button::GAMEPAD_BUTTON_LEFT_TRIGGER_1)),
+    ("RB", button(Button::GAMEPAD_BUTTON_RIGHT_TRIGGER_1)),
+    ("LT", button(Button::GAMEPAD_BUTTON_LEFT_TRIGGER_2)),
+    ("RT", button(Button::GAMEPAD_BUTTON_RIGHT_TRIGGER_2)),
+    ("SELECT", button(Button::GAMEPAD_BUTTON_MIDDLE_LEFT)),
+    ("START", button(Button::GAMEPAD_BUTTON_MIDDLE_RIGHT)),
+    ("L3", button(Button::GAMEPAD_BUTTON_LEFT_THUMB)),
+    ("R3", button(Button::GAMEPAD_BUTTON_RIGHT_THUMB)),
+    ("LSTICK_UP", stick(Axis::GAMEPAD_AXIS_LEFT_Y, false)),
+    ("LSTICK_DOWN", stick(Axis::GAMEPAD_AXIS_LEFT_Y, true)),
+    ("LSTICK_LEFT", stick(Axis::GAMEPAD_AXIS_LEFT_X, false)),
+    ("LSTICK_RIGHT", stick(Axis::GAMEPAD_AXIS_LEFT_X, true)),
+    ("RSTICK_UP", stick(Axis::GAMEPAD_AXIS_RIGHT_Y, false)),
+    ("RSTICK_DOWN", stick(Axis::GAMEPAD_AXIS_RIGHT_Y, true)),
+    ("RSTICK_LEFT", stick(Axis::GAMEPAD_AXIS_RIGHT_X, false)),
+    ("RSTICK_RIGHT", stick(Axis::GAMEPAD_AXIS_RIGHT_X, true)),
+];
+
+/// Shorthand for a button input in `INPUT_NAMES`.
+const fn button(button: Button) -> GamepadInput {
+    GamepadInput::Button(button)
+}
+
+/// Shorthand for a stick direction in `INPUT_NAMES`.
+const fn stick(axis: Axis, positive: bool) -> GamepadInput {
+    GamepadInput::Stick { axis, positive }
+}
+
+/// Returns the name of a gamepad input in a mapping.
+fn input_name(input: GamepadInput) -> &'static str {
+    INPUT_NAMES
+        .iter()
+        .find(|(_, named)| *named == input)
+        .map(|(name, _)| *name)
+        .expect("every input has a name")
+}
+
+/// Returns the gamepad input called `name` (case-insensitive), `None` if there is no such input.
+fn input_from_name(name: &str) -> Option<GamepadInput> {
+    INPUT_NAMES
+        .iter()
+        .find(|(named, _)| named.eq_ignore_ascii_case(name))
+        .map(|&(_, input)| input)
+}
+
+/// Returns the first connected gamepad, `None` if there is none.
+pub fn find_gamepad(handle: &RaylibHandle) -> Option<i32> {
+    (0..MAX_GAMEPADS).find(|&gamepad| handle.is_gamepad_available(gamepad))
+}
+
+/// A binding of gamepad inputs to the CHIP-8 hexadecimal keypad.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadMap {
+    /// The inputs with the CHIP-8 key (0x0 to 0xF) each one presses.
+    bindings: Vec<(GamepadInput, u8)>,
+}
+
+impl GamepadMap {
+    /// The mapping used when none is configured: the D-pad and the left stick press the keys
+    /// `5`, `7`, `8` and `9` (`WASD` on a QWERTY keyboard, as in most Octo games), `A` presses `6`
+    /// (`E`) and `B` presses `4` (`Q`).
+    pub const DEFAULT: &'static str = "UP=5 LEFT=7 DOWN=8 RIGHT=9 \
+        LSTICK_UP=5 LSTICK_LEFT=7 LSTICK_DOWN=8 LSTICK_RIGHT=9 A=6 B=4";
+
+    /// Returns the state of the CHIP-8 keypad pressed by the gamepad `gamepad`.
+    pub fn get_keypad(&self, handle: &RaylibHandle, gamepad: i32) -> [bool; 16] {
+        let mut keypad = [false; 16];
+        for &(input, key) in &self.bindings {
+            keypad[key as usize] |= input.is_down(handle, gamepad);
+        }
+
+        keypad
+    }
+}
+
+impl Default for GamepadMap {
+    fn default() -> Self {
+        Self::DEFAULT.parse().expect("the default mapping is valid")
+    }
+}
+
+impl std::fmt::Display for GamepadMap {
+    /// Writes the mapping in the format read by `from_str`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bindings: Vec<String> = self
+            .bindings
+            .iter()
+            .map(|&(input, key)| format!("{}={key:X}", input_name(input)))
+            .collect();
+
+        write!(f, "{}", bindings.join(" "))
+    }
+}
+
+impl std::str::FromStr for GamepadMap {
+    type Err = String;
+
+    /// Parses a mapping from bindings separated by spaces, each one written `INPUT=KEY` where
+    /// `KEY` is a CHIP-8 key from 0 to F and `INPUT` is one of (case-insensitive):
+    /// - the D-pad: `UP`, `DOWN`, `LEFT` and `RIGHT`;
+    /// - the buttons: `A`, `B`, `X`, `Y`, `LB`, `RB`, `LT`, `RT`, `SELECT`, `START`, `L3` and `R3`;
+    /// - the sticks: `LSTICK_UP`, `LSTICK_DOWN`, `LSTICK_LEFT`, `LSTICK_RIGHT` and the same
+    ///   with `RSTICK`.
+    ///
+    /// An input can press several keys, e.g. `UP=2 UP=5`. An empty mapping disables the gamepad.
+    fn from_str(mapping: &str) -> Result<Self, Self::Err> {
+        let mut bindings = Vec::new();
+        for binding in mapping.split_whitespace() {
+            let (name, key) = binding.split_once('=').ok_or_else(|| {
+                format!("gamepad binding `{binding}` must be written INPUT=KEY (e.g. UP=5)")
+            })?;
+
+            let input = input_from_name(name).ok_or_else(|| {
+                format!(
+                    "unknown gamepad input `{name}`, expected one of: {}",
+                    INPUT_NAMES.map(|(name, _)| name).join(", ")
+                )
+            })?;
+            let digit = match key.chars().collect::<Vec<_>>()[..] {
+                [c] => c.to_digit(16),
+                _ => None,
+            };
+            let digit = digit.ok_or_else(|| {
+                format!("gamepad binding `{binding}` has an invalid CHIP-8 key `{key}`, expected 0 to F")
+            })?;
+
+            bindings.push((input, digit as u8));
+        }
+
+        Ok(Self { bindings })
+    }
+}
@@ -0,0 +1,390 @@
+//! Keyboard mapping for the CHIP-8 keypad.
+//!
+//! This module contains the `KeyMap` struct, which binds a keyboard key to each of the 16 CHIP-8 keys,
+//! its layout presets, and `Rebinding`, the state of the in-app rebinding screen.
+//!
+//! Depending on the platform, raylib identifies the letter keys by their label or by their position
+//! on a US keyboard: if the `qwerty` preset does not match the keys of a non-QWERTY keyboard, use
+//! the preset of its layout.
+
+use raylib::prelude::*;
+
+/// The names of the keys accepted in a keymap, in addition to the letters and digits.
+const KEY_NAMES: [(&str, KeyboardKey); 28] = [
+    ("'", KeyboardKey::KEY_APOSTROPHE),
+    (",", KeyboardKey::KEY_COMMA),
+    ("-", KeyboardKey::KEY_MINUS),
+    (".", KeyboardKey::KEY_PERIOD),
+    ("/", KeyboardKey::KEY_SLASH),
+    (";", KeyboardKey::KEY_SEMICOLON),
+    ("=", KeyboardKey::KEY_EQUAL),
+    ("SPACE", KeyboardKey::KEY_SPACE),
+    ("UP", KeyboardKey::KEY_UP),
+    ("DOWN", KeyboardKey::KEY_DOWN),
+    ("LEFT", KeyboardKey::KEY_LEFT),
+    ("RIGHT", KeyboardKey::KEY_RIGHT),
+    ("KP_0", KeyboardKey::KEY_KP_0),
+    ("KP_1", KeyboardKey::KEY_KP_1),
+    ("KP_2", KeyboardKey::KEY_KP_2),
+    ("KP_3", KeyboardKey::KEY_KP_3),
+    ("KP_4", KeyboardKey::KEY_KP_4),
+    ("KP_5", KeyboardKey::KEY_KP_5),
+    ("KP_6", KeyboardKey::KEY_KP_6),
+    ("KP_7", KeyboardKey::KEY_KP_7),
+    ("KP_8", KeyboardKey::KEY_KP_8),
+    ("KP_9", KeyboardKey::KEY_KP_9),
+    ("KP_DECIMAL", KeyboardKey::KEY_KP_DECIMAL),
+    ("KP_ENTER", KeyboardKey::KEY_KP_ENTER),
+    ("KP_DIVIDE", KeyboardKey::KEY_KP_DIVIDE),
+    ("KP_MULTIPLY", KeyboardKey::KEY_KP_MULTIPLY),
+    ("KP_SUBTRACT", KeyboardKey::KEY_KP_SUBTRACT),
+    ("KP_ADD", KeyboardKey::KEY_KP_ADD),
+];
+
+/// The keys used by the frontend itself, which cannot be bound to the keypad.
+const RESERVED_KEYS: [KeyboardKey; 17] = [
+    KeyboardKey::KEY_F1,
+    KeyboardKey::KEY_F2,
+    KeyboardKey::KEY_F3,
+    KeyboardKey::KEY_F4,
+    KeyboardKey::KEY_F5,
+    KeyboardKey::KEY_F6,
+    KeyboardKey::KEY_F7,
+    KeyboardKey::KEY_F8,
+    KeyboardKey::KEY_F9,
+    KeyboardKey::KEY_F10,
+    KeyboardKey::KEY_F11,
+    KeyboardKey::KEY_F12,
+    KeyboardKey::KEY_TAB,
+    KeyboardKey::KEY_BACKSPACE,
+    KeyboardKey::KEY_ESCAPE,
+    KeyboardKey::KEY_LEFT_SHIFT,
+    KeyboardKey::KEY_RIGHT_SHIFT,
+];
+
+/// Returns the name of a keyboard key in a keymap, `None` if it cannot be part of a keymap.
+fn key_name(key: KeyboardKey) -> Option<String> {
+    let code = key as i32;
+    if (b'0' as i32..=b'9' as i32).contains(&code) || (b'A' as i32..=b'Z' as i32).contains(&code) {
+        return Some((code as u8 as char).to_string());
+    }
+
+    KEY_NAMES
+        .iter()
+        .find(|(_, named)| *named == key)
+        .map(|(name, _)| name.to_string())
+}
+
+/// Returns the keyboard key called `name` (case-insensitive), `None` if there is no such key.
+fn key_from_name(name: &str) -> Option<KeyboardKey> {
+    let name = name.to_ascii_uppercase();
+    if let [c] = name.as_bytes() {
+        if c.is_ascii_alphanumeric() {
+            return key_from_i32(*c as i32);
+        }
+    }
+
+    KEY_NAMES
+        .iter()
+        .find(|(named, _)| *named == name)
+        .map(|&(_, key)| key)
+}
+
+/// A binding of keyboard keys to the CHIP-8 hexadecimal keypad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyMap {
+    /// The keyboard key bound to each CHIP-8 key (index 0x0 to 0xF).
+    keys: [KeyboardKey; 16],
+}
+
+impl KeyMap {
+    /// The classic QWERTY layout, mapping `1234/QWER/ASDF/ZXCV` to the keypad.
+    ///
+    /// ```text
+    /// 1 2 3 C      1 2 3 4
+    /// 4 5 6 D  ->  Q W E R
+    /// 7 8 9 E      A S D F
+    /// A 0 B F      Z X C V
+    /// ```
+    pub const QWERTY: Self = Self {
+        keys: [
+            KeyboardKey::KEY_X,
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_Q,
+            KeyboardKey::KEY_W,
+            KeyboardKey::KEY_E,
+            KeyboardKey::KEY_A,
+            KeyboardKey::KEY_S,
+            KeyboardKey::KEY_D,
+            KeyboardKey::KEY_Z,
+            KeyboardKey::KEY_C,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_R,
+            KeyboardKey::KEY_F,
+            KeyboardKey::KEY_V,
+        ],
+    };
+
+    /// The same keys as [`KeyMap::QWERTY`] on an AZERTY keyboard, `1234/AZER/QSDF/WXCV`.
+    pub const AZERTY: Self = Self {
+        keys: [
+            KeyboardKey::KEY_X,
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_A,
+            KeyboardKey::KEY_Z,
+            KeyboardKey::KEY_E,
+            KeyboardKey::KEY_Q,
+            KeyboardKey::KEY_S,
+            KeyboardKey::KEY_D,
+            KeyboardKey::KEY_W,
+            KeyboardKey::KEY_C,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_R,
+            KeyboardKey::KEY_F,
+            KeyboardKey::KEY_V,
+        ],
+    };
+
+    /// The same keys as [`KeyMap::QWERTY`] on a Dvorak keyboard, `1234/',.P/AOEU/;QJK`.
+    pub const DVORAK: Self = Self {
+        keys: [
+            KeyboardKey::KEY_Q,
+            KeyboardKey::KEY_ONE,
+            KeyboardKey::KEY_TWO,
+            KeyboardKey::KEY_THREE,
+            KeyboardKey::KEY_APOSTROPHE,
+            KeyboardKey::KEY_COMMA,
+            KeyboardKey::KEY_PERIOD,
+            KeyboardKey::KEY_A,
+            KeyboardKey::KEY_O,
+            KeyboardKey::KEY_E,
+            KeyboardKey::KEY_SEMICOLON,
+            KeyboardKey::KEY_J,
+            KeyboardKey::KEY_FOUR,
+            KeyboardKey::KEY_P,
+            KeyboardKey::KEY_U,
+            KeyboardKey::KEY_K,
+        ],
+    };
+
+    /// The numeric keypad: the digits are bound to the keys with the same label.
+    ///
+    /// ```text
+    /// A B C D E F  ->  . Enter / * - +
+    /// ```
+    pub const NUMPAD: Self = Self {
+        keys: [
+            KeyboardKey::KEY_KP_0,
+            KeyboardKey::KEY_KP_1,
+            KeyboardKey::KEY_KP_2,
+            KeyboardKey::KEY_KP_3,
+            KeyboardKey::KEY_KP_4,
+            KeyboardKey::KEY_KP_5,
+            KeyboardKey::KEY_KP_6,
+            KeyboardKey::KEY_KP_7,
+            KeyboardKey::KEY_KP_8,
+            KeyboardKey::KEY_KP_9,
+            KeyboardKey::KEY_KP_DECIMAL,
+            KeyboardKey::KEY_KP_ENTER,
+            KeyboardKey::KEY_KP_DIVIDE,
+            KeyboardKey::KEY_KP_MULTIPLY,
+            KeyboardKey::KEY_KP_SUBTRACT,
+            KeyboardKey::KEY_KP_ADD,
+        ],
+    };
+
+    /// The presets, with the names accepted by `from_str`.
+    pub const PRESETS: [(&'static str, Self); 4] = [
+        ("qwerty", Self::QWERTY),
+        ("azerty", Self::AZERTY),
+        ("dvorak", Self::DVORAK),
+        ("numpad", Self::NUMPAD),
+    ];
+
+    /// Returns the keyboard key bound to the CHIP-8 `key` (0x0 to 0xF).
+    pub fn get(&self, key: u8) -> KeyboardKey {
+        self.keys[key as usize]
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::QWERTY
+    }
+}
+
+impl std::fmt::Display for KeyMap {
+    /// Writes the keymap in the format read by `from_str`: the name of a preset, 16 characters
+    /// if every key is a single character, or else 16 key names separated by spaces.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some((name, _)) = Self::PRESETS.iter().find(|(_, preset)| preset == self) {
+            return write!(f, "{name}");
+        }
+
+        let names: Vec<String> = self
+            .keys
+            .iter()
+            .map(|&key| key_name(key).expect("a keymap only holds named keys"))
+            .collect();
+        if names.iter().all(|name| name.len() == 1) {
+            write!(f, "{}", names.concat())
+        } else {
+            write!(f, "{}", names.join(" "))
+        }
+    }
+}
+
+impl std::str::FromStr for KeyMap {
+    type Err = String;
+
+    /// Parses a keymap, bound to the CHIP-8 keys 0 to F in order, from one of:
+    /// - the name of a preset: `qwerty`, `azerty`, `dvorak` or `numpad`;
+    /// - 16 characters, letters, digits or `',-./;=` (e.g. `X123QWEASDZC4RFV` for QWERTY);
+    /// - 16 key names separated by spaces, the characters above or `SPACE`, `UP`, `DOWN`, `LEFT`,
+    ///   `RIGHT`, `KP_0` to `KP_9`, `KP_DECIMAL`, `KP_ENTER`, `KP_DIVIDE`, `KP_MULTIPLY`,
+    ///   `KP_SUBTRACT` and `KP_ADD` (e.g. `KP_0 KP_1 ... KP_ADD`).
+    fn from_str(keys: &str) -> Result<Self, Self::Err> {
+        if let Some((_, preset)) = Self::PRESETS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(keys.trim()))
+        {
+            return Ok(*preset);
+        }
+
+        let names: Vec<String> = if keys.trim().contains(char::is_whitespace) {
+            keys.split_whitespace().map(str::to_string).collect()
+        } else {
+            keys.chars().map(String::from).collect()
+        };
+        if names.len() != 16 {
+            return Err(format!(
+                "keymap `{keys}` must have 16 keys (one per CHIP-8 key, from 0 to F) or be one of: {}",
+                Self::PRESETS.map(|(name, _)| name).join(", ")
+            ));
+        }
+
+        let mut keymap = Self::QWERTY;
+        for (binding, name) in keymap.keys.iter_mut().zip(&names) {
+            *binding = key_from_name(name)
+                .ok_or_else(|| format!("keymap `{keys}` contains an invalid key `{name}`"))?;
+        }
+
+        Ok(keymap)
+    }
+}
+
+/// The order in which the rebinding screen asks for the CHIP-8 keys, row by row on the keypad.
+const REBINDING_ORDER: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+/// The state of the rebinding screen, capturing the next pressed key for each CHIP-8 key in turn.
+#[derive(Debug, Clone)]
+pub struct Rebinding {
+    /// The keymap being built, starting from the current one.
+    keymap: KeyMap,
+
+    /// The number of CHIP-8 keys already bound.
+    bound: usize,
+}
+
+impl Rebinding {
+    /// Starts rebinding the keys of `keymap`.
+    pub fn new(keymap: KeyMap) -> Self {
+        Self { keymap, bound: 0 }
+    }
+
+    /// Returns the CHIP-8 key waiting for a keyboard key.
+    pub fn get_current_key(&self) -> u8 {
+        REBINDING_ORDER[self.bound]
+    }
+
+    /// Binds the keyboard key `key` to the current CHIP-8 key.
+    ///
+    /// The keys used by the frontend, the keys already bound during this rebinding and the keys
+    /// that cannot be written in a keymap are ignored.
+    ///
+    /// # Returns
+    ///
+    /// The new keymap once all the CHIP-8 keys are bound, else `None`.
+    pub fn capture(&mut self, key: KeyboardKey) -> Option<KeyMap> {
+        let already_bound = REBINDING_ORDER[..self.bound]
+            .iter()
+            .any(|&chip8_key| self.keymap.get(chip8_key) == key);
+        if RESERVED_KEYS.contains(&key) || already_bound || key_name(key).is_none() {
+            return None;
+        }
+
+        self.keymap.keys[self.get_current_key() as usize] = key;
+        self.bound += 1;
+
+        (self.bound == REBINDING_ORDER.len()).then_some(self.keymap)
+    }
+
+    /// Draws the rebinding screen on top of the current frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - A mutable reference to `RaylibDrawHandle` for rendering.
+    pub fn draw(&self, handle: &mut RaylibDrawHandle<'_>) {
+        const TITLE_SIZE: i32 = 60;
+        const SIZE: i32 = 40;
+        const CELL: i32 = 120;
+
+        let (width, height) = (handle.get_screen_width(), handle.get_screen_height());
+        handle.draw_rectangle(0, 0, width, height, Color::new(0, 0, 0, 220));
+
+        let title = format!(
+            "Press the key for {:X} ({}/16)",
+            self.get_current_key(),
+            self.bound + 1
+        );
+        let title_width = handle.measure_text(&title, TITLE_SIZE);
+        let top = (height - 4 * CELL) / 2;
+        handle.draw_text(
+            &title,
+            (width - title_width) / 2,
+            top - 2 * TITLE_SIZE,
+            TITLE_SIZE,
+            Color::WHITE,
+        );
+
+        // The keypad, with the keys bound so far
+        let left = (width - 4 * CELL) / 2;
+        for (i, &chip8_key) in REBINDING_ORDER.iter().enumerate() {
+            let (x, y) = (left + (i as i32 % 4) * CELL, top + (i as i32 / 4) * CELL);
+            let (label, color) = match i.cmp(&self.bound) {
+                std::cmp::Ordering::Less => (
+                    key_name(self.keymap.get(chip8_key)).unwrap_or_default(),
+                    Color::WHITE,
+                ),
+                std::cmp::Ordering::Equal => ("?".to_string(), Color::ORANGE),
+                std::cmp::Ordering::Greater => (format!("{chip8_key:X}"), Color::GRAY),
+            };
+
+            handle.draw_rectangle_lines(x + 4, y + 4, CELL - 8, CELL - 8, color);
+            let label_width = handle.measure_text(&label, SIZE);
+            handle.draw_text(
+                &label,
+                x + (CELL - label_width) / 2,
+                y + (CELL - SIZE) / 2,
+                SIZE,
+                color,
+            );
+        }
+
+        let hint = "Tab: cancel";
+        let hint_width = handle.measure_text(hint, SIZE);
+        handle.draw_text(
+            hint,
+            (width - hint_width) / 2,
+            top + 4 * CELL + SIZE,
+            SIZE,
+            Color::GRAY,
+        );
+    }
+}
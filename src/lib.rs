@@ -0,0 +1,54 @@
+#![warn(missing_debug_implementations, rust_2018_idioms, missing_docs)]
+
+//! # CHIP-8 Emulator Library
+//!
+//! This crate contains a headless CHIP-8 core that can be embedded in other tools.
+//! The core performs no I/O on its own: rendering, audio and input are left to the frontend,
+//! which reads the machine state through getters and feeds the keypad through [`Chip8::update_key`].
+//!
+//! ## Features
+//! - `frontend` *(default)*: builds the raylib frontend binary.
+//! - `audio` *(enabled by `frontend`)*: `rodio` based audio playback in the [`audio`] module.
+//! - `config` *(enabled by `frontend`)*: TOML configuration file with per-ROM settings in the [`config`] module.
+//!
+//! ## Usage
+//! ```no_run
+//! use rust_chip8::Chip8;
+//!
+//! let mut chip8 = Chip8::new();
+//! chip8.load_fontset();
+//! chip8.load_rom("games/PONG.ch8").unwrap();
+//!
+//! for _ in 0..1000 {
+//!     chip8.step().unwrap();
+//! }
+//!
+//! let lit = chip8.get_display().iter().filter(|&&pixel| pixel != 0).count();
+//! println!("{lit} pixels on");
+//! ```
+
+/// Octo assembler module.
+pub mod assembler;
+/// CHIP-8 emulator core module.
+pub mod chip8;
+/// Constants module for the emulator.
+pub mod constants;
+/// Input recording and playback module.
+pub mod movie;
+/// Rewind history module.
+pub mod rewind;
+/// Display conversion to image formats module.
+pub mod screenshot;
+
+/// Audio playback module.
+#[cfg(feature = "audio")]
+pub mod audio;
+
+/// Configuration file module.
+#[cfg(feature = "config")]
+pub mod config;
+
+pub use chip8::{
+    instructions, BreakReason, Chip8, Chip8Builder, Chip8Error, Instruction, KeyWait, Platform,
+    Quirks, StateError, StepOutcome,
+};
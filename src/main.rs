@@ -4,45 +4,225 @@
 //!
 //! This is a CHIP-8 emulator written in Rust using the `raylib` graphics library for rendering.
 //! It loads a CHIP-8 ROM file, emulates the system, and provides a graphical output.
+//! The emulation itself lives in the `rust_chip8` library, this binary is only the frontend.
 //!
 //! ## Features
 //! - CHIP-8 CPU emulation
 //!
 //! ## Feats
-//! - All tested ROMs from the `test` folder are working
+//! - Conformance tests against the Timendus test suite ROMs placed in `tests/roms`
+//!   (see `tests/conformance.rs`)
 //! - Sound support
 //!
 //! ## Usage
 //! ```sh
-//! cargo run <ROM file>
+//! cargo run -- [OPTIONS] <ROM file>
 //! ```
 //! Example:
 //! ```sh
-//! cargo run games/PONG.ch8
+//! cargo run -- --platform schip --ips 1200 --fg FFFFFF --bg 000000 games/PONG.ch8
 //! ```
+//! Run with `--help` for the list of options.
+//! A ROM file with the `.8o` extension is an Octo source, assembled before running
+//! (see the `rust_chip8::assembler` module).
+//!
+//! The default settings and per-ROM settings are read from a TOML configuration file
+//! (see the `rust_chip8::config` module), `--remember` saves the options given on the
+//! command line for the current ROM.
+//!
+//! ## Keyboard
+//! The keys bound to the CHIP-8 keypad are set with `--keymap` or in the configuration file, as
+//! a layout preset (`qwerty`, `azerty`, `dvorak` or `numpad`) or as a list of keys.
+//! `Tab` opens the rebinding screen, which asks for a key for each CHIP-8 key in turn and saves
+//! the new bindings as the default keymap of the configuration file; `Tab` again cancels it.
+//!
+//! ## Gamepad
+//! The first connected gamepad presses the CHIP-8 keys bound to its buttons, D-pad and sticks,
+//! along with the keyboard. The bindings are set with `--gamepad` or in the configuration file,
+//! usually per ROM since games use different keys, e.g. `--gamepad "UP=1 DOWN=4" --remember`
+//! for Pong (see the `gamepad` module).
+//!
+//! ## Save states
+//! `F1`-`F8` load a save slot, `Shift` + `F1`-`F8` save to it. The slots are stored in the
+//! `states` directory next to the configuration file.
+//!
+//! ## Rewind
+//! Hold `Backspace` to step backwards through the last seconds of emulation (see `--rewind`).
+//!
+//! ## Movies
+//! `--record <FILE>` records the keypad of every frame, along with the ROM hash, the seed, the quirks
+//! and the speed, to a movie file written when the window is closed. `--play <FILE>` plays it back,
+//! reproducing the run exactly; the keyboard takes over at the end of the movie. Loading a save
+//! state and rewinding are disabled during a recording or a playback
+//! (see the `rust_chip8::movie` module).
+//!
+//! ## Debugger
+//! `F9` shows the debugger panel, `F12` pauses or continues, `F11` steps a single instruction
+//! and `F10` steps over subroutine calls. The keypad is still read while paused, so that an `Fx0A`
+//! waiting for a key can be stepped through.
+//! When running an Octo source, the debugger shows the labels and pauses on its `:breakpoint`s.
+//! The emulator also pauses on the breakpoints and watchpoints given with `--break`, `--break-if`,
+//! `--watch` and `--watch-reg`, the reason being shown at the bottom of the screen.
 //! If no ROM file is provided, the program will exit with an error code.
 
 use raylib::prelude::*;
-/// CHIP-8 emulator core module.
-pub mod chip8;
-/// Constants module for the emulator.
-pub mod constants;
+use rust_chip8::assembler::assemble;
+use rust_chip8::config::{hash_to_hex, Config};
+use rust_chip8::movie::Movie;
+use rust_chip8::rewind::Rewind;
+use rust_chip8::{audio, constants, Chip8, Chip8Error, StepOutcome};
+
+/// Command-line interface module.
+pub mod cli;
+/// Debugger panel module.
+pub mod debugger;
 /// Rendering and display module.
 pub mod draw;
+/// Gamepad mapping module.
+pub mod gamepad;
+/// Keyboard mapping module.
+pub mod keymap;
+/// Save state slots module.
+pub mod slots;
+
+/// Sets the CHIP-8 keypad from the keyboard and the gamepad in use, if any.
+fn poll_keypad(
+    rl_handle: &RaylibHandle,
+    chip8: &mut Chip8,
+    keymap: &keymap::KeyMap,
+    gamepad_map: &gamepad::GamepadMap,
+    gamepad: Option<i32>,
+) {
+    let pad = gamepad.map_or([false; 16], |id| gamepad_map.get_keypad(rl_handle, id));
+    for key in 0..16 {
+        let pressed = rl_handle.is_key_down(keymap.get(key)) || pad[key as usize];
+        chip8.update_key(key, pressed);
+    }
+}
 
 #[doc(hidden)]
-fn main() -> Result<(), std::io::Error> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        eprintln!("[Usage] <ROM file>");
+fn main() -> Result<(), Chip8Error> {
+    let args = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Run(args)) => args,
+        Ok(cli::Command::Help) => {
+            println!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Err(err) => {
+            eprintln!("[Usage] {err}\n\n{}", cli::USAGE);
+            std::process::exit(1);
+        }
+    };
+
+    // Configuration (a missing default file is not an error)
+    let config_path = args.config.clone().or_else(Config::default_path);
+    let mut config = match &config_path {
+        Some(path) if args.config.is_some() || path.exists() => {
+            Config::load(path).unwrap_or_else(|err| {
+                eprintln!("[Config] {}: {err}", path.display());
+                std::process::exit(1);
+            })
+        }
+        _ => Config::default(),
+    };
+
+    // Emulator
+    let mut chip8 = Chip8::new();
+    let program = if args.rom.ends_with(".8o") {
+        let source = std::fs::read_to_string(&args.rom)?;
+        let program = assemble(&source).unwrap_or_else(|err| {
+            eprintln!("[Assembler] {}: {err}", args.rom);
+            std::process::exit(1);
+        });
+        chip8.load_rom_bytes(&program.rom)?;
+        Some(program)
+    } else {
+        chip8.load_rom(&args.rom)?;
+        None
+    };
+    chip8.load_fontset();
+
+    if args.remember {
+        let rom_settings = config.rom_settings_mut(chip8.get_rom_hash());
+        *rom_settings = std::mem::take(rom_settings).merge(args.settings.clone());
+
+        match &config_path {
+            Some(path) => match config.save(path) {
+                Ok(()) => println!(
+                    "[Config] Saved the settings of ROM {} to {}",
+                    hash_to_hex(chip8.get_rom_hash()),
+                    path.display()
+                ),
+                Err(err) => eprintln!("[Config] {}: {err}", path.display()),
+            },
+            None => eprintln!("[Config] No configuration file location, use --config"),
+        }
+    }
+
+    let settings = config
+        .settings_for(chip8.get_rom_hash())
+        .merge(args.settings.clone());
+    let options = cli::Options::resolve(&settings).unwrap_or_else(|err| {
+        eprintln!("[Config] {err}");
         std::process::exit(1);
+    });
+
+    chip8.set_quirks(options.quirks);
+    if let Some(seed) = args.seed {
+        chip8.seed_rng(seed);
+    }
+
+    // Movie playback, with the quirks, seed and speed of the recording
+    let playback = args.play.as_ref().map(|path| {
+        Movie::load(path)
+            .and_then(|movie| movie.start(&mut chip8).map(|()| movie))
+            .unwrap_or_else(|err| {
+                eprintln!("[Movie] {}: {err}", path.display());
+                std::process::exit(1);
+            })
+    });
+    let cycles_per_frame = playback
+        .as_ref()
+        .map_or(options.cycles_per_frame(), Movie::get_cycles_per_frame);
+
+    // Movie recording, the seed is needed to reproduce the run
+    let mut recording = args.record.as_ref().map(|_| {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        chip8.seed_rng(seed);
+        Movie::new(&chip8, seed, cycles_per_frame)
+    });
+    let mut movie_frame = 0;
+    *chip8.get_breakpoints_mut() = args.breakpoints.clone();
+    for &address in program
+        .iter()
+        .flat_map(|program| program.breakpoints.keys())
+    {
+        chip8.get_breakpoints_mut().add_breakpoint(address, None);
+    }
+
+    let state_dir = config_path
+        .as_deref()
+        .and_then(std::path::Path::parent)
+        .map_or_else(|| "states".into(), |dir| dir.join("states"));
+    let slots = slots::SaveSlots::new(state_dir, chip8.get_rom_hash());
+    let mut rewind = Rewind::new(options.rewind_frames());
+    let mut debugger = debugger::Debugger::default();
+    if let Some(program) = program {
+        debugger.set_program(program);
     }
 
     // Init
-    let (mut rl_handle, rl_thread) = raylib::init()
-        .size(640 * 6, 320 * 6)
-        .title("CHIP8 Emulator")
-        .build();
+    let mut builder = raylib::init();
+    builder
+        .size(
+            (constants::SCREEN_WIDTH as f32 * options.scale * 1.25) as i32,
+            (constants::SCREEN_HEIGHT as f32 * options.scale * 1.25) as i32,
+        )
+        .title("CHIP8 Emulator");
+    if args.fullscreen {
+        builder.fullscreen();
+    }
+    let (mut rl_handle, rl_thread) = builder.build();
     rl_handle.set_target_fps(constants::SCREEN_FPS);
 
     // UI elements
@@ -52,55 +232,254 @@ fn main() -> Result<(), std::io::Error> {
             constants::SCREEN_WIDTH as f32,
             constants::SCREEN_HEIGHT as f32,
         ),
-        Color::new(255, 200, 45, 255),
-        Color::new(45, 10, 45, 255),
-        48.0,
+        options.fg,
+        options.bg,
+        options.scale,
     );
     display.center(&rl_handle);
 
-    // Emulator
-    let mut chip8 = chip8::Chip8::new();
-    chip8.load_rom(&args[1])?;
-    chip8.load_fontset();
-    println!("{:?}", chip8);
+    // Audio (the emulator keeps running muted if no output device is available)
+    let beeper = if args.mute {
+        None
+    } else {
+        match audio::Beeper::new(&options.audio) {
+            Ok(beeper) => Some(beeper),
+            Err(err) => {
+                eprintln!("[Audio] {err}, sound disabled");
+                None
+            }
+        }
+    };
+
+    // Set once the emulated program crashes, the machine is then frozen
+    let mut crash: Option<Chip8Error> = None;
+
+    // Status message shown at the bottom of the screen, with its remaining display time
+    let mut status: Option<(String, f32)> = None;
+
+    // The keys bound to the keypad, and the rebinding screen when it is open
+    let mut keymap = options.keymap;
+    let mut rebinding: Option<keymap::Rebinding> = None;
+
+    // The gamepad bindings, and the gamepad in use if one is connected
+    let gamepad_map = options.gamepad;
+    let mut gamepad: Option<i32> = None;
+
+    // A breakpoint would pause in the middle of a frame, they are set aside during a movie
+    let mut movie_breakpoints = None;
+    if (recording.is_some() || playback.is_some()) && !chip8.get_breakpoints().is_empty() {
+        movie_breakpoints = Some(std::mem::take(chip8.get_breakpoints_mut()));
+        status = Some(("Breakpoints are disabled during a movie".to_string(), 2.0));
+    }
 
     let mut timer = 0.0;
     while !rl_handle.window_should_close() {
-        let frame_time = rl_handle.get_frame_time();
+        timer += rl_handle.get_frame_time();
+        let in_movie = recording.is_some()
+            || playback
+                .as_ref()
+                .is_some_and(|movie| movie_frame < movie.len());
 
-        if timer > 0.0 {
-            timer -= frame_time;
-        } else {
-            // The inside of this block will run at `constants::FPS` frames per second
-            timer += constants::FRAME_TIME - frame_time;
-
-            chip8.update_key(0x1, rl_handle.is_key_down(KeyboardKey::KEY_ONE));
-            chip8.update_key(0x2, rl_handle.is_key_down(KeyboardKey::KEY_TWO));
-            chip8.update_key(0x3, rl_handle.is_key_down(KeyboardKey::KEY_THREE));
-            chip8.update_key(0xC, rl_handle.is_key_down(KeyboardKey::KEY_FOUR));
-            chip8.update_key(0x4, rl_handle.is_key_down(KeyboardKey::KEY_Q));
-            chip8.update_key(0x5, rl_handle.is_key_down(KeyboardKey::KEY_W));
-            chip8.update_key(0x6, rl_handle.is_key_down(KeyboardKey::KEY_E));
-            chip8.update_key(0xD, rl_handle.is_key_down(KeyboardKey::KEY_R));
-            chip8.update_key(0x7, rl_handle.is_key_down(KeyboardKey::KEY_A));
-            chip8.update_key(0x8, rl_handle.is_key_down(KeyboardKey::KEY_S));
-            chip8.update_key(0x9, rl_handle.is_key_down(KeyboardKey::KEY_D));
-            chip8.update_key(0xE, rl_handle.is_key_down(KeyboardKey::KEY_F));
-            chip8.update_key(0xA, rl_handle.is_key_down(KeyboardKey::KEY_Z));
-            chip8.update_key(0x0, rl_handle.is_key_down(KeyboardKey::KEY_X));
-            chip8.update_key(0xB, rl_handle.is_key_down(KeyboardKey::KEY_C));
-            chip8.update_key(0xF, rl_handle.is_key_down(KeyboardKey::KEY_V));
-
-            chip8.step();
+        // Key rebinding, the emulation is paused while the screen is open
+        if rl_handle.is_key_pressed(KeyboardKey::KEY_TAB) {
+            rebinding = match rebinding {
+                Some(_) => {
+                    status = Some(("Rebinding cancelled".to_string(), 2.0));
+                    None
+                }
+                None => Some(keymap::Rebinding::new(keymap)),
+            };
+        } else if let Some(screen) = &mut rebinding {
+            let mut rebound = None;
+            while let Some(key) = rl_handle.get_key_pressed() {
+                rebound = screen.capture(key);
+                if rebound.is_some() {
+                    break;
+                }
+            }
+
+            if let Some(new_keymap) = rebound {
+                keymap = new_keymap;
+                rebinding = None;
+
+                config.default.keymap = Some(keymap.to_string());
+                let message = match &config_path {
+                    Some(path) => match config.save(path) {
+                        Ok(()) => format!("Saved the key bindings to {}", path.display()),
+                        Err(err) => format!("Cannot save the key bindings: {err}"),
+                    },
+                    None => "Key bindings updated".to_string(),
+                };
+                status = Some((message, 2.0));
+            }
+        }
+
+        // Gamepad (dis)connection
+        let connected = gamepad::find_gamepad(&rl_handle);
+        if connected != gamepad {
+            let message = match connected {
+                Some(id) => format!(
+                    "Gamepad connected: {}",
+                    rl_handle.get_gamepad_name(id).unwrap_or_default()
+                ),
+                None => "Gamepad disconnected".to_string(),
+            };
+            status = Some((message, 2.0));
+            gamepad = connected;
+        }
+
+        // Save states
+        let shift = rl_handle.is_key_down(KeyboardKey::KEY_LEFT_SHIFT)
+            || rl_handle.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+        for (i, &key) in slots::SLOT_KEYS.iter().enumerate() {
+            if rebinding.is_some() || !rl_handle.is_key_pressed(key) {
+                continue;
+            }
+
+            let slot = i + 1;
+            let message = if shift {
+                slots
+                    .save(slot, &chip8)
+                    .map(|()| format!("Saved slot {slot}"))
+            } else if in_movie {
+                Err("Cannot load a slot during a movie".to_string())
+            } else {
+                slots.load(slot, &mut chip8).map(|()| {
+                    crash = None;
+                    format!("Loaded slot {slot}")
+                })
+            };
+            status = Some((message.unwrap_or_else(|err| err), 2.0));
+        }
+
+        // Debugger controls, the keypad is still read while paused so that a key wait can be stepped
+        let playing = playback
+            .as_ref()
+            .is_some_and(|movie| movie_frame < movie.len());
+        if debugger.is_paused() && rebinding.is_none() && !playing {
+            poll_keypad(&rl_handle, &mut chip8, &keymap, &gamepad_map, gamepad);
+        }
+        if in_movie && debugger::Debugger::is_step_requested(&rl_handle) {
+            status = Some(("Cannot step during a movie".to_string(), 2.0));
+        }
+        if crash.is_none() {
+            match debugger.update(&rl_handle, &mut chip8, cycles_per_frame, !in_movie) {
+                Ok(Some(reason)) => status = Some((format!("Break: {reason}"), 2.0)),
+                Ok(None) => {}
+                Err(err) => {
+                    eprintln!("[Crash] {err}");
+                    crash = Some(err);
+                }
+            }
+        }
+
+        // Run as many 60 Hz frames as the elapsed time requires
+        let rewinding = rl_handle.is_key_down(KeyboardKey::KEY_BACKSPACE);
+        while timer >= constants::FRAME_TIME {
+            timer -= constants::FRAME_TIME;
+
+            // Step one frame back in time instead of running, this also undoes a crash
+            if rewinding && in_movie {
+                status = Some(("Cannot rewind during a movie".to_string(), 0.5));
+            } else if rewinding {
+                match rewind.rewind(&mut chip8) {
+                    Ok(true) => {
+                        crash = None;
+                        status = Some(("Rewinding".to_string(), 0.1));
+                    }
+                    Ok(false) => status = Some(("No more history".to_string(), 0.5)),
+                    Err(err) => status = Some((format!("Cannot rewind: {err}"), 2.0)),
+                }
+            } else if crash.is_none() && !debugger.is_paused() && rebinding.is_none() {
+                let played = playback
+                    .as_ref()
+                    .is_some_and(|movie| movie.apply_frame(&mut chip8, movie_frame));
+                if !played {
+                    poll_keypad(&rl_handle, &mut chip8, &keymap, &gamepad_map, gamepad);
+                }
+                if let Some(movie) = &mut recording {
+                    movie.record_frame(chip8.get_keypad());
+                }
+                movie_frame += 1;
+                if playback
+                    .as_ref()
+                    .is_some_and(|movie| movie_frame == movie.len())
+                {
+                    status = Some(("Movie ended".to_string(), 2.0));
+                    if let Some(breakpoints) = movie_breakpoints.take() {
+                        *chip8.get_breakpoints_mut() = breakpoints;
+                    }
+                }
+
+                rewind.record(&chip8);
+                match chip8.run_frame(cycles_per_frame) {
+                    Ok(StepOutcome::BreakpointHit(reason)) => {
+                        debugger.pause();
+                        status = Some((format!("Break: {reason}"), 2.0));
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        eprintln!("[Crash] {err}");
+                        crash = Some(err);
+                    }
+                }
+                if let Some(beeper) = &beeper {
+                    beeper.update(&chip8);
+                }
+            }
+        }
+
+        // The sound timer is frozen when no frame runs, the tone would never stop
+        let running = crash.is_none() && !debugger.is_paused() && rebinding.is_none();
+        if let Some(beeper) = beeper.as_ref().filter(|_| !running || rewinding) {
+            beeper.silence();
         }
 
         // Non frame-rate dependent logic here
-        display.update(&mut rl_handle, &rl_thread, chip8.get_display());
+        if debugger.is_visible() {
+            display.align_left(&rl_handle);
+        } else {
+            display.center(&rl_handle);
+        }
+        if let Some((_, remaining)) = &mut status {
+            *remaining -= rl_handle.get_frame_time();
+            if *remaining <= 0.0 {
+                status = None;
+            }
+        }
+        display.update(
+            &mut rl_handle,
+            &rl_thread,
+            chip8.get_display(),
+            chip8.get_display_size(),
+        );
 
         // Drawing
         let mut d = rl_handle.begin_drawing(&rl_thread);
         d.clear_background(Color::new(50, 50, 50, 255));
         display.draw(&mut d);
+        let panel_x = (display.get_right_edge() as i32).min(d.get_screen_width() * 3 / 4);
+        debugger.draw(&mut d, &chip8, panel_x);
+        if let Some(err) = &crash {
+            draw::draw_crash_screen(&mut d, &err.to_string());
+        }
+        if let Some((message, _)) = &status {
+            draw::draw_status(&mut d, message);
+        }
+        if let Some(screen) = &rebinding {
+            screen.draw(&mut d);
+        }
+    }
+
+    if let (Some(movie), Some(path)) = (&recording, &args.record) {
+        match movie.save(path) {
+            Ok(()) => println!(
+                "[Movie] Recorded {} frames to {}",
+                movie.len(),
+                path.display()
+            ),
+            Err(err) => eprintln!("[Movie] {}: {err}", path.display()),
+        }
     }
 
     Ok(())
@@ -0,0 +1,348 @@
+//! # Movies
+//!
+//! A movie is a recording of the keypad state at every 60 Hz frame of a run, along with everything
+//! else the run depends on: the ROM hash, the seed of the random number generator, the quirks and
+//! the number of instructions per frame. Playing a movie back on a fresh machine reproduces the run
+//! exactly, e.g. to attach a reproduction to a bug report or to check that a new version of the
+//! emulator still behaves the same.
+//!
+//! All the values are little-endian. A movie file is laid out as follows:
+//!
+//! | Offset | Size       | Content                                                  |
+//! |--------|------------|----------------------------------------------------------|
+//! | 0      | 4          | Magic number `CH8M`                                      |
+//! | 4      | 2          | Format version ([`MOVIE_VERSION`])                       |
+//! | 6      | 20         | SHA-1 hash of the ROM                                    |
+//! | 26     | 8          | Seed of the random number generator                      |
+//! | 34     | 1          | Quirks, packed by [`Quirks::to_bits`]                    |
+//! | 35     | 4          | Instructions per frame                                   |
+//! | 39     | 4          | Number of frames `n`                                     |
+//! | 43     | 2 * `n`    | Keypad of each frame, bit `k` set if the key `k` is held |
+//!
+//! A run can only be reproduced if the machine is driven by frames alone: loading a save state,
+//! rewinding or stepping in the debugger during a recording or a playback breaks the reproduction.
+
+use std::path::Path;
+
+use crate::chip8::{Chip8, Chip8Error, Quirks, StepOutcome};
+
+/// The magic number at the start of every movie.
+const MOVIE_MAGIC: &[u8; 4] = b"CH8M";
+
+/// The version of the movie format written by [`Movie::to_bytes`].
+pub const MOVIE_VERSION: u16 = 1;
+
+/// The size of the movie header, before the frames.
+const HEADER_SIZE: usize = 43;
+
+/// An error raised while reading or starting a movie.
+#[derive(Debug)]
+pub enum MovieError {
+    /// The data does not start with the movie magic number.
+    BadMagic,
+
+    /// The movie was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+
+    /// The movie was recorded with another ROM than the loaded one.
+    RomMismatch,
+
+    /// The data ends before the last frame.
+    Truncated,
+
+    /// The program crashed during the playback.
+    Crash {
+        /// The frame being played.
+        frame: usize,
+
+        /// The error raised by the program.
+        error: Chip8Error,
+    },
+
+    /// The movie file could not be read or written.
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for MovieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a movie"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported movie version {version} (expected {MOVIE_VERSION})"
+            ),
+            Self::RomMismatch => write!(f, "movie was recorded with another ROM"),
+            Self::Truncated => write!(f, "movie is truncated"),
+            Self::Crash { frame, error } => write!(f, "crash at frame {frame}: {error}"),
+            Self::Io(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Crash { error, .. } => Some(error),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MovieError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// A recording of the keypad, frame by frame.
+///
+/// # Example
+/// ```
+/// use rust_chip8::movie::Movie;
+/// use rust_chip8::{Chip8, Quirks};
+///
+/// // SKP V0 (key 0); JP 0x200; LD V1, 1; JP 0x206
+/// let rom = [0xE0, 0x9E, 0x12, 0x00, 0x61, 0x01, 0x12, 0x06];
+///
+/// // Record a run where the key 0 is pressed at the third frame
+/// let mut chip8 = Chip8::with_seed(Quirks::SUPER_CHIP, 42);
+/// chip8.load_rom_bytes(&rom).unwrap();
+/// let mut movie = Movie::new(&chip8, 42, 10);
+/// for frame in 0..5 {
+///     chip8.update_key(0, frame == 2);
+///     movie.record_frame(chip8.get_keypad());
+///     chip8.run_frame(10).unwrap();
+/// }
+///
+/// // Play it back on a fresh machine
+/// let movie = Movie::from_bytes(&movie.to_bytes()).unwrap();
+/// let mut replay = Chip8::new();
+/// replay.load_rom_bytes(&rom).unwrap();
+/// movie.play(&mut replay).unwrap();
+///
+/// assert_eq!(replay.get_registers()[1], 1);
+/// assert_eq!(replay.get_pc(), chip8.get_pc());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    /// SHA-1 hash of the ROM.
+    rom_hash: [u8; 20],
+
+    /// Seed of the random number generator.
+    seed: u64,
+
+    /// Quirks of the run.
+    quirks: Quirks,
+
+    /// Instructions executed per frame.
+    cycles_per_frame: u32,
+
+    /// Keypad of each frame, bit `k` set if the key `k` is held.
+    frames: Vec<u16>,
+}
+
+impl Movie {
+    /// Creates an empty movie for a run of the ROM loaded in `chip8`, with its current quirks.
+    ///
+    /// # Arguments
+    ///
+    /// * `chip8` - The machine about to be recorded, with its ROM loaded.
+    /// * `seed` - The seed its random number generator was seeded with.
+    /// * `cycles_per_frame` - The number of instructions it executes per frame.
+    pub fn new(chip8: &Chip8, seed: u64, cycles_per_frame: usize) -> Self {
+        Self {
+            rom_hash: *chip8.get_rom_hash(),
+            seed,
+            quirks: chip8.get_quirks(),
+            cycles_per_frame: cycles_per_frame as u32,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Returns the SHA-1 hash of the ROM the movie was recorded with.
+    pub fn get_rom_hash(&self) -> &[u8; 20] {
+        &self.rom_hash
+    }
+
+    /// Returns the seed of the random number generator.
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the quirks of the run.
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Returns the number of instructions executed per frame.
+    pub fn get_cycles_per_frame(&self) -> usize {
+        self.cycles_per_frame as usize
+    }
+
+    /// Returns the number of recorded frames.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no frame was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the keypad of the frame `frame`, or `None` past the last frame.
+    pub fn get_frame(&self, frame: usize) -> Option<[bool; 16]> {
+        let bits = *self.frames.get(frame)?;
+
+        Some(std::array::from_fn(|key| bits & 1 << key != 0))
+    }
+
+    /// Appends a frame, with the keypad state used to run it.
+    pub fn record_frame(&mut self, keypad: &[bool; 16]) {
+        let bits = (keypad.iter().enumerate())
+            .filter(|(_, &pressed)| pressed)
+            .fold(0, |bits, (key, _)| bits | 1 << key);
+
+        self.frames.push(bits);
+    }
+
+    /// Prepares `chip8` to play the movie: applies its quirks and seeds the random number generator.
+    ///
+    /// The machine should be fresh, with only the ROM and the fontset loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::RomMismatch` if the loaded ROM is not the one the movie was recorded with.
+    pub fn start(&self, chip8: &mut Chip8) -> Result<(), MovieError> {
+        if chip8.get_rom_hash() != &self.rom_hash {
+            return Err(MovieError::RomMismatch);
+        }
+
+        chip8.set_quirks(self.quirks);
+        chip8.seed_rng(self.seed);
+
+        Ok(())
+    }
+
+    /// Sets the keypad of `chip8` to the state of the frame `frame`.
+    ///
+    /// Returns `false`, leaving the keypad unchanged, past the last frame.
+    pub fn apply_frame(&self, chip8: &mut Chip8, frame: usize) -> bool {
+        let Some(keypad) = self.get_frame(frame) else {
+            return false;
+        };
+
+        for (key, pressed) in keypad.into_iter().enumerate() {
+            chip8.update_key(key as u8, pressed);
+        }
+
+        true
+    }
+
+    /// Starts the movie on `chip8` and runs all its frames.
+    ///
+    /// # Returns
+    ///
+    /// The outcome of the last executed instruction. The playback stops early if the program
+    /// exits or hits a breakpoint.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::RomMismatch` if the loaded ROM is not the one the movie was recorded with,
+    /// or `MovieError::Crash` if the program crashes.
+    pub fn play(&self, chip8: &mut Chip8) -> Result<StepOutcome, MovieError> {
+        self.start(chip8)?;
+
+        let mut outcome = StepOutcome::Executed;
+        for frame in 0..self.len() {
+            self.apply_frame(chip8, frame);
+            outcome = chip8
+                .run_frame(self.get_cycles_per_frame())
+                .map_err(|error| MovieError::Crash { frame, error })?;
+            if outcome != StepOutcome::Executed {
+                break;
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    /// Serializes the movie.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + 2 * self.frames.len());
+
+        bytes.extend_from_slice(MOVIE_MAGIC);
+        bytes.extend_from_slice(&MOVIE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.rom_hash);
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(self.quirks.to_bits());
+        bytes.extend_from_slice(&self.cycles_per_frame.to_le_bytes());
+        bytes.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            bytes.extend_from_slice(&frame.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Deserializes a movie serialized by [`Movie::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `MovieError` if the data is not a valid movie.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MovieError> {
+        if !bytes.starts_with(MOVIE_MAGIC) {
+            return Err(MovieError::BadMagic);
+        }
+        if bytes.len() < HEADER_SIZE {
+            return Err(MovieError::Truncated);
+        }
+
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion(version));
+        }
+
+        let rom_hash = bytes[6..26].try_into().unwrap();
+        let seed = u64::from_le_bytes(bytes[26..34].try_into().unwrap());
+        let quirks = Quirks::from_bits(bytes[34]);
+        let cycles_per_frame = u32::from_le_bytes(bytes[35..39].try_into().unwrap());
+        let count = u32::from_le_bytes(bytes[39..43].try_into().unwrap()) as usize;
+
+        let data = &bytes[HEADER_SIZE..];
+        if data.len() < 2 * count {
+            return Err(MovieError::Truncated);
+        }
+        let frames = data[..2 * count]
+            .chunks_exact(2)
+            .map(|frame| u16::from_le_bytes([frame[0], frame[1]]))
+            .collect();
+
+        Ok(Self {
+            rom_hash,
+            seed,
+            quirks,
+            cycles_per_frame,
+            frames,
+        })
+    }
+
+    /// Reads a movie file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::Io` if the file cannot be read, or another `MovieError` if it is not
+    /// a valid movie.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MovieError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Writes the movie to a file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MovieError::Io` if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MovieError> {
+        Ok(std::fs::write(path, self.to_bytes())?)
+    }
+}
@@ -0,0 +1,202 @@
+//! # Rewind
+//!
+//! This module records the recent history of a [`Chip8`] so that the frontend can step backwards
+//! through time, one frame at a time.
+//!
+//! The history is a bounded ring buffer of save states (see [`Chip8::save_state`]). Only the most
+//! recent state is kept whole: every older state is stored as the difference with the state
+//! recorded right after it. Since a frame changes few bytes of the memory and of the display,
+//! a difference is usually a few dozen bytes instead of the 64 KB of a full state.
+
+use std::collections::VecDeque;
+
+use crate::chip8::{Chip8, StateError};
+
+/// The difference between two consecutive states, turning the newer one into the older one.
+#[derive(Debug, Clone)]
+enum Delta {
+    /// Runs of changed bytes, each encoded as the number of unchanged bytes before the run (`u32`),
+    /// the length of the run (`u16`) and the XOR of the old and new bytes.
+    Xor(Vec<u8>),
+
+    /// The whole older state, used when the state size changed (e.g. the display resolution).
+    Full(Vec<u8>),
+}
+
+impl Delta {
+    /// Computes the difference turning `new` into `old`.
+    fn new(old: &[u8], new: &[u8]) -> Self {
+        if old.len() != new.len() {
+            return Self::Full(old.to_vec());
+        }
+
+        let mut delta = Vec::new();
+        let mut unchanged = 0u32;
+        let mut i = 0;
+        while i < old.len() {
+            if old[i] == new[i] {
+                unchanged += 1;
+                i += 1;
+                continue;
+            }
+
+            let start = i;
+            while i < old.len() && old[i] != new[i] && i - start < u16::MAX as usize {
+                i += 1;
+            }
+
+            delta.extend_from_slice(&unchanged.to_le_bytes());
+            delta.extend_from_slice(&((i - start) as u16).to_le_bytes());
+            delta.extend(old[start..i].iter().zip(&new[start..i]).map(|(a, b)| a ^ b));
+            unchanged = 0;
+        }
+
+        Self::Xor(delta)
+    }
+
+    /// Turns `state` into the older state.
+    fn apply(&self, state: &mut Vec<u8>) {
+        match self {
+            Self::Full(old) => state.clone_from(old),
+            Self::Xor(delta) => {
+                let (mut offset, mut rest) = (0, delta.as_slice());
+                while let [a, b, c, d, e, f, tail @ ..] = rest {
+                    offset += u32::from_le_bytes([*a, *b, *c, *d]) as usize;
+                    let len = u16::from_le_bytes([*e, *f]) as usize;
+
+                    for (byte, xor) in state[offset..offset + len].iter_mut().zip(&tail[..len]) {
+                        *byte ^= xor;
+                    }
+
+                    offset += len;
+                    rest = &tail[len..];
+                }
+            }
+        }
+    }
+
+    /// Returns the size of the difference in bytes.
+    fn size(&self) -> usize {
+        match self {
+            Self::Xor(delta) | Self::Full(delta) => delta.len(),
+        }
+    }
+}
+
+/// A bounded history of machine states.
+///
+/// # Example
+/// ```
+/// use rust_chip8::rewind::Rewind;
+/// use rust_chip8::Chip8;
+///
+/// let mut chip8 = Chip8::new();
+/// chip8.load_rom_bytes(&[0x70, 0x01, 0x12, 0x00]).unwrap(); // ADD V0, 1; JP 0x200
+///
+/// let mut rewind = Rewind::new(60);
+/// for _ in 0..10 {
+///     rewind.record(&chip8);
+///     chip8.step().unwrap();
+///     chip8.step().unwrap();
+/// }
+/// assert_eq!(chip8.get_registers()[0], 10);
+///
+/// // The last recorded state is restored first, then the older ones
+/// assert!(rewind.rewind(&mut chip8).unwrap());
+/// assert_eq!(chip8.get_registers()[0], 9);
+/// assert!(rewind.rewind(&mut chip8).unwrap());
+/// assert_eq!(chip8.get_registers()[0], 8);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Rewind {
+    /// The maximum number of states kept.
+    capacity: usize,
+
+    /// The most recent state.
+    latest: Option<Vec<u8>>,
+
+    /// The differences leading to the older states, the oldest first.
+    deltas: VecDeque<Delta>,
+}
+
+impl Rewind {
+    /// Creates an empty history.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The maximum number of states kept (e.g. 600 for 10 seconds at 60 frames
+    ///   per second). The oldest states are dropped first.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            latest: None,
+            deltas: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records the current state of `chip8`, this should be called once per frame.
+    pub fn record(&mut self, chip8: &Chip8) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let state = chip8.save_state();
+        if let Some(latest) = self.latest.replace(state) {
+            if self.deltas.len() + 1 >= self.capacity {
+                self.deltas.pop_front();
+            }
+            self.deltas
+                .push_back(Delta::new(&latest, self.latest.as_ref().unwrap()));
+        }
+    }
+
+    /// Restores the most recent state into `chip8` and removes it from the history.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the history is empty, `chip8` is then left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `StateError` if the history was recorded with another ROM,
+    /// the history is then left unchanged.
+    pub fn rewind(&mut self, chip8: &mut Chip8) -> Result<bool, StateError> {
+        let Some(mut state) = self.latest.take() else {
+            return Ok(false);
+        };
+
+        if let Err(err) = chip8.load_state(&state) {
+            self.latest = Some(state);
+            return Err(err);
+        }
+
+        if let Some(delta) = self.deltas.pop_back() {
+            delta.apply(&mut state);
+            self.latest = Some(state);
+        }
+
+        Ok(true)
+    }
+
+    /// Returns the number of states in the history.
+    pub fn len(&self) -> usize {
+        self.latest.as_ref().map_or(0, |_| self.deltas.len() + 1)
+    }
+
+    /// Returns `true` if the history is empty.
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_none()
+    }
+
+    /// Returns the memory used by the history in bytes (approximately).
+    pub fn size(&self) -> usize {
+        self.latest.as_ref().map_or(0, Vec::len)
+            + self.deltas.iter().map(Delta::size).sum::<usize>()
+    }
+
+    /// Removes all the states from the history.
+    pub fn clear(&mut self) {
+        self.latest = None;
+        self.deltas.clear();
+    }
+}
@@ -0,0 +1,179 @@
+//! # Screenshots
+//!
+//! This module converts the display of a [`Chip8`] to image formats, without any dependency:
+//! ASCII art, plain PBM and PNG. It is used by the `chip8-headless` runner to check the output
+//! of ROMs in CI.
+
+use crate::chip8::Chip8;
+
+/// The default colors of the 4 pixel values: off, plane 1, plane 2 and both planes (XO-CHIP).
+pub const DEFAULT_PALETTE: [[u8; 3]; 4] =
+    [[0, 0, 0], [255, 255, 255], [200, 60, 45], [255, 255, 255]];
+
+/// The characters used by [`to_ascii`] for the 4 pixel values.
+const ASCII_PIXELS: [char; 4] = ['.', '#', '+', '@'];
+
+/// Returns the display as ASCII art, one line per row.
+///
+/// Off pixels are written `.` and on pixels `#`. With XO-CHIP planes, the pixels only on in the
+/// second plane are written `+` and the pixels on in both planes `@`.
+///
+/// # Example
+/// ```
+/// use rust_chip8::screenshot::to_ascii;
+/// use rust_chip8::Chip8;
+///
+/// let mut chip8 = Chip8::new();
+/// chip8.load_fontset();
+/// chip8.load_rom_bytes(&[0xF0, 0x29, 0xD0, 0x05]).unwrap(); // LD F, V0; DRW V0, V0, 5
+/// chip8.step().unwrap();
+/// chip8.step().unwrap();
+///
+/// let ascii = to_ascii(&chip8);
+/// assert!(ascii.starts_with("####....."));
+/// assert_eq!(ascii.lines().nth(1).unwrap()[..4], *"#..#");
+/// assert_eq!(ascii.lines().count(), 32);
+/// ```
+pub fn to_ascii(chip8: &Chip8) -> String {
+    let (width, _) = chip8.get_display_size();
+
+    chip8
+        .get_display()
+        .chunks(width)
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|&pixel| ASCII_PIXELS[(pixel & 0b11) as usize])
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// Returns the display as a plain (`P1`) PBM image, the on pixels being black.
+///
+/// # Example
+/// ```
+/// use rust_chip8::screenshot::to_pbm;
+/// use rust_chip8::Chip8;
+///
+/// let pbm = to_pbm(&Chip8::new());
+/// assert!(pbm.starts_with("P1\n64 32\n0 0 0"));
+/// ```
+pub fn to_pbm(chip8: &Chip8) -> String {
+    let (width, height) = chip8.get_display_size();
+    let mut pbm = format!("P1\n{width} {height}\n");
+
+    for row in chip8.get_display().chunks(width) {
+        let bits: Vec<&str> = row
+            .iter()
+            .map(|&pixel| if pixel != 0 { "1" } else { "0" })
+            .collect();
+        pbm += &bits.join(" ");
+        pbm.push('\n');
+    }
+
+    pbm
+}
+
+/// Returns the display as a PNG image, one image pixel per CHIP-8 pixel.
+///
+/// # Arguments
+///
+/// * `chip8` - The machine whose display is converted.
+/// * `palette` - The RGB colors of the 4 pixel values (see [`DEFAULT_PALETTE`]).
+///
+/// # Example
+/// ```
+/// use rust_chip8::screenshot::{to_png, DEFAULT_PALETTE};
+/// use rust_chip8::Chip8;
+///
+/// let png = to_png(&Chip8::new(), &DEFAULT_PALETTE);
+/// assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+/// ```
+pub fn to_png(chip8: &Chip8, palette: &[[u8; 3]; 4]) -> Vec<u8> {
+    let (width, height) = chip8.get_display_size();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per pixel, indexed colors, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    // Every row starts with its filter type, 0 (none)
+    let mut pixels = Vec::with_capacity((width + 1) * height);
+    for row in chip8.get_display().chunks(width) {
+        pixels.push(0);
+        pixels.extend(row.iter().map(|pixel| pixel & 0b11));
+    }
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"PLTE", palette.as_flattened());
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&pixels));
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+/// Appends a PNG chunk: its length, type, data and CRC.
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream of uncompressed (stored) deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+
+    let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+    for (i, block) in blocks.iter().enumerate() {
+        let last = i + 1 == blocks.len();
+        let length = block.len() as u16;
+
+        stream.push(last as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+    if blocks.is_empty() {
+        stream.extend_from_slice(&[1, 0x00, 0x00, 0xFF, 0xFF]);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+/// Computes the CRC-32 of `data`, as used by PNG.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+
+    !crc
+}
+
+/// Computes the Adler-32 checksum of `data`, as used by zlib.
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
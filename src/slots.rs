@@ -0,0 +1,83 @@
+//! Save state slots of the emulator.
+//!
+//! This module contains the `SaveSlots` struct, which stores numbered save states of the running
+//! ROM on disk. The slots are bound to the function keys: `F1`-`F8` load a slot and
+//! `Shift` + `F1`-`F8` save to it.
+
+use std::path::PathBuf;
+
+use raylib::prelude::*;
+use rust_chip8::config::hash_to_hex;
+use rust_chip8::Chip8;
+
+/// The function keys bound to the slots 1 to 8.
+pub const SLOT_KEYS: [KeyboardKey; 8] = [
+    KeyboardKey::KEY_F1,
+    KeyboardKey::KEY_F2,
+    KeyboardKey::KEY_F3,
+    KeyboardKey::KEY_F4,
+    KeyboardKey::KEY_F5,
+    KeyboardKey::KEY_F6,
+    KeyboardKey::KEY_F7,
+    KeyboardKey::KEY_F8,
+];
+
+/// The numbered save states of a ROM.
+///
+/// The states are stored as `<ROM SHA-1>.<slot>.state` files, so the slots of different ROMs
+/// can share the same directory.
+#[derive(Debug, Clone)]
+pub struct SaveSlots {
+    /// The directory holding the state files.
+    dir: PathBuf,
+
+    /// The hexadecimal hash of the ROM.
+    rom_hash: String,
+}
+
+impl SaveSlots {
+    /// Creates the save slots of the ROM with the given hash.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory holding the state files, created on the first save.
+    /// * `rom_hash` - The SHA-1 hash of the ROM (see `Chip8::get_rom_hash`).
+    pub fn new(dir: PathBuf, rom_hash: &[u8; 20]) -> Self {
+        Self {
+            dir,
+            rom_hash: hash_to_hex(rom_hash),
+        }
+    }
+
+    /// Returns the path of the state file of `slot`.
+    pub fn path(&self, slot: usize) -> PathBuf {
+        self.dir.join(format!("{}.{slot}.state", self.rom_hash))
+    }
+
+    /// Saves the state of `chip8` to `slot`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the state file cannot be written.
+    pub fn save(&self, slot: usize, chip8: &Chip8) -> Result<(), String> {
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(self.path(slot), chip8.save_state()))
+            .map_err(|err| format!("Failed to save slot {slot}: {err}"))
+    }
+
+    /// Restores the state of `chip8` from `slot`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the slot is empty or its state cannot be restored.
+    pub fn load(&self, slot: usize, chip8: &mut Chip8) -> Result<(), String> {
+        let state = std::fs::read(self.path(slot)).map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => format!("Slot {slot} is empty"),
+            _ => format!("Failed to load slot {slot}: {err}"),
+        })?;
+
+        chip8
+            .load_state(&state)
+            .map_err(|err| format!("Failed to load slot {slot}: {err}"))
+    }
+}
@@ -0,0 +1,294 @@
+//! Conformance tests: test ROMs are run headlessly under every quirk profile, and their final
+//! display is compared with golden hashes stored in `tests/golden/`.
+//!
+//! Two kinds of ROMs are run:
+//! - The [Timendus CHIP-8 test suite](https://github.com/Timendus/chip8-test-suite), which is not
+//!   distributed with the crate yet: its tests are ignored by default. Copy its `.ch8` files to
+//!   `tests/roms/`, record their golden hashes, then run them with
+//!   `cargo test --test conformance -- --include-ignored`. A missing ROM fails its test.
+//! - The Octo programs of `tests/programs/`, assembled by the `assembler` module.
+//!
+//! After an intended change of the output, record the new golden hashes with:
+//! ```sh
+//! CHIP8_BLESS=1 cargo test --test conformance
+//! ```
+//! A mismatch prints the display as ASCII art.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use rust_chip8::assembler::assemble;
+use rust_chip8::screenshot::to_ascii;
+use rust_chip8::{Chip8, Platform};
+use sha1::{Digest, Sha1};
+
+/// The number of instructions per frame, as in the frontend.
+const CYCLES_PER_FRAME: usize = rust_chip8::constants::CYCLES_PER_FRAME;
+
+/// The default maximum number of frames of a run (50 seconds). With the `display_wait` quirk
+/// a single sprite is drawn per frame, so a ROM drawing a full results screen needs hundreds.
+const MAX_FRAMES: u64 = 3000;
+
+/// A ROM to run, and how to run it.
+struct Case<'a> {
+    /// The name of the golden hash file.
+    name: &'a str,
+
+    /// The ROM image.
+    rom: Vec<u8>,
+
+    /// The platforms to run the ROM under.
+    platforms: &'a [Platform],
+
+    /// The maximum number of frames to run, the run stops earlier once the ROM settles.
+    frames: u64,
+
+    /// The keys held during the whole run.
+    keys: &'a [u8],
+
+    /// The value written at `0x1FF` for each platform, used by the Timendus ROMs to skip their menu.
+    menu: fn(Platform) -> Option<u8>,
+}
+
+impl Default for Case<'_> {
+    fn default() -> Self {
+        Self {
+            name: "",
+            rom: Vec::new(),
+            platforms: &Platform::ALL,
+            frames: MAX_FRAMES,
+            keys: &[],
+            menu: |_| None,
+        }
+    }
+}
+
+/// Returns the path of a file of the `tests` directory.
+fn tests_path(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(path)
+}
+
+/// Reads a Timendus ROM from `tests/roms/`.
+///
+/// # Panics
+///
+/// Panics if the ROM is missing.
+fn timendus_rom(file: &str) -> Vec<u8> {
+    let path = tests_path("roms").join(file);
+
+    std::fs::read(&path)
+        .unwrap_or_else(|_| panic!("{} not found (see tests/roms/README.md)", path.display()))
+}
+
+/// Assembles an Octo program of `tests/programs/`.
+fn program(file: &str) -> Vec<u8> {
+    let path = tests_path("programs").join(file);
+    let source = std::fs::read_to_string(&path).expect("Could not read the program");
+
+    assemble(&source)
+        .unwrap_or_else(|err| panic!("{}: {err}", path.display()))
+        .rom
+}
+
+/// Runs a ROM and returns the machine in its final state.
+fn run(case: &Case<'_>, platform: Platform) -> Chip8 {
+    let mut chip8 = Chip8::with_seed(platform.quirks(), 0);
+    chip8.load_fontset();
+    chip8
+        .load_rom_bytes(&case.rom)
+        .expect("Could not load the ROM");
+    if let Some(value) = (case.menu)(platform) {
+        chip8.get_memory_mut()[0x1FF] = value;
+    }
+
+    for &key in case.keys {
+        chip8.update_key(key, true);
+    }
+    for frame in 0..case.frames {
+        if is_settled(&chip8) {
+            break;
+        }
+        if let Err(err) = chip8.run_frame(CYCLES_PER_FRAME) {
+            panic!("{} ({platform}) crashed at frame {frame}: {err}", case.name);
+        }
+    }
+
+    chip8
+}
+
+/// Returns `true` if the ROM has finished: it is halted, or stuck on a jump to itself
+/// (`loop again` in Octo), as the test ROMs are once their results are drawn.
+fn is_settled(chip8: &Chip8) -> bool {
+    let pc = chip8.get_pc();
+    let memory = chip8.get_memory();
+    let opcode = match memory.get(pc as usize..pc as usize + 2) {
+        Some(&[high, low]) => u16::from_be_bytes([high, low]),
+        _ => return false,
+    };
+
+    chip8.is_halted() || (pc < 0x1000 && opcode == 0x1000 | pc)
+}
+
+/// Returns the hexadecimal SHA-1 hash of the display and its size.
+fn display_hash(chip8: &Chip8) -> String {
+    let (width, height) = chip8.get_display_size();
+    let mut hasher = Sha1::new();
+    hasher.update(format!("{width}x{height}"));
+    hasher.update(chip8.get_display());
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Runs a case under all its platforms and compares the displays with the golden hashes,
+/// or records them if `CHIP8_BLESS` is set.
+fn check(case: Case<'_>) {
+    let golden_path = tests_path("golden").join(format!("{}.txt", case.name));
+    let bless = std::env::var_os("CHIP8_BLESS").is_some();
+
+    let mut actual = BTreeMap::new();
+    let mut displays = BTreeMap::new();
+    for &platform in case.platforms {
+        let chip8 = run(&case, platform);
+        actual.insert(platform.name(), display_hash(&chip8));
+        displays.insert(platform.name(), to_ascii(&chip8));
+    }
+
+    if bless {
+        let content: String = actual
+            .iter()
+            .map(|(platform, hash)| format!("{platform} {hash}\n"))
+            .collect();
+        std::fs::write(&golden_path, content).expect("Could not write the golden hashes");
+        return;
+    }
+
+    let golden = std::fs::read_to_string(&golden_path).unwrap_or_else(|_| {
+        panic!(
+            "No golden hashes in {}, record them with CHIP8_BLESS=1",
+            golden_path.display()
+        )
+    });
+    let golden: BTreeMap<&str, &str> = golden
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .collect();
+
+    let mut failures = Vec::new();
+    for (platform, hash) in &actual {
+        if golden.get(platform) != Some(&hash.as_str()) {
+            failures.push(format!(
+                "{} ({platform}): display hash {hash}, expected {}\n{}",
+                case.name,
+                golden.get(platform).unwrap_or(&"none"),
+                displays[platform]
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn chip8_logo() {
+    check(Case {
+        name: "1-chip8-logo",
+        rom: timendus_rom("1-chip8-logo.ch8"),
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn ibm_logo() {
+    check(Case {
+        name: "2-ibm-logo",
+        rom: timendus_rom("2-ibm-logo.ch8"),
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn corax_plus() {
+    check(Case {
+        name: "3-corax+",
+        rom: timendus_rom("3-corax+.ch8"),
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn flags() {
+    check(Case {
+        name: "4-flags",
+        rom: timendus_rom("4-flags.ch8"),
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn quirks() {
+    check(Case {
+        name: "5-quirks",
+        rom: timendus_rom("5-quirks.ch8"),
+        platforms: &[Platform::CosmacVip, Platform::SuperChip, Platform::XoChip],
+        menu: |platform| match platform {
+            Platform::CosmacVip => Some(1),
+            Platform::SuperChip => Some(2),
+            Platform::XoChip => Some(3),
+            Platform::Chip48 => None,
+        },
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn keypad() {
+    // The "EX9E" test, with the key 5 held
+    check(Case {
+        name: "6-keypad",
+        rom: timendus_rom("6-keypad.ch8"),
+        keys: &[0x5],
+        menu: |_| Some(1),
+        ..Case::default()
+    });
+}
+
+#[test]
+#[ignore = "needs the Timendus ROMs in tests/roms (see tests/roms/README.md)"]
+fn beep() {
+    // The ROM beeps while the key B is held
+    check(Case {
+        name: "7-beep",
+        rom: timendus_rom("7-beep.ch8"),
+        keys: &[0xB],
+        ..Case::default()
+    });
+}
+
+#[test]
+fn program_flags() {
+    check(Case {
+        name: "program-flags",
+        rom: program("flags.8o"),
+        ..Case::default()
+    });
+}
+
+#[test]
+fn program_quirks() {
+    check(Case {
+        name: "program-quirks",
+        rom: program("quirks.8o"),
+        ..Case::default()
+    });
+}